@@ -1,14 +1,25 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token, Address, Env, IntoVal, Symbol, Val,
+    contract, contractimpl, contracttype, token, xdr::ToXdr, Address, Bytes, BytesN, Env, IntoVal,
+    Symbol, Vec,
 };
 
+// Most bids a batch auction's book holds; keeps the book within one storage entry.
+// A full book only takes bids that beat its lowest, which is evicted and refunded.
+const MAX_BATCH_BIDS: u32 = 500;
+
+// Batch auctions live in persistent storage, extended whenever they are written
+const BATCH_TTL_THRESHOLD: u32 = 17_280; // ~1 day
+const BATCH_TTL_EXTEND: u32 = 518_400; // ~30 days
+
 // 1. DATA STRUCTURES
 #[contracttype]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum AuctionType {
     English = 1,
     Dutch = 2,
+    SealedBid = 3,
+    Batch = 4, // Uniform-price; created with create_batch_auction
 }
 
 // NEW: Grouping settings to avoid the 10-parameter limit
@@ -21,6 +32,16 @@ pub struct AuctionSettings {
     pub reserve_price: i128,
     pub buy_now_price: i128,
     pub min_bid_increment: i128,
+    // English only: a bid within `snipe_window` seconds of the end pushes the
+    // end out to `snipe_extension` seconds after the bid
+    pub snipe_window: u64,
+    pub snipe_extension: u64,
+    // Sealed-bid only: reveals are accepted for this long after end_time
+    pub reveal_duration: u64,
+    // Sealed-bid only: unrevealed deposits go to the seller instead of back to the bidder
+    pub forfeit_unrevealed: bool,
+    pub creator: Option<Address>, // For royalty payments
+    pub royalty_bps: u32,         // Royalty in basis points (10000 = 100%)
 }
 
 #[contracttype]
@@ -37,12 +58,90 @@ pub struct AuctionInfo {
     pub highest_bidder: Option<Address>,
     pub current_bid: i128,
     pub settled: bool,
+    pub cancelled: bool,
+    pub fee_bps: u32, // Marketplace fee when the auction was created
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuctionConfig {
+    pub admin: Address,
+    pub fee_recipient: Address,
+    pub fee_bps: u32, // Marketplace fee in basis points (10000 = 100%)
+}
+
+/// An entry in an English auction's bid log
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BidRecord {
+    pub bidder: Address,
+    pub amount: i128,
+    pub timestamp: u64,
+}
+
+/// A sealed bid. `commitment` is sha256(amount as 16 big-endian bytes || salt);
+/// the deposit must cover the revealed amount.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SealedBid {
+    pub commitment: BytesN<32>,
+    pub deposit: i128,
+    pub revealed: bool,
+    pub amount: i128,
+    pub withdrawn: bool,
+}
+
+/// Settings for a uniform-price batch auction of `quantity` units
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchSettings {
+    pub quantity: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub min_price: i128, // Per unit
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchAuction {
+    pub batch_id: u64,
+    pub seller: Address,
+    pub item_token: Address,
+    pub payment_token: Address,
+    pub settings: BatchSettings,
+    pub bid_count: u32,
+    pub clearing_price: i128, // Set at settlement
+    pub sold: i128,
+    pub settled: bool,
+    pub fee_bps: u32, // Marketplace fee when the auction was created
+}
+
+/// A bid in a batch auction's book. The full `quantity * max_price` is
+/// escrowed; `filled` is set at settlement.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchBid {
+    pub bidder: Address,
+    pub quantity: i128,
+    pub max_price: i128,
+    pub filled: i128,
+    pub claimed: bool,
 }
 
 #[contracttype]
 pub enum DataKey {
+    Config, // AuctionConfig
     Auction(u64),
     AuctionCount,
+    Refund(u64, Address), // Outbid funds awaiting withdraw_refund
+    Bid(u64, u32),        // Bid log entry by (auction_id, index)
+    BidCount(u64),
+    SealedBid(u64, Address),
+    SealedBidders(u64),
+    Batch(u64),
+    BatchCount,
+    BatchBid(u64, u32), // Bid book entry by (batch_id, bid_id)
+    BatchBook(u64),     // Vec<(i128, u32)> - (max_price, bid_id), best price first
 }
 
 // 2. CONTRACT LOGIC
@@ -51,11 +150,47 @@ pub struct AuctionContract;
 
 #[contractimpl]
 impl AuctionContract {
-    /// Initialize the contract
-    pub fn init(env: Env, _admin: Address) {
-        if !env.storage().instance().has(&DataKey::AuctionCount) {
-            env.storage().instance().set(&DataKey::AuctionCount, &0u64);
+    /// Initialize the contract. Fees start at zero and are paid to the admin.
+    pub fn init(env: Env, admin: Address) {
+        if env.storage().instance().has(&DataKey::Config) {
+            panic!("Already initialized");
         }
+        admin.require_auth();
+
+        let config = AuctionConfig {
+            admin: admin.clone(),
+            fee_recipient: admin,
+            fee_bps: 0,
+        };
+        env.storage().instance().set(&DataKey::Config, &config);
+        env.storage().instance().set(&DataKey::AuctionCount, &0u64);
+    }
+
+    /// Update the marketplace fee settings (admin only). Auctions keep the fee
+    /// they were created with.
+    pub fn update_config(env: Env, fee_recipient: Option<Address>, fee_bps: Option<u32>) {
+        let mut config = Self::get_config(env.clone());
+        config.admin.require_auth();
+
+        if let Some(recipient) = fee_recipient {
+            config.fee_recipient = recipient;
+        }
+
+        if let Some(bps) = fee_bps {
+            if bps > 10000 {
+                panic!("Fee cannot exceed 100%");
+            }
+            config.fee_bps = bps;
+        }
+
+        env.storage().instance().set(&DataKey::Config, &config);
+    }
+
+    pub fn get_config(env: Env) -> AuctionConfig {
+        env.storage()
+            .instance()
+            .get(&DataKey::Config)
+            .expect("Not initialized")
     }
 
     /// Creates a new auction
@@ -73,6 +208,24 @@ impl AuctionContract {
         if settings.end_time <= settings.start_time {
             panic!("End time must be after start time");
         }
+        if auction_type == AuctionType::Batch {
+            panic!("Use create_batch_auction for batch auctions");
+        }
+        if auction_type == AuctionType::SealedBid && settings.reveal_duration == 0 {
+            panic!("Sealed-bid auctions need a reveal window");
+        }
+        let config = Self::get_config(env.clone());
+        if settings.royalty_bps + config.fee_bps > 10000 {
+            panic!("Fee and royalty cannot exceed 100%");
+        }
+
+        // Take custody of the NFT until the auction settles or is cancelled
+        let transfer_args = (seller.clone(), env.current_contract_address(), nft_id);
+        env.invoke_contract::<()>(
+            &nft_contract,
+            &Symbol::new(&env, "transfer"),
+            transfer_args.into_val(&env),
+        );
 
         // Generate ID
         let mut id: u64 = env
@@ -95,6 +248,8 @@ impl AuctionContract {
             highest_bidder: None,
             current_bid: 0,
             settled: false,
+            cancelled: false,
+            fee_bps: config.fee_bps,
         };
 
         // Save
@@ -144,13 +299,13 @@ impl AuctionContract {
                 panic!("Bid too low: must meet min increment");
             }
 
-            // Refund the previous bidder!
-            // We send the money held in the contract back to them.
-            token_client.transfer(
-                &env.current_contract_address(),
-                &previous_bidder,
-                &auction.current_bid,
-            );
+            // Credit the previous bidder; they pull it with withdraw_refund
+            // so a failing transfer can't block new bids.
+            let refund_key = DataKey::Refund(auction_id, previous_bidder);
+            let balance: i128 = env.storage().persistent().get(&refund_key).unwrap_or(0);
+            env.storage()
+                .persistent()
+                .set(&refund_key, &(balance + auction.current_bid));
         } else {
             // CASE B: First bid of the auction
             if bid_amount < auction.settings.starting_price {
@@ -163,13 +318,33 @@ impl AuctionContract {
         token_client.transfer(&bidder, &env.current_contract_address(), &bid_amount);
 
         // 6. Anti-Sniping (Extension)
-        // If bid is placed in the last 5 minutes (300 seconds), extend end time by 5 mins.
+        // If bid is placed inside the snipe window, push the end time out.
         let time_remaining = auction.settings.end_time - current_time;
-        if time_remaining < 300 {
-            auction.settings.end_time = current_time + 300;
+        if time_remaining < auction.settings.snipe_window
+            && current_time + auction.settings.snipe_extension > auction.settings.end_time
+        {
+            auction.settings.end_time = current_time + auction.settings.snipe_extension;
         }
 
-        // 7. Update State & Save
+        // 7. Log the bid
+        let bid_count: u32 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::BidCount(auction_id))
+            .unwrap_or(0);
+        let record = BidRecord {
+            bidder: bidder.clone(),
+            amount: bid_amount,
+            timestamp: current_time,
+        };
+        env.storage()
+            .persistent()
+            .set(&DataKey::Bid(auction_id, bid_count), &record);
+        env.storage()
+            .persistent()
+            .set(&DataKey::BidCount(auction_id), &(bid_count + 1));
+
+        // 8. Update State & Save
         auction.highest_bidder = Some(bidder);
         auction.current_bid = bid_amount;
 
@@ -178,6 +353,217 @@ impl AuctionContract {
             .set(&DataKey::Auction(auction_id), &auction);
     }
 
+    /// Withdraw everything credited to `bidder` after being outbid
+    pub fn withdraw_refund(env: Env, bidder: Address, auction_id: u64) -> i128 {
+        bidder.require_auth();
+
+        let auction = Self::load_auction(&env, auction_id);
+        let refund_key = DataKey::Refund(auction_id, bidder.clone());
+        let amount: i128 = env.storage().persistent().get(&refund_key).unwrap_or(0);
+        if amount == 0 {
+            panic!("Nothing to withdraw");
+        }
+        env.storage().persistent().remove(&refund_key);
+
+        let token_client = token::Client::new(&env, &auction.payment_token);
+        token_client.transfer(&env.current_contract_address(), &bidder, &amount);
+        amount
+    }
+
+    pub fn get_refund(env: Env, auction_id: u64, bidder: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Refund(auction_id, bidder))
+            .unwrap_or(0)
+    }
+
+    pub fn get_bid_count(env: Env, auction_id: u64) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::BidCount(auction_id))
+            .unwrap_or(0)
+    }
+
+    /// Bid log for an English auction, oldest first
+    pub fn get_bids(env: Env, auction_id: u64, offset: u32, limit: u32) -> Vec<BidRecord> {
+        let count = Self::get_bid_count(env.clone(), auction_id);
+        let end = offset.saturating_add(limit).min(count);
+
+        let mut bids = Vec::new(&env);
+        for i in offset..end {
+            bids.push_back(
+                env.storage()
+                    .persistent()
+                    .get(&DataKey::Bid(auction_id, i))
+                    .unwrap(),
+            );
+        }
+        bids
+    }
+
+    /// Commit to a hidden bid on a Sealed-Bid Auction, escrowing `deposit`.
+    /// Depositing more than the bid keeps the amount private.
+    pub fn commit_bid(
+        env: Env,
+        bidder: Address,
+        auction_id: u64,
+        commitment: BytesN<32>,
+        deposit: i128,
+    ) {
+        bidder.require_auth();
+
+        let auction = Self::load_auction(&env, auction_id);
+        if auction.auction_type != AuctionType::SealedBid {
+            panic!("This function is for sealed-bid auctions only");
+        }
+        if auction.settled {
+            panic!("Auction is already settled");
+        }
+
+        let current_time = env.ledger().timestamp();
+        if current_time < auction.settings.start_time {
+            panic!("Auction has not started yet");
+        }
+        if current_time > auction.settings.end_time {
+            panic!("Bidding has ended");
+        }
+        if deposit < auction.settings.starting_price {
+            panic!("Deposit below starting price");
+        }
+
+        let key = DataKey::SealedBid(auction_id, bidder.clone());
+        if env.storage().persistent().has(&key) {
+            panic!("Bid already committed");
+        }
+
+        let token_client = token::Client::new(&env, &auction.payment_token);
+        token_client.transfer(&bidder, &env.current_contract_address(), &deposit);
+
+        let bid = SealedBid {
+            commitment,
+            deposit,
+            revealed: false,
+            amount: 0,
+            withdrawn: false,
+        };
+        env.storage().persistent().set(&key, &bid);
+
+        let mut bidders: Vec<Address> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::SealedBidders(auction_id))
+            .unwrap_or(Vec::new(&env));
+        bidders.push_back(bidder);
+        env.storage()
+            .persistent()
+            .set(&DataKey::SealedBidders(auction_id), &bidders);
+    }
+
+    /// Reveal a sealed bid during the reveal window
+    pub fn reveal_bid(env: Env, bidder: Address, auction_id: u64, amount: i128, salt: BytesN<32>) {
+        bidder.require_auth();
+
+        let auction = Self::load_auction(&env, auction_id);
+        if auction.auction_type != AuctionType::SealedBid {
+            panic!("This function is for sealed-bid auctions only");
+        }
+
+        let current_time = env.ledger().timestamp();
+        if current_time <= auction.settings.end_time {
+            panic!("Reveal window has not opened");
+        }
+        if current_time > auction.settings.end_time + auction.settings.reveal_duration {
+            panic!("Reveal window has closed");
+        }
+
+        let key = DataKey::SealedBid(auction_id, bidder.clone());
+        let mut bid: SealedBid = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .unwrap_or_else(|| panic!("No committed bid"));
+
+        if bid.revealed {
+            panic!("Bid already revealed");
+        }
+        if Self::bid_commitment(env.clone(), auction_id, bidder.clone(), amount, salt)
+            != bid.commitment
+        {
+            panic!("Reveal does not match commitment");
+        }
+        if amount > bid.deposit {
+            panic!("Bid exceeds deposit");
+        }
+
+        bid.revealed = true;
+        bid.amount = amount;
+        env.storage().persistent().set(&key, &bid);
+    }
+
+    /// Hash a sealed bid is committed as: sha256(auction_id as 8 big-endian
+    /// bytes || bidder XDR || amount as 16 big-endian bytes || salt). Binding
+    /// the auction and bidder keeps others from replaying a copied commitment.
+    pub fn bid_commitment(
+        env: Env,
+        auction_id: u64,
+        bidder: Address,
+        amount: i128,
+        salt: BytesN<32>,
+    ) -> BytesN<32> {
+        let mut data = Bytes::from_array(&env, &auction_id.to_be_bytes());
+        data.append(&bidder.to_xdr(&env));
+        data.extend_from_slice(&amount.to_be_bytes());
+        data.extend_from_slice(&salt.to_array());
+        env.crypto().sha256(&data).into()
+    }
+
+    /// Pull a sealed-bid deposit back after settlement: the full deposit for
+    /// losing bids, the deposit minus the price paid for the winner, and
+    /// unrevealed deposits unless the auction forfeits them.
+    pub fn withdraw_deposit(env: Env, bidder: Address, auction_id: u64) -> i128 {
+        bidder.require_auth();
+
+        let auction = Self::load_auction(&env, auction_id);
+        if !auction.settled {
+            panic!("Auction is not settled");
+        }
+
+        let key = DataKey::SealedBid(auction_id, bidder.clone());
+        let mut bid: SealedBid = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .unwrap_or_else(|| panic!("No committed bid"));
+
+        if bid.withdrawn {
+            panic!("Deposit already withdrawn");
+        }
+
+        let amount = if !bid.revealed && auction.settings.forfeit_unrevealed {
+            0
+        } else if auction.highest_bidder == Some(bidder.clone()) {
+            bid.deposit - auction.current_bid
+        } else {
+            bid.deposit
+        };
+        if amount == 0 {
+            panic!("Nothing to withdraw");
+        }
+
+        bid.withdrawn = true;
+        env.storage().persistent().set(&key, &bid);
+
+        let token_client = token::Client::new(&env, &auction.payment_token);
+        token_client.transfer(&env.current_contract_address(), &bidder, &amount);
+        amount
+    }
+
+    pub fn get_sealed_bid(env: Env, auction_id: u64, bidder: Address) -> Option<SealedBid> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::SealedBid(auction_id, bidder))
+    }
+
     /// Helper: Calculate the current price for a Dutch Auction
     /// Formula: StartPrice - (DecayPerSecond * SecondsElapsed)
     fn calculate_dutch_price(settings: &AuctionSettings, current_time: u64) -> i128 {
@@ -235,9 +621,10 @@ impl AuctionContract {
         token_client.transfer(&buyer, &env.current_contract_address(), &current_price);
 
         // 5. End the Auction Immediately
-        auction.highest_bidder = Some(buyer);
+        auction.highest_bidder = Some(buyer.clone());
         auction.current_bid = current_price;
         auction.settled = true; // Dutch auctions end instantly
+        Self::pay_out(&env, &auction, &buyer);
 
         env.storage()
             .instance()
@@ -256,37 +643,58 @@ impl AuctionContract {
             panic!("Auction is already settled");
         }
 
-        // For English auctions, ensure time has passed
-        if auction.auction_type == AuctionType::English {
-            if env.ledger().timestamp() < auction.settings.end_time {
-                panic!("Auction is still ongoing");
+        // For English and unsold Dutch auctions, ensure time has passed
+        if auction.auction_type != AuctionType::SealedBid
+            && env.ledger().timestamp() < auction.settings.end_time
+        {
+            panic!("Auction is still ongoing");
+        }
+
+        if auction.auction_type == AuctionType::SealedBid {
+            if env.ledger().timestamp()
+                <= auction.settings.end_time + auction.settings.reveal_duration
+            {
+                panic!("Reveal window is still open");
             }
+            Self::resolve_sealed_bids(&env, &mut auction);
         }
 
-        // If there is a winner...
-        if let Some(winner) = auction.highest_bidder.clone() {
-            // 1. Pay the Seller
-            let token_client = token::Client::new(&env, &auction.payment_token);
-            token_client.transfer(
-                &env.current_contract_address(),
-                &auction.seller,
-                &auction.current_bid,
-            );
+        // Pay out and hand over the NFT, or return it to an unsold seller
+        match auction.highest_bidder.clone() {
+            Some(winner) => Self::pay_out(&env, &auction, &winner),
+            None => Self::release_nft(&env, &auction, &auction.seller),
+        }
+
+        // Mark as settled so it can't be processed again
+        auction.settled = true;
+        env.storage()
+            .instance()
+            .set(&DataKey::Auction(auction_id), &auction);
+    }
 
-            // 2. Transfer the NFT
-            // We invoke the NFT contract's "transfer" function dynamically.
-            // Args: (from, to, token_id)
-            let transfer_args = (auction.seller.clone(), winner, auction.nft_id);
+    /// Cancel an auction that has no bids yet, returning the NFT to the seller
+    pub fn cancel_auction(env: Env, auction_id: u64) {
+        let mut auction = Self::load_auction(&env, auction_id);
+        auction.seller.require_auth();
 
-            env.invoke_contract::<()>(
-                &auction.nft_contract,
-                &Symbol::new(&env, "transfer"),
-                transfer_args.into_val(&env),
-            );
+        if auction.settled {
+            panic!("Auction is already settled");
+        }
+        let has_bids = match auction.auction_type {
+            AuctionType::SealedBid => env
+                .storage()
+                .persistent()
+                .has(&DataKey::SealedBidders(auction_id)),
+            _ => auction.highest_bidder.is_some(),
+        };
+        if has_bids {
+            panic!("Auction already has bids");
         }
 
-        // Mark as settled so it can't be processed again
+        Self::release_nft(&env, &auction, &auction.seller);
+
         auction.settled = true;
+        auction.cancelled = true;
         env.storage()
             .instance()
             .set(&DataKey::Auction(auction_id), &auction);
@@ -296,6 +704,397 @@ impl AuctionContract {
     pub fn get_auction(env: Env, auction_id: u64) -> Option<AuctionInfo> {
         env.storage().instance().get(&DataKey::Auction(auction_id))
     }
+
+    /// Creates a uniform-price batch auction, escrowing `settings.quantity`
+    /// units of `item_token` from the seller
+    pub fn create_batch_auction(
+        env: Env,
+        seller: Address,
+        item_token: Address,
+        payment_token: Address,
+        settings: BatchSettings,
+    ) -> u64 {
+        seller.require_auth();
+        let config = Self::get_config(env.clone());
+
+        if settings.end_time <= settings.start_time {
+            panic!("End time must be after start time");
+        }
+        if settings.quantity <= 0 {
+            panic!("Quantity must be positive");
+        }
+        if settings.min_price <= 0 {
+            panic!("Minimum price must be positive");
+        }
+
+        let item_client = token::Client::new(&env, &item_token);
+        item_client.transfer(&seller, &env.current_contract_address(), &settings.quantity);
+
+        let mut id: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::BatchCount)
+            .unwrap_or(0);
+        id += 1;
+        env.storage().instance().set(&DataKey::BatchCount, &id);
+
+        let batch = BatchAuction {
+            batch_id: id,
+            seller,
+            item_token,
+            payment_token,
+            settings,
+            bid_count: 0,
+            clearing_price: 0,
+            sold: 0,
+            settled: false,
+            fee_bps: config.fee_bps,
+        };
+        Self::save_batch(&env, &batch);
+
+        id
+    }
+
+    /// Bid for up to `quantity` units at no more than `max_price` each,
+    /// escrowing the maximum cost. Returns the bid id.
+    pub fn place_batch_bid(
+        env: Env,
+        bidder: Address,
+        batch_id: u64,
+        quantity: i128,
+        max_price: i128,
+    ) -> u32 {
+        bidder.require_auth();
+
+        let mut batch = Self::load_batch(&env, batch_id);
+        if batch.settled {
+            panic!("Auction is already settled");
+        }
+
+        let current_time = env.ledger().timestamp();
+        if current_time < batch.settings.start_time {
+            panic!("Auction has not started yet");
+        }
+        if current_time > batch.settings.end_time {
+            panic!("Auction has ended");
+        }
+        if quantity <= 0 || quantity > batch.settings.quantity {
+            panic!("Invalid quantity");
+        }
+        if max_price < batch.settings.min_price {
+            panic!("Bid below minimum price");
+        }
+
+        let mut book: Vec<(i128, u32)> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::BatchBook(batch_id))
+            .unwrap_or(Vec::new(&env));
+        if book.len() >= MAX_BATCH_BIDS {
+            // The last entry is the lowest price, and the latest bid among ties
+            let (lowest_price, lowest_id) = book.last().unwrap();
+            if max_price <= lowest_price {
+                panic!("Bid does not beat the lowest bid in a full book");
+            }
+            book.pop_back();
+            Self::evict_batch_bid(&env, &batch, lowest_id);
+        }
+
+        let token_client = token::Client::new(&env, &batch.payment_token);
+        token_client.transfer(
+            &bidder,
+            &env.current_contract_address(),
+            &(quantity * max_price),
+        );
+
+        let bid_id = batch.bid_count;
+        let bid = BatchBid {
+            bidder,
+            quantity,
+            max_price,
+            filled: 0,
+            claimed: false,
+        };
+        env.storage()
+            .persistent()
+            .set(&DataKey::BatchBid(batch_id, bid_id), &bid);
+
+        // Insert after every bid at or above this price, keeping ties in bid order
+        let (mut low, mut high) = (0, book.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if book.get(mid).unwrap().0 >= max_price {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        book.insert(low, (max_price, bid_id));
+        env.storage()
+            .persistent()
+            .set(&DataKey::BatchBook(batch_id), &book);
+
+        batch.bid_count += 1;
+        Self::save_batch(&env, &batch);
+
+        bid_id
+    }
+
+    /// Close the bid book and fix the clearing price. Bids fill from the
+    /// highest price down; the bid that exhausts supply is the marginal one
+    /// and may fill partially, with earlier bids winning ties at its price.
+    /// Every winner pays the marginal bid's price, or `min_price` if demand
+    /// falls short of supply. Proceeds (less the marketplace fee) and unsold
+    /// units go back to the seller.
+    pub fn settle_batch(env: Env, batch_id: u64) {
+        let mut batch = Self::load_batch(&env, batch_id);
+        if batch.settled {
+            panic!("Auction is already settled");
+        }
+        if env.ledger().timestamp() <= batch.settings.end_time {
+            panic!("Auction is still ongoing");
+        }
+
+        // The book is kept sorted as bids arrive, so only filled bids are read
+        let book: Vec<(i128, u32)> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::BatchBook(batch_id))
+            .unwrap_or(Vec::new(&env));
+
+        let mut remaining = batch.settings.quantity;
+        let mut clearing_price = batch.settings.min_price;
+        for (_, bid_id) in book.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let mut bid: BatchBid = env
+                .storage()
+                .persistent()
+                .get(&DataKey::BatchBid(batch_id, bid_id))
+                .unwrap();
+            bid.filled = bid.quantity.min(remaining);
+            remaining -= bid.filled;
+            if remaining == 0 {
+                clearing_price = bid.max_price;
+            }
+            env.storage()
+                .persistent()
+                .set(&DataKey::BatchBid(batch_id, bid_id), &bid);
+        }
+
+        batch.clearing_price = clearing_price;
+        batch.sold = batch.settings.quantity - remaining;
+        batch.settled = true;
+        Self::save_batch(&env, &batch);
+
+        let contract = env.current_contract_address();
+        let proceeds = batch.sold * clearing_price;
+        if proceeds > 0 {
+            let config = Self::get_config(env.clone());
+            let fee_amount = proceeds * batch.fee_bps as i128 / 10000;
+            let token_client = token::Client::new(&env, &batch.payment_token);
+            token_client.transfer(&contract, &batch.seller, &(proceeds - fee_amount));
+            if fee_amount > 0 {
+                token_client.transfer(&contract, &config.fee_recipient, &fee_amount);
+            }
+        }
+        if remaining > 0 {
+            let item_client = token::Client::new(&env, &batch.item_token);
+            item_client.transfer(&contract, &batch.seller, &remaining);
+        }
+    }
+
+    /// After settlement, deliver a bid's filled units and refund the rest of
+    /// its escrow. Returns (units received, payment refunded).
+    pub fn claim_batch_bid(env: Env, batch_id: u64, bid_id: u32) -> (i128, i128) {
+        let batch = Self::load_batch(&env, batch_id);
+        if !batch.settled {
+            panic!("Auction is not settled");
+        }
+
+        let key = DataKey::BatchBid(batch_id, bid_id);
+        let mut bid: BatchBid = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .unwrap_or_else(|| panic!("Bid not found"));
+        bid.bidder.require_auth();
+
+        if bid.claimed {
+            panic!("Bid already claimed");
+        }
+        bid.claimed = true;
+        env.storage().persistent().set(&key, &bid);
+
+        let contract = env.current_contract_address();
+        if bid.filled > 0 {
+            let item_client = token::Client::new(&env, &batch.item_token);
+            item_client.transfer(&contract, &bid.bidder, &bid.filled);
+        }
+
+        let refund = bid.quantity * bid.max_price - bid.filled * batch.clearing_price;
+        if refund > 0 {
+            let token_client = token::Client::new(&env, &batch.payment_token);
+            token_client.transfer(&contract, &bid.bidder, &refund);
+        }
+
+        (bid.filled, refund)
+    }
+
+    pub fn get_batch(env: Env, batch_id: u64) -> Option<BatchAuction> {
+        env.storage().persistent().get(&DataKey::Batch(batch_id))
+    }
+
+    pub fn get_batch_bid(env: Env, batch_id: u64, bid_id: u32) -> Option<BatchBid> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::BatchBid(batch_id, bid_id))
+    }
+}
+
+impl AuctionContract {
+    /// Splits the winning price between the marketplace fee, the creator
+    /// royalty and the seller, then sends the escrowed NFT to the winner.
+    fn pay_out(env: &Env, auction: &AuctionInfo, winner: &Address) {
+        let config = Self::get_config(env.clone());
+        let price = auction.current_bid;
+        let fee_amount = price * auction.fee_bps as i128 / 10000;
+        let royalty_amount = match auction.settings.creator {
+            Some(_) => price * auction.settings.royalty_bps as i128 / 10000,
+            None => 0,
+        };
+        let seller_amount = price - fee_amount - royalty_amount;
+
+        let token_client = token::Client::new(env, &auction.payment_token);
+        let contract = env.current_contract_address();
+
+        // 1. Pay the Seller (after fees and royalties)
+        token_client.transfer(&contract, &auction.seller, &seller_amount);
+
+        // 2. Pay marketplace fee
+        if fee_amount > 0 {
+            token_client.transfer(&contract, &config.fee_recipient, &fee_amount);
+        }
+
+        // 3. Pay royalty to creator
+        if royalty_amount > 0 {
+            if let Some(creator) = &auction.settings.creator {
+                token_client.transfer(&contract, creator, &royalty_amount);
+            }
+        }
+
+        // 4. Transfer the NFT
+        Self::release_nft(env, auction, winner);
+    }
+
+    /// Sends the escrowed NFT out of the contract.
+    /// Invokes the NFT contract's `transfer(from, to, token_id)` dynamically.
+    fn release_nft(env: &Env, auction: &AuctionInfo, to: &Address) {
+        let transfer_args = (env.current_contract_address(), to.clone(), auction.nft_id);
+        env.invoke_contract::<()>(
+            &auction.nft_contract,
+            &Symbol::new(env, "transfer"),
+            transfer_args.into_val(env),
+        );
+    }
+
+    fn load_batch(env: &Env, batch_id: u64) -> BatchAuction {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Batch(batch_id))
+            .unwrap_or_else(|| panic!("Auction not found"))
+    }
+
+    fn save_batch(env: &Env, batch: &BatchAuction) {
+        let key = DataKey::Batch(batch.batch_id);
+        env.storage().persistent().set(&key, batch);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, BATCH_TTL_THRESHOLD, BATCH_TTL_EXTEND);
+    }
+
+    /// Drops a bid pushed out of a full book and refunds its whole escrow
+    fn evict_batch_bid(env: &Env, batch: &BatchAuction, bid_id: u32) {
+        let key = DataKey::BatchBid(batch.batch_id, bid_id);
+        let bid: BatchBid = env.storage().persistent().get(&key).unwrap();
+        env.storage().persistent().remove(&key);
+
+        let token_client = token::Client::new(env, &batch.payment_token);
+        token_client.transfer(
+            &env.current_contract_address(),
+            &bid.bidder,
+            &(bid.quantity * bid.max_price),
+        );
+    }
+
+    fn load_auction(env: &Env, auction_id: u64) -> AuctionInfo {
+        env.storage()
+            .instance()
+            .get(&DataKey::Auction(auction_id))
+            .unwrap_or_else(|| panic!("Auction not found"))
+    }
+
+    /// Vickrey resolution: the highest revealed bid at or above the reserve
+    /// wins and pays the second-highest revealed bid (or the starting price if
+    /// it was the only one). Forfeited deposits are paid to the seller here;
+    /// everything else is left for `withdraw_deposit`.
+    fn resolve_sealed_bids(env: &Env, auction: &mut AuctionInfo) {
+        let bidders: Vec<Address> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::SealedBidders(auction.auction_id))
+            .unwrap_or(Vec::new(env));
+
+        let mut highest: Option<(Address, i128)> = None;
+        let mut second = auction.settings.starting_price;
+        let mut forfeited = 0;
+
+        for bidder in bidders.iter() {
+            let bid: SealedBid = env
+                .storage()
+                .persistent()
+                .get(&DataKey::SealedBid(auction.auction_id, bidder.clone()))
+                .unwrap();
+
+            if !bid.revealed {
+                if auction.settings.forfeit_unrevealed {
+                    forfeited += bid.deposit;
+                }
+                continue;
+            }
+            if bid.amount < auction.settings.starting_price {
+                continue;
+            }
+
+            match &highest {
+                Some((_, top)) if bid.amount <= *top => {
+                    if bid.amount > second {
+                        second = bid.amount;
+                    }
+                }
+                _ => {
+                    if let Some((_, top)) = &highest {
+                        second = *top;
+                    }
+                    highest = Some((bidder, bid.amount));
+                }
+            }
+        }
+
+        if forfeited > 0 {
+            let token_client = token::Client::new(env, &auction.payment_token);
+            token_client.transfer(&env.current_contract_address(), &auction.seller, &forfeited);
+        }
+
+        // The winner pays the runner-up's bid, but never less than the reserve
+        if let Some((winner, amount)) = highest {
+            if amount >= auction.settings.reserve_price {
+                auction.highest_bidder = Some(winner);
+                auction.current_bid = second.max(auction.settings.reserve_price);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -2,10 +2,26 @@
 
 use super::*;
 use soroban_sdk::{
-    testutils::{Address as _, Ledger},
-    token, Address, Env,
+    contract, contractimpl,
+    testutils::{Address as _, EnvTestConfig, Ledger},
+    token, Address, BytesN, Env,
 };
 
+// Minimal NFT that records the last transfer
+#[contract]
+pub struct MockNft;
+
+#[contractimpl]
+impl MockNft {
+    pub fn transfer(env: Env, _from: Address, to: Address, _token_id: u64) {
+        env.storage().instance().set(&0u32, &to);
+    }
+
+    pub fn owner(env: Env) -> Option<Address> {
+        env.storage().instance().get(&0u32)
+    }
+}
+
 #[test]
 fn test_bidding_and_refunds() {
     let env = Env::default();
@@ -15,7 +31,9 @@ fn test_bidding_and_refunds() {
     // 2. Setup a Fake Token (e.g., USDC)
     let token_admin = Address::generate(&env);
     // Note: Using the v2 registration to avoid deprecation warnings
-    let token_contract_id = env.register_stellar_asset_contract_v2(token_admin.clone()).address();
+    let token_contract_id = env
+        .register_stellar_asset_contract_v2(token_admin.clone())
+        .address();
 
     // These clients let us interact with the token
     let token_client = token::Client::new(&env, &token_contract_id);
@@ -44,12 +62,18 @@ fn test_bidding_and_refunds() {
         reserve_price: 150,
         buy_now_price: 500,
         min_bid_increment: 10,
+        snipe_window: 300,
+        snipe_extension: 300,
+        reveal_duration: 0,
+        forfeit_unrevealed: false,
+        creator: None,
+        royalty_bps: 0,
     };
 
     // create_auction args...
     let auction_id = client.create_auction(
         &seller,
-        &Address::generate(&env), // Random NFT
+        &env.register_contract(None, MockNft),
         &1u64,
         &token_contract_id, // Use our fake token
         &AuctionType::English,
@@ -78,20 +102,111 @@ fn test_bidding_and_refunds() {
     // This should trigger a refund to Bidder 1.
     client.place_bid(&bidder2, &auction_id, &120);
 
-    // CHECK: Did Bidder 1 get their refund?
-    // Bidder 1 should be back to 1000
-    assert_eq!(token_client.balance(&bidder1), 1000);
+    // CHECK: Bidder 1's refund is credited, not pushed
+    assert_eq!(token_client.balance(&bidder1), 900);
+    assert_eq!(client.get_refund(&auction_id, &bidder1), 100);
 
     // CHECK: Did Bidder 2 pay?
     // Bidder 2 should be down to 880 (1000 - 120)
     assert_eq!(token_client.balance(&bidder2), 880);
 
-    // CHECK: Contract should now hold 120 (the new highest bid)
-    assert_eq!(token_client.balance(&contract_id), 120);
+    // CHECK: Contract holds the new highest bid plus the pending refund
+    assert_eq!(token_client.balance(&contract_id), 220);
 
     // CHECK: Who is winning?
     let auction_updated = client.get_auction(&auction_id).unwrap();
     assert_eq!(auction_updated.highest_bidder, Some(bidder2));
+
+    // --- SCENARIO 3: Pull the refund ---
+    assert_eq!(client.withdraw_refund(&bidder1, &auction_id), 100);
+    assert_eq!(token_client.balance(&bidder1), 1000);
+    assert_eq!(client.get_refund(&auction_id, &bidder1), 0);
+    assert_eq!(token_client.balance(&contract_id), 120);
+    assert!(client.try_withdraw_refund(&bidder1, &auction_id).is_err());
+}
+
+#[test]
+fn test_bid_log_and_anti_sniping() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let token_contract_id = env
+        .register_stellar_asset_contract_v2(token_admin.clone())
+        .address();
+    let token_admin_client = token::StellarAssetClient::new(&env, &token_contract_id);
+
+    let contract_id = env.register_contract(None, AuctionContract);
+    let client = AuctionContractClient::new(&env, &contract_id);
+    client.init(&token_admin);
+
+    let bidder1 = Address::generate(&env);
+    let bidder2 = Address::generate(&env);
+    token_admin_client.mint(&bidder1, &1000);
+    token_admin_client.mint(&bidder2, &1000);
+
+    // Bids in the last 60 seconds push the end to 120 seconds after the bid
+    let settings = AuctionSettings {
+        start_time: 1000,
+        end_time: 2000,
+        starting_price: 100,
+        reserve_price: 0,
+        buy_now_price: 0,
+        min_bid_increment: 10,
+        snipe_window: 60,
+        snipe_extension: 120,
+        reveal_duration: 0,
+        forfeit_unrevealed: false,
+        creator: None,
+        royalty_bps: 0,
+    };
+    let auction_id = client.create_auction(
+        &Address::generate(&env),
+        &env.register_contract(None, MockNft),
+        &1u64,
+        &token_contract_id,
+        &AuctionType::English,
+        &settings,
+    );
+
+    env.ledger().set_timestamp(1500);
+    client.place_bid(&bidder1, &auction_id, &100);
+    assert_eq!(
+        client.get_auction(&auction_id).unwrap().settings.end_time,
+        2000
+    );
+
+    env.ledger().set_timestamp(1950);
+    client.place_bid(&bidder2, &auction_id, &110);
+    assert_eq!(
+        client.get_auction(&auction_id).unwrap().settings.end_time,
+        2070
+    );
+
+    env.ledger().set_timestamp(2060);
+    client.place_bid(&bidder1, &auction_id, &130);
+    assert_eq!(
+        client.get_auction(&auction_id).unwrap().settings.end_time,
+        2180
+    );
+
+    // Outbid twice, bidder 1 has a single credit covering the first bid
+    assert_eq!(client.get_refund(&auction_id, &bidder1), 100);
+    assert_eq!(client.get_refund(&auction_id, &bidder2), 110);
+
+    assert_eq!(client.get_bid_count(&auction_id), 3);
+    let page = client.get_bids(&auction_id, &1, &5);
+    assert_eq!(page.len(), 2);
+    assert_eq!(
+        page.get(0).unwrap(),
+        BidRecord {
+            bidder: bidder2,
+            amount: 110,
+            timestamp: 1950,
+        }
+    );
+    assert_eq!(page.get(1).unwrap().amount, 130);
+    assert_eq!(client.get_bids(&auction_id, &3, &5).len(), 0);
 }
 
 #[test]
@@ -101,7 +216,9 @@ fn test_dutch_auction_decay() {
 
     // 1. Setup Token & Users
     let token_admin = Address::generate(&env);
-    let token_contract_id = env.register_stellar_asset_contract_v2(token_admin.clone()).address();
+    let token_contract_id = env
+        .register_stellar_asset_contract_v2(token_admin.clone())
+        .address();
     let token_client = token::Client::new(&env, &token_contract_id);
     let token_admin_client = token::StellarAssetClient::new(&env, &token_contract_id);
 
@@ -124,11 +241,17 @@ fn test_dutch_auction_decay() {
         reserve_price: 100,
         buy_now_price: 0, // Not used in Dutch logic usually, or same as start
         min_bid_increment: 0,
+        snipe_window: 0,
+        snipe_extension: 0,
+        reveal_duration: 0,
+        forfeit_unrevealed: false,
+        creator: None,
+        royalty_bps: 0,
     };
 
     let auction_id = client.create_auction(
         &seller,
-        &Address::generate(&env),
+        &env.register_contract(None, MockNft),
         &1u64,
         &token_contract_id,
         &AuctionType::Dutch,
@@ -150,7 +273,7 @@ fn test_dutch_auction_decay() {
     let auction = client.get_auction(&auction_id).unwrap();
 
     // Status checks
-    assert_eq!(auction.settled, true);
+    assert!(auction.settled);
     assert_eq!(auction.highest_bidder, Some(buyer.clone()));
 
     // Price check: Did they pay 300?
@@ -158,4 +281,547 @@ fn test_dutch_auction_decay() {
 
     // Balance check: Buyer started with 1000, paid 300. Remainder: 700.
     assert_eq!(token_client.balance(&buyer), 700);
-}
\ No newline at end of file
+}
+struct SealedSetup<'a> {
+    env: Env,
+    client: AuctionContractClient<'a>,
+    token: token::Client<'a>,
+    nft: MockNftClient<'a>,
+    seller: Address,
+    auction_id: u64,
+}
+
+// Commit phase 1000..2000, reveal phase 2000..2500
+fn setup_sealed(forfeit_unrevealed: bool) -> SealedSetup<'static> {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let token_contract_id = env
+        .register_stellar_asset_contract_v2(token_admin.clone())
+        .address();
+    let token = token::Client::new(&env, &token_contract_id);
+
+    let contract_id = env.register_contract(None, AuctionContract);
+    let client = AuctionContractClient::new(&env, &contract_id);
+    client.init(&token_admin);
+
+    let nft_id = env.register_contract(None, MockNft);
+    let nft = MockNftClient::new(&env, &nft_id);
+
+    let seller = Address::generate(&env);
+    let settings = AuctionSettings {
+        start_time: 1000,
+        end_time: 2000,
+        starting_price: 100,
+        reserve_price: 150,
+        buy_now_price: 0,
+        min_bid_increment: 0,
+        snipe_window: 0,
+        snipe_extension: 0,
+        reveal_duration: 500,
+        forfeit_unrevealed,
+        creator: None,
+        royalty_bps: 0,
+    };
+    let auction_id = client.create_auction(
+        &seller,
+        &nft_id,
+        &1u64,
+        &token_contract_id,
+        &AuctionType::SealedBid,
+        &settings,
+    );
+    env.ledger().set_timestamp(1500);
+
+    SealedSetup {
+        env,
+        client,
+        token,
+        nft,
+        seller,
+        auction_id,
+    }
+}
+
+fn commit(s: &SealedSetup, amount: i128, deposit: i128, salt: u8) -> Address {
+    let bidder = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.token.address).mint(&bidder, &1000);
+    let commitment = s.client.bid_commitment(
+        &s.auction_id,
+        &bidder,
+        &amount,
+        &BytesN::from_array(&s.env, &[salt; 32]),
+    );
+    s.client
+        .commit_bid(&bidder, &s.auction_id, &commitment, &deposit);
+    bidder
+}
+
+fn reveal(s: &SealedSetup, bidder: &Address, amount: i128, salt: u8) {
+    s.client.reveal_bid(
+        bidder,
+        &s.auction_id,
+        &amount,
+        &BytesN::from_array(&s.env, &[salt; 32]),
+    );
+}
+
+#[test]
+fn test_sealed_bid_vickrey_settlement() {
+    let s = setup_sealed(false);
+
+    let alice = commit(&s, 300, 500, 1);
+    let bob = commit(&s, 200, 250, 2);
+    let carol = commit(&s, 400, 400, 3);
+    assert_eq!(s.token.balance(&s.client.address), 1150);
+
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 1);
+    reveal(&s, &bob, 200, 2);
+    // carol never reveals
+
+    s.env.ledger().set_timestamp(2501);
+    s.client.settle_auction(&s.auction_id);
+
+    // Alice wins at Bob's price
+    let auction = s.client.get_auction(&s.auction_id).unwrap();
+    assert_eq!(auction.highest_bidder, Some(alice.clone()));
+    assert_eq!(auction.current_bid, 200);
+    assert_eq!(s.token.balance(&s.seller), 200);
+    assert_eq!(s.nft.owner(), Some(alice.clone()));
+
+    // Deposits are pulled back, less the price for the winner
+    assert_eq!(s.client.withdraw_deposit(&alice, &s.auction_id), 300);
+    assert_eq!(s.client.withdraw_deposit(&bob, &s.auction_id), 250);
+    assert_eq!(s.client.withdraw_deposit(&carol, &s.auction_id), 400);
+    assert_eq!(s.token.balance(&alice), 800);
+    assert_eq!(s.token.balance(&bob), 1000);
+    assert_eq!(s.token.balance(&carol), 1000);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+}
+
+#[test]
+fn test_sealed_bid_single_reveal_pays_reserve_price() {
+    // Reserve 150 is above the starting price of 100
+    let s = setup_sealed(false);
+    let alice = commit(&s, 300, 300, 1);
+
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 1);
+    s.env.ledger().set_timestamp(2501);
+    s.client.settle_auction(&s.auction_id);
+
+    let auction = s.client.get_auction(&s.auction_id).unwrap();
+    assert_eq!(auction.highest_bidder, Some(alice.clone()));
+    assert_eq!(auction.current_bid, 150);
+    assert_eq!(s.token.balance(&s.seller), 150);
+    assert_eq!(s.client.withdraw_deposit(&alice, &s.auction_id), 150);
+}
+
+#[test]
+fn test_sealed_bid_runner_up_below_reserve_pays_reserve() {
+    let s = setup_sealed(false);
+    let alice = commit(&s, 300, 300, 1);
+    let bob = commit(&s, 120, 120, 2);
+
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 1);
+    reveal(&s, &bob, 120, 2);
+    s.env.ledger().set_timestamp(2501);
+    s.client.settle_auction(&s.auction_id);
+
+    // Bob's 120 clears the starting price but not the reserve
+    let auction = s.client.get_auction(&s.auction_id).unwrap();
+    assert_eq!(auction.highest_bidder, Some(alice.clone()));
+    assert_eq!(auction.current_bid, 150);
+    assert_eq!(s.client.withdraw_deposit(&alice, &s.auction_id), 150);
+    assert_eq!(s.client.withdraw_deposit(&bob, &s.auction_id), 120);
+}
+
+#[test]
+fn test_sealed_bid_below_reserve_does_not_sell() {
+    let s = setup_sealed(false);
+    let alice = commit(&s, 120, 120, 1);
+
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 120, 1);
+    s.env.ledger().set_timestamp(2501);
+    s.client.settle_auction(&s.auction_id);
+
+    let auction = s.client.get_auction(&s.auction_id).unwrap();
+    assert_eq!(auction.highest_bidder, None);
+    assert_eq!(s.nft.owner(), Some(s.seller.clone()));
+    assert_eq!(s.client.withdraw_deposit(&alice, &s.auction_id), 120);
+}
+
+#[test]
+fn test_sealed_bid_forfeits_unrevealed_deposit() {
+    let s = setup_sealed(true);
+    let alice = commit(&s, 300, 300, 1);
+    let bob = commit(&s, 500, 600, 2);
+
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 1);
+    s.env.ledger().set_timestamp(2501);
+    s.client.settle_auction(&s.auction_id);
+
+    // Seller receives the reserve price plus Bob's forfeited deposit
+    assert_eq!(s.token.balance(&s.seller), 150 + 600);
+    assert!(s.client.try_withdraw_deposit(&bob, &s.auction_id).is_err());
+    assert_eq!(s.token.balance(&bob), 400);
+}
+
+#[test]
+#[should_panic(expected = "Reveal does not match commitment")]
+fn test_sealed_bid_reveal_wrong_salt() {
+    let s = setup_sealed(false);
+    let alice = commit(&s, 300, 300, 1);
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 9);
+}
+
+#[test]
+#[should_panic(expected = "Reveal does not match commitment")]
+fn test_sealed_bid_copied_commitment_cannot_reveal() {
+    let s = setup_sealed(false);
+    let alice = commit(&s, 300, 300, 1);
+
+    // Mallory copies Alice's commitment and replays her reveal
+    let mallory = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.token.address).mint(&mallory, &1000);
+    let copied = s
+        .client
+        .get_sealed_bid(&s.auction_id, &alice)
+        .unwrap()
+        .commitment;
+    s.client.commit_bid(&mallory, &s.auction_id, &copied, &300);
+
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 1);
+    reveal(&s, &mallory, 300, 1);
+}
+
+#[test]
+#[should_panic(expected = "Reveal window has not opened")]
+fn test_sealed_bid_reveal_during_bidding() {
+    let s = setup_sealed(false);
+    let alice = commit(&s, 300, 300, 1);
+    reveal(&s, &alice, 300, 1);
+}
+
+#[test]
+#[should_panic(expected = "Bid exceeds deposit")]
+fn test_sealed_bid_reveal_over_deposit() {
+    let s = setup_sealed(false);
+    let alice = commit(&s, 300, 200, 1);
+    s.env.ledger().set_timestamp(2100);
+    reveal(&s, &alice, 300, 1);
+}
+
+#[test]
+fn test_settlement_pays_fee_and_royalty_and_moves_nft() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let token_contract_id = env
+        .register_stellar_asset_contract_v2(token_admin.clone())
+        .address();
+    let token_client = token::Client::new(&env, &token_contract_id);
+    let token_admin_client = token::StellarAssetClient::new(&env, &token_contract_id);
+
+    let admin = Address::generate(&env);
+    let fee_recipient = Address::generate(&env);
+    let contract_id = env.register_contract(None, AuctionContract);
+    let client = AuctionContractClient::new(&env, &contract_id);
+    client.init(&admin);
+    client.update_config(&Some(fee_recipient.clone()), &Some(250)); // 2.5%
+
+    let nft_id = env.register_contract(None, MockNft);
+    let nft = MockNftClient::new(&env, &nft_id);
+    let seller = Address::generate(&env);
+    let creator = Address::generate(&env);
+    let bidder = Address::generate(&env);
+    token_admin_client.mint(&bidder, &1000);
+
+    let settings = AuctionSettings {
+        start_time: 1000,
+        end_time: 2000,
+        starting_price: 100,
+        reserve_price: 0,
+        buy_now_price: 0,
+        min_bid_increment: 10,
+        snipe_window: 0,
+        snipe_extension: 0,
+        reveal_duration: 0,
+        forfeit_unrevealed: false,
+        creator: Some(creator.clone()),
+        royalty_bps: 500, // 5%
+    };
+    let auction_id = client.create_auction(
+        &seller,
+        &nft_id,
+        &1u64,
+        &token_contract_id,
+        &AuctionType::English,
+        &settings,
+    );
+
+    // The contract holds the NFT while the auction runs
+    assert_eq!(nft.owner(), Some(contract_id.clone()));
+
+    env.ledger().set_timestamp(1500);
+    client.place_bid(&bidder, &auction_id, &1000);
+
+    // Raising the fee later doesn't touch a running auction
+    client.update_config(&None, &Some(10_000));
+
+    env.ledger().set_timestamp(2000);
+    client.settle_auction(&auction_id);
+
+    assert_eq!(nft.owner(), Some(bidder));
+    assert_eq!(token_client.balance(&fee_recipient), 25);
+    assert_eq!(token_client.balance(&creator), 50);
+    assert_eq!(token_client.balance(&seller), 925);
+    assert_eq!(token_client.balance(&contract_id), 0);
+}
+
+#[test]
+fn test_cancel_before_first_bid_returns_nft() {
+    let s = setup_sealed(false);
+    assert_eq!(s.nft.owner(), Some(s.client.address.clone()));
+
+    s.client.cancel_auction(&s.auction_id);
+
+    let auction = s.client.get_auction(&s.auction_id).unwrap();
+    assert!(auction.cancelled);
+    assert!(auction.settled);
+    assert_eq!(s.nft.owner(), Some(s.seller.clone()));
+}
+
+#[test]
+#[should_panic(expected = "Auction already has bids")]
+fn test_cancel_after_bid_fails() {
+    let s = setup_sealed(false);
+    commit(&s, 300, 300, 1);
+    s.client.cancel_auction(&s.auction_id);
+}
+
+#[test]
+fn test_init_requires_admin_auth() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, AuctionContract);
+    let client = AuctionContractClient::new(&env, &contract_id);
+
+    assert!(client.try_init(&Address::generate(&env)).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Already initialized")]
+fn test_init_twice_fails() {
+    let s = setup_sealed(false);
+    s.client.init(&Address::generate(&s.env));
+}
+
+struct BatchSetup<'a> {
+    env: Env,
+    client: AuctionContractClient<'a>,
+    items: token::Client<'a>,
+    payment: token::Client<'a>,
+    seller: Address,
+    batch_id: u64,
+}
+
+// 10 units, bidding open 1000..2000, minimum 5 per unit
+fn setup_batch() -> BatchSetup<'static> {
+    setup_batch_in(Env::default())
+}
+
+fn setup_batch_in(env: Env) -> BatchSetup<'static> {
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let items_id = env
+        .register_stellar_asset_contract_v2(admin.clone())
+        .address();
+    let payment_id = env
+        .register_stellar_asset_contract_v2(admin.clone())
+        .address();
+
+    let contract_id = env.register_contract(None, AuctionContract);
+    let client = AuctionContractClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let seller = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &items_id).mint(&seller, &10);
+
+    let settings = BatchSettings {
+        quantity: 10,
+        start_time: 1000,
+        end_time: 2000,
+        min_price: 5,
+    };
+    let batch_id = client.create_batch_auction(&seller, &items_id, &payment_id, &settings);
+    env.ledger().set_timestamp(1500);
+
+    BatchSetup {
+        items: token::Client::new(&env, &items_id),
+        payment: token::Client::new(&env, &payment_id),
+        env,
+        client,
+        seller,
+        batch_id,
+    }
+}
+
+fn batch_bid(s: &BatchSetup, quantity: i128, max_price: i128) -> (Address, u32) {
+    let bidder = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.payment.address).mint(&bidder, &100);
+    let bid_id = s
+        .client
+        .place_batch_bid(&bidder, &s.batch_id, &quantity, &max_price);
+    (bidder, bid_id)
+}
+
+#[test]
+fn test_batch_auction_clears_at_marginal_price() {
+    let s = setup_batch();
+    assert_eq!(s.items.balance(&s.client.address), 10);
+
+    let (a, a_id) = batch_bid(&s, 4, 10);
+    let (b, b_id) = batch_bid(&s, 5, 8);
+    let (c, c_id) = batch_bid(&s, 3, 8);
+    let (d, d_id) = batch_bid(&s, 2, 6);
+    assert_eq!(s.payment.balance(&s.client.address), 40 + 40 + 24 + 12);
+
+    s.env.ledger().set_timestamp(2001);
+    s.client.settle_batch(&s.batch_id);
+
+    // A and B fill fully, C takes the last unit at the margin, D misses out
+    let batch = s.client.get_batch(&s.batch_id).unwrap();
+    assert_eq!(batch.clearing_price, 8);
+    assert_eq!(batch.sold, 10);
+    assert_eq!(s.payment.balance(&s.seller), 80);
+
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &a_id), (4, 8));
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &b_id), (5, 0));
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &c_id), (1, 16));
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &d_id), (0, 12));
+
+    assert_eq!(s.items.balance(&a), 4);
+    assert_eq!(s.items.balance(&b), 5);
+    assert_eq!(s.items.balance(&c), 1);
+    assert_eq!(s.items.balance(&d), 0);
+    assert_eq!(s.payment.balance(&a), 68);
+    assert_eq!(s.payment.balance(&d), 100);
+    assert_eq!(s.payment.balance(&s.client.address), 0);
+    assert_eq!(s.items.balance(&s.client.address), 0);
+
+    assert!(s.client.try_claim_batch_bid(&s.batch_id, &a_id).is_err());
+}
+
+#[test]
+fn test_batch_book_fills_by_price_then_bid_order() {
+    let s = setup_batch();
+    let (_, low_id) = batch_bid(&s, 2, 6);
+    let (_, early_id) = batch_bid(&s, 3, 8);
+    let (_, top_id) = batch_bid(&s, 4, 10);
+    let (_, late_id) = batch_bid(&s, 5, 8);
+
+    s.env.ledger().set_timestamp(2001);
+    s.client.settle_batch(&s.batch_id);
+
+    // Ties at 8 fill in bid order; the later one takes what is left
+    assert_eq!(s.client.get_batch(&s.batch_id).unwrap().clearing_price, 8);
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &top_id), (4, 8));
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &early_id), (3, 0));
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &late_id), (3, 16));
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &low_id), (0, 12));
+}
+
+#[test]
+fn test_batch_auction_undersubscribed_clears_at_min_price() {
+    let s = setup_batch();
+    let (a, a_id) = batch_bid(&s, 3, 9);
+    batch_bid(&s, 2, 7);
+
+    s.env.ledger().set_timestamp(2001);
+    s.client.settle_batch(&s.batch_id);
+
+    let batch = s.client.get_batch(&s.batch_id).unwrap();
+    assert_eq!(batch.clearing_price, 5);
+    assert_eq!(batch.sold, 5);
+    assert_eq!(s.payment.balance(&s.seller), 25);
+    assert_eq!(s.items.balance(&s.seller), 5);
+
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &a_id), (3, 12));
+    assert_eq!(s.payment.balance(&a), 85);
+}
+
+#[test]
+#[should_panic(expected = "Auction is still ongoing")]
+fn test_batch_settle_before_end_fails() {
+    let s = setup_batch();
+    batch_bid(&s, 3, 9);
+    s.client.settle_batch(&s.batch_id);
+}
+
+#[test]
+fn test_full_batch_book_evicts_lowest_bid() {
+    // A 500-bid book makes for a snapshot too large to keep
+    let s = setup_batch_in(Env::new_with_config(EnvTestConfig {
+        capture_snapshot_at_drop: false,
+    }));
+    let spammer = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.payment.address).mint(&spammer, &10_000);
+
+    // Filling the book takes 500 calls, more than one default budget covers
+    s.env.budget().reset_unlimited();
+    let mut last_id = 0;
+    for _ in 0..500 {
+        last_id = s.client.place_batch_bid(&spammer, &s.batch_id, &1, &5);
+    }
+    assert_eq!(s.payment.balance(&spammer), 10_000 - 500 * 5);
+
+    // A full book refuses bids that don't beat its lowest
+    let bidder = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.payment.address).mint(&bidder, &100);
+    assert!(s
+        .client
+        .try_place_batch_bid(&bidder, &s.batch_id, &10, &5)
+        .is_err());
+
+    // A better bid pushes out the latest of the lowest bids, which is refunded,
+    // within a default budget
+    s.env.budget().reset_default();
+    let bid_id = s.client.place_batch_bid(&bidder, &s.batch_id, &10, &6);
+    assert!(s.client.get_batch_bid(&s.batch_id, &last_id).is_none());
+    assert_eq!(s.payment.balance(&spammer), 10_000 - 499 * 5);
+
+    s.env.ledger().set_timestamp(2001);
+    s.client.settle_batch(&s.batch_id);
+    assert_eq!(s.client.get_batch(&s.batch_id).unwrap().clearing_price, 6);
+    assert_eq!(s.client.claim_batch_bid(&s.batch_id, &bid_id), (10, 0));
+}
+
+#[test]
+#[should_panic(expected = "Minimum price must be positive")]
+fn test_batch_auction_requires_min_price() {
+    let s = setup_batch();
+    let settings = BatchSettings {
+        quantity: 10,
+        start_time: 1000,
+        end_time: 2000,
+        min_price: 0,
+    };
+    s.client
+        .create_batch_auction(&s.seller, &s.items.address, &s.payment.address, &settings);
+}
+
+#[test]
+#[should_panic(expected = "Bid below minimum price")]
+fn test_batch_bid_below_min_price_fails() {
+    let s = setup_batch();
+    batch_bid(&s, 3, 4);
+}
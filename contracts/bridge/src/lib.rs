@@ -1,6 +1,6 @@
 #![no_std]
 
-use soroban_sdk::{contract, contracterror, contractimpl, contracttype, symbol_short, token, Address, Bytes, BytesN, Env, Map, Vec};
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, symbol_short, token, xdr::{FromXdr, ToXdr}, Address, IntoVal, Symbol, Val, Bytes, BytesN, Env, Map, Vec};
 
 /// Cross-Chain Asset Bridge Contract
 ///
@@ -36,6 +36,8 @@ pub enum BridgeStatus {
     Completed = 2,   // Successfully processed
     Failed = 3,      // Failed validation
     Cancelled = 4,   // Cancelled by user/admin
+    Queued = 5,      // Held in the delay queue awaiting release or veto
+    Refunded = 6,    // Refunded to the sender after the confirmation timeout
 }
 
 /// Cross-chain message format for asset transfers
@@ -68,6 +70,8 @@ pub struct BridgeMessage {
     pub timestamp: u64,
     /// Nonce to prevent replay attacks
     pub nonce: u64,
+    /// Validator set version active when the message was created
+    pub validator_set_version: u32,
 }
 
 /// Validator signature for message verification
@@ -78,6 +82,35 @@ pub struct ValidatorSignature {
     pub signature: BytesN<64>, // Ed25519 signature
 }
 
+/// Validator address and the Ed25519 key it signs with
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidatorInfo {
+    pub validator: Address,
+    pub public_key: BytesN<32>,
+}
+
+/// One epoch of the validator set
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ValidatorSet {
+    pub version: u32,
+    pub validators: Vec<ValidatorInfo>,
+    pub required_signatures: u32,
+    pub activated_at: u64,
+    /// Set when a newer epoch replaces this one
+    pub retired_at: Option<u64>,
+}
+
+/// Validator set rotation waiting out its activation delay
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ValidatorSetProposal {
+    pub validators: Vec<ValidatorInfo>,
+    pub required_signatures: u32,
+    pub activate_after: u64,
+}
+
 /// Bridge configuration
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -100,6 +133,18 @@ pub struct BridgeConfig {
     pub paused: bool,
     /// Chain ID for this bridge instance
     pub chain_id: u32,
+    /// Seconds after which an unconfirmed lock can be refunded to its sender
+    pub confirmation_timeout: u64,
+}
+
+/// Where an outbound transfer goes and what its fee is paid in
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct BridgeRequest {
+    pub dest_chain: u32,
+    pub recipient: Bytes,
+    /// Token the fee is paid in; the bridged token if not given
+    pub fee_token: Option<Address>,
 }
 
 /// Locked asset information
@@ -116,6 +161,42 @@ pub struct LockedAsset {
     pub recipient: Bytes,
 }
 
+/// Outflow cap for released tokens, refilled linearly over `window`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Maximum amount that can be released within one window
+    pub max_amount: i128,
+    /// Window length in seconds
+    pub window: u64,
+}
+
+/// Outflow counted against a rate limit
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct Outflow {
+    pub amount: i128,
+    pub updated_at: u64,
+}
+
+/// Holding period applied to large unlocks of an asset
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransferDelay {
+    /// Unlocks of at least this amount are queued
+    pub threshold: i128,
+    /// Seconds a queued unlock waits before it can be executed
+    pub delay: u64,
+}
+
+/// Unlock held back by the delay queue or the circuit breaker
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct QueuedUnlock {
+    pub message: BridgeMessage,
+    pub release_at: u64,
+}
+
 /// NFT metadata for wrapped tokens
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -144,16 +225,29 @@ pub struct WrappedNFT {
 #[contracttype]
 pub enum DataKey {
     Config,
-    Validators,                    // Vec<Address>
-    ValidatorSetVersion,          // u32
-    LockedAssets(BytesN<32>),     // LockedAsset
-    WrappedNFTs(i128),           // WrappedNFT
+    ValidatorSet(u32),            // ValidatorSet - one entry per epoch
+    ValidatorSetVersion,          // u32 - current epoch
+    PendingValidatorSet,          // ValidatorSetProposal
+    LockedAssets(BytesN<32>),     // Persistent: LockedAsset
+    WrappedNFTs(i128),           // Persistent: WrappedNFT
     NFTMetadata(i128),           // NFTMetadata
-    ProcessedMessages,           // Map<BytesN<32>, BridgeStatus>
-    MessageSignatures(BytesN<32>), // Vec<ValidatorSignature>
+    MessageStatus(BytesN<32>),   // Persistent: BridgeStatus
+    Messages(BytesN<32>),        // Persistent: outbound BridgeMessage
+    PendingMessages(Address),    // Persistent: Vec<BytesN<32>> - unconfirmed locks per sender
+    MessageSignatures(BytesN<32>), // Persistent: Vec<ValidatorSignature>
     UserNonces(Address),         // u64
     BridgeNonces,                // u64
     FeeBalance(Address),         // i128 - accumulated fees per token
+    Liquidity(Address),          // i128 - locked tokens available for unlocks
+    NFTCustody(Address, i128),   // Persistent: BytesN<32> - lock message of an NFT held by the bridge
+    AssetRateLimit(Address),     // RateLimit - across all chains
+    ChainRateLimit(u32, Address), // RateLimit - for unlocks from one source chain
+    AssetOutflow(Address),       // Outflow
+    ChainOutflow(u32, Address),  // Outflow
+    LockRateLimit(u32, Address), // RateLimit - for locks to one destination chain
+    LockOutflow(u32, Address),   // Outflow
+    TransferDelay(Address),      // TransferDelay
+    QueuedUnlocks(BytesN<32>),   // Persistent: QueuedUnlock
 }
 
 /// Custom error codes for the bridge contract
@@ -179,12 +273,40 @@ pub enum Error {
     NFTNotWrapped = 16,
     InvalidRecipient = 17,
     ReentrantCall = 18,
+    InvalidPublicKey = 19,
+    InsufficientLiquidity = 20,
+    NFTAlreadyWrapped = 21,
+    FeeTokenRequired = 22,
+    InvalidRateLimit = 23,
+    UnlockNotQueued = 24,
+    UnlockNotReady = 25,
+    UnknownValidatorSet = 26,
+    ValidatorSetExpired = 27,
+    InsufficientValidators = 28,
+    NoPendingRotation = 29,
+    RotationNotReady = 30,
+    MessageNotExpired = 31,
+    LockNotConfirmed = 32,
+    RateLimitExceeded = 33,
 }
 
 // Constants
 const MAX_VALIDATORS: u32 = 50;
 const BASIS_POINTS: u32 = 10000;
 const MAX_CHAIN_ID: u32 = 1000;
+const STRKEY_LEN: u32 = 56;
+// Time between proposing and activating a validator set rotation
+const ROTATION_DELAY: u64 = 24 * 60 * 60;
+// How long messages signed by a retired validator set are still accepted
+const EPOCH_GRACE_PERIOD: u64 = 7 * 24 * 60 * 60;
+const DEFAULT_CONFIRMATION_TIMEOUT: u64 = 24 * 60 * 60;
+// TTL bounds (in ledgers) for per-message persistent entries
+const MESSAGE_TTL_THRESHOLD: u32 = 100_000;
+const MESSAGE_TTL_EXTEND: u32 = 500_000;
+// XDR discriminants used to decode recipient addresses
+const SCV_ADDRESS: u32 = 18;
+const SC_ADDRESS_ACCOUNT: u32 = 0;
+const SC_ADDRESS_CONTRACT: u32 = 1;
 
 #[contract]
 pub struct BridgeContract;
@@ -227,10 +349,17 @@ impl BridgeContract {
             max_fee: 1_000_000_000_000, // 1M XLM maximum
             paused: false,
             chain_id,
+            confirmation_timeout: DEFAULT_CONFIRMATION_TIMEOUT,
         };
 
         storage.set(&DataKey::Config, &config);
-        storage.set(&DataKey::Validators, &Vec::<Address>::new(&env));
+        storage.set(&DataKey::ValidatorSet(1), &ValidatorSet {
+            version: 1,
+            validators: Vec::new(&env),
+            required_signatures,
+            activated_at: env.ledger().timestamp(),
+            retired_at: None,
+        });
         storage.set(&DataKey::ValidatorSetVersion, &1u32);
         storage.set(&DataKey::UserNonces(env.current_contract_address()), &0u64);
         storage.set(&DataKey::BridgeNonces, &0u64);
@@ -240,50 +369,55 @@ impl BridgeContract {
 
     // ───────────── ADMIN FUNCTIONS ─────────────
 
-    /// Add a validator (admin only)
-    pub fn add_validator(env: Env, admin: Address, validator: Address) -> Result<(), Error> {
+    /// Add a validator with its Ed25519 signing key (admin only)
+    ///
+    /// Proposes the current set (or the pending proposal, if any) plus the
+    /// validator, to be activated like any other rotation once
+    /// `ROTATION_DELAY` has passed. Returns the earliest activation time.
+    pub fn add_validator(
+        env: Env,
+        admin: Address,
+        validator: Address,
+        public_key: BytesN<32>,
+    ) -> Result<u64, Error> {
         admin.require_auth();
         Self::assert_admin(&env, &admin)?;
         Self::assert_not_paused(&env)?;
 
-        let mut validators: Vec<Address> = env.storage().instance().get(&DataKey::Validators).unwrap_or(Vec::new(&env));
+        let (mut validators, required_signatures) = Self::next_validator_set(&env);
+        validators.push_back(ValidatorInfo {
+            validator: validator.clone(),
+            public_key,
+        });
+        Self::validate_validator_set(&validators)?;
 
-        if validators.contains(&validator) {
-            return Err(Error::InvalidMessage);
-        }
-
-        if (validators.len() as u32) >= MAX_VALIDATORS {
-            return Err(Error::InvalidMessage);
-        }
-
-        validators.push_back(validator.clone());
-        env.storage().instance().set(&DataKey::Validators, &validators);
-
-        // Increment validator set version
-        let version: u32 = env.storage().instance().get(&DataKey::ValidatorSetVersion).unwrap_or(1);
-        env.storage().instance().set(&DataKey::ValidatorSetVersion, &(version + 1));
+        let activate_after = Self::propose_epoch(&env, validators, required_signatures);
 
         env.events().publish(
             (symbol_short!("V_ADD"), validator),
-            version + 1,
+            activate_after,
         );
 
-        Ok(())
+        Ok(activate_after)
     }
 
     /// Remove a validator (admin only)
-    pub fn remove_validator(env: Env, admin: Address, validator: Address) -> Result<(), Error> {
+    ///
+    /// Proposes the current set (or the pending proposal, if any) without the
+    /// validator, subject to the same `ROTATION_DELAY`. Fails if the remaining
+    /// set could no longer reach `required_signatures`.
+    pub fn remove_validator(env: Env, admin: Address, validator: Address) -> Result<u64, Error> {
         admin.require_auth();
         Self::assert_admin(&env, &admin)?;
         Self::assert_not_paused(&env)?;
 
-        let mut validators: Vec<Address> = env.storage().instance().get(&DataKey::Validators).unwrap_or(Vec::new(&env));
+        let (validators, required_signatures) = Self::next_validator_set(&env);
 
-        let mut new_validators: Vec<Address> = Vec::new(&env);
+        let mut new_validators: Vec<ValidatorInfo> = Vec::new(&env);
         let mut found = false;
 
-        for v in validators {
-            if v != validator {
+        for v in validators.iter() {
+            if v.validator != validator {
                 new_validators.push_back(v);
             } else {
                 found = true;
@@ -294,20 +428,75 @@ impl BridgeContract {
             return Err(Error::InvalidMessage);
         }
 
-        env.storage().instance().set(&DataKey::Validators, &new_validators);
+        if new_validators.len() < required_signatures {
+            return Err(Error::InsufficientValidators);
+        }
 
-        // Increment validator set version
-        let version: u32 = env.storage().instance().get(&DataKey::ValidatorSetVersion).unwrap_or(1);
-        env.storage().instance().set(&DataKey::ValidatorSetVersion, &(version + 1));
+        let activate_after = Self::propose_epoch(&env, new_validators, required_signatures);
 
         env.events().publish(
             (symbol_short!("V_REM"), validator),
-            version + 1,
+            activate_after,
         );
 
+        Ok(activate_after)
+    }
+
+    /// Propose replacing the validator set and threshold (admin only)
+    ///
+    /// The rotation can be activated once `ROTATION_DELAY` has passed and
+    /// replaces any earlier proposal.
+    pub fn propose_validator_set(
+        env: Env,
+        admin: Address,
+        validators: Vec<ValidatorInfo>,
+        required_signatures: u32,
+    ) -> Result<u64, Error> {
+        admin.require_auth();
+        Self::assert_admin(&env, &admin)?;
+
+        Self::validate_validator_set(&validators)?;
+        if required_signatures == 0 || required_signatures > validators.len() {
+            return Err(Error::InsufficientValidators);
+        }
+
+        Ok(Self::propose_epoch(&env, validators, required_signatures))
+    }
+
+    /// Drop the pending validator set rotation (admin only)
+    pub fn cancel_validator_set(env: Env, admin: Address) -> Result<(), Error> {
+        admin.require_auth();
+        Self::assert_admin(&env, &admin)?;
+
+        if !env.storage().instance().has(&DataKey::PendingValidatorSet) {
+            return Err(Error::NoPendingRotation);
+        }
+        env.storage().instance().remove(&DataKey::PendingValidatorSet);
+
         Ok(())
     }
 
+    /// Activate the pending rotation once its delay has passed (permissionless)
+    pub fn activate_validator_set(env: Env) -> Result<u32, Error> {
+        let proposal: ValidatorSetProposal = env.storage().instance()
+            .get(&DataKey::PendingValidatorSet)
+            .ok_or(Error::NoPendingRotation)?;
+
+        if env.ledger().timestamp() < proposal.activate_after {
+            return Err(Error::RotationNotReady);
+        }
+
+        env.storage().instance().remove(&DataKey::PendingValidatorSet);
+        let version = Self::activate_epoch(&env, proposal.validators, proposal.required_signatures);
+
+        env.events().publish(
+            (symbol_short!("VS_ACT"), version),
+            proposal.required_signatures,
+        );
+
+        Ok(version)
+    }
+
     /// Update bridge fees (admin only)
     pub fn update_fees(
         env: Env,
@@ -330,6 +519,139 @@ impl BridgeContract {
         Ok(())
     }
 
+    /// Set or clear the outflow cap for `asset`, either across all chains
+    /// (`chain` = None) or for transfers arriving from one chain (admin only)
+    pub fn set_rate_limit(
+        env: Env,
+        admin: Address,
+        asset: Address,
+        chain: Option<u32>,
+        limit: Option<RateLimit>,
+    ) -> Result<(), Error> {
+        admin.require_auth();
+        Self::assert_admin(&env, &admin)?;
+
+        let (limit_key, outflow_key) = match chain {
+            Some(chain) => (DataKey::ChainRateLimit(chain, asset.clone()), DataKey::ChainOutflow(chain, asset.clone())),
+            None => (DataKey::AssetRateLimit(asset.clone()), DataKey::AssetOutflow(asset.clone())),
+        };
+
+        match limit {
+            Some(limit) => {
+                if limit.max_amount <= 0 || limit.window == 0 {
+                    return Err(Error::InvalidRateLimit);
+                }
+                env.storage().instance().set(&limit_key, &limit);
+            }
+            None => {
+                env.storage().instance().remove(&limit_key);
+                env.storage().instance().remove(&outflow_key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set or clear the cap on tokens of `asset` locked for `dest_chain` (admin only)
+    pub fn set_lock_rate_limit(
+        env: Env,
+        admin: Address,
+        asset: Address,
+        dest_chain: u32,
+        limit: Option<RateLimit>,
+    ) -> Result<(), Error> {
+        admin.require_auth();
+        Self::assert_admin(&env, &admin)?;
+
+        let limit_key = DataKey::LockRateLimit(dest_chain, asset.clone());
+        match limit {
+            Some(limit) => {
+                if limit.max_amount <= 0 || limit.window == 0 {
+                    return Err(Error::InvalidRateLimit);
+                }
+                env.storage().instance().set(&limit_key, &limit);
+            }
+            None => {
+                env.storage().instance().remove(&limit_key);
+                env.storage().instance().remove(&DataKey::LockOutflow(dest_chain, asset));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set or clear the delay applied to large unlocks of `asset` (admin only)
+    pub fn set_transfer_delay(
+        env: Env,
+        admin: Address,
+        asset: Address,
+        delay: Option<TransferDelay>,
+    ) -> Result<(), Error> {
+        admin.require_auth();
+        Self::assert_admin(&env, &admin)?;
+
+        match delay {
+            Some(delay) => {
+                if delay.threshold <= 0 {
+                    return Err(Error::InvalidRateLimit);
+                }
+                env.storage().instance().set(&DataKey::TransferDelay(asset), &delay);
+            }
+            None => env.storage().instance().remove(&DataKey::TransferDelay(asset)),
+        }
+
+        Ok(())
+    }
+
+    /// Withdraw accumulated fees in `token` to the fee collector
+    pub fn withdraw_fees(
+        env: Env,
+        fee_collector: Address,
+        token: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        fee_collector.require_auth();
+
+        let config: BridgeConfig = env.storage().instance().get(&DataKey::Config).ok_or(Error::NotInitialized)?;
+        if config.fee_collector != fee_collector {
+            return Err(Error::Unauthorized);
+        }
+
+        if amount <= 0 {
+            return Err(Error::InvalidAssetAmount);
+        }
+
+        let fee_key = DataKey::FeeBalance(token.clone());
+        let balance: i128 = env.storage().instance().get(&fee_key).unwrap_or(0);
+        if balance < amount {
+            return Err(Error::InsufficientBalance);
+        }
+
+        env.storage().instance().set(&fee_key, &(balance - amount));
+
+        let token_client = token::Client::new(&env, &token);
+        token_client.transfer(&env.current_contract_address(), &fee_collector, &amount);
+
+        env.events().publish(
+            (symbol_short!("FEE_WDRAW"), token),
+            (fee_collector, amount),
+        );
+
+        Ok(())
+    }
+
+    /// Set how long a lock may stay unconfirmed before it is refundable (admin only)
+    pub fn set_confirmation_timeout(env: Env, admin: Address, timeout: u64) -> Result<(), Error> {
+        admin.require_auth();
+        Self::assert_admin(&env, &admin)?;
+
+        let mut config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
+        config.confirmation_timeout = timeout;
+        env.storage().instance().set(&DataKey::Config, &config);
+
+        Ok(())
+    }
+
     /// Emergency pause/unpause (admin only)
     pub fn set_paused(env: Env, admin: Address, paused: bool) -> Result<(), Error> {
         admin.require_auth();
@@ -350,16 +672,20 @@ impl BridgeContract {
     // ───────────── BRIDGE OPERATIONS ─────────────
 
     /// Initiate asset bridging (lock assets)
+    ///
+    /// The bridge fee is charged on top of `amount`, in the request's
+    /// `fee_token` if given, otherwise in the bridged token. NFT transfers pay
+    /// a flat `min_fee` and need a `fee_token` whenever that fee is non-zero.
     pub fn bridge_assets(
         env: Env,
         sender: Address,
         asset_address: Address,
         asset_type: AssetType,
         amount: i128,
-        dest_chain: u32,
-        recipient: Bytes,
+        request: BridgeRequest,
     ) -> Result<BytesN<32>, Error> {
         sender.require_auth();
+        let BridgeRequest { dest_chain, recipient, fee_token } = request;
         Self::assert_not_paused(&env)?;
 
         if amount <= 0 {
@@ -376,35 +702,55 @@ impl BridgeContract {
 
         let config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
 
+        // Calculate bridge fee
+        let fee_amount = match asset_type {
+            AssetType::Token => Self::calculate_fee(amount, &config)?,
+            // For NFTs, amount represents token_id so only the flat minimum applies
+            AssetType::NFT => config.min_fee,
+        };
+
+        let fee_payment_token = match (&fee_token, asset_type) {
+            (Some(token), _) => Some(token.clone()),
+            (None, AssetType::Token) => Some(asset_address.clone()),
+            (None, AssetType::NFT) => None,
+        };
+        if fee_amount > 0 && fee_payment_token.is_none() {
+            return Err(Error::FeeTokenRequired);
+        }
+
         // Check sender balance
         match asset_type {
             AssetType::Token => {
                 let token_client = token::Client::new(&env, &asset_address);
                 let balance = token_client.balance(&sender);
-                if balance < amount {
+                let required = if fee_token.is_none() { amount + fee_amount } else { amount };
+                if balance < required {
                     return Err(Error::InsufficientBalance);
                 }
             }
             AssetType::NFT => {
-                // For NFTs, amount represents token_id
-                // We'll verify ownership during transfer
+                // Ownership is enforced by the NFT contract's transfer
             }
         }
 
-        // Calculate bridge fee
-        let fee_amount = Self::calculate_fee(&env, amount, &config)?;
-
         // Generate unique message ID
-        let message_id = Self::generate_message_id(&env, &sender, asset_type.clone(), amount, dest_chain);
+        let message_id = Self::generate_message_id(&env, &sender, asset_type, amount, dest_chain);
 
         // Check for replay attack
-        let processed: Option<BridgeStatus> = env.storage().instance().get(&DataKey::ProcessedMessages)
-            .and_then(|m: Map<BytesN<32>, BridgeStatus>| m.get(message_id.clone()));
-
-        if processed.is_some() {
+        if Self::message_status(&env, &message_id).is_some() {
             return Err(Error::MessageAlreadyProcessed);
         }
 
+        if asset_type == AssetType::Token {
+            let scope = (
+                DataKey::LockRateLimit(dest_chain, asset_address.clone()),
+                DataKey::LockOutflow(dest_chain, asset_address.clone()),
+            );
+            if !Self::consume_outflow(&env, &[scope], amount) {
+                return Err(Error::RateLimitExceeded);
+            }
+        }
+
         // Transfer assets to bridge (lock them)
         match asset_type {
             AssetType::Token => {
@@ -412,17 +758,31 @@ impl BridgeContract {
                 token_client.transfer(&sender, &env.current_contract_address(), &amount);
             }
             AssetType::NFT => {
-                // For NFTs, we need to handle the transfer
-                // This would typically involve calling the NFT contract
-                // For now, we'll store the lock information
+                Self::transfer_nft(&env, &asset_address, &sender, &env.current_contract_address(), amount)?;
+                Self::set_persistent(&env, &DataKey::NFTCustody(asset_address.clone(), amount), &message_id);
             }
         }
 
+        // Collect fee
+        if let Some(fee_payment_token) = fee_payment_token.filter(|_| fee_amount > 0) {
+            let fee_client = token::Client::new(&env, &fee_payment_token);
+            fee_client.transfer(&sender, &env.current_contract_address(), &fee_amount);
+
+            let fee_key = DataKey::FeeBalance(fee_payment_token.clone());
+            let balance: i128 = env.storage().instance().get(&fee_key).unwrap_or(0);
+            env.storage().instance().set(&fee_key, &(balance + fee_amount));
+
+            env.events().publish(
+                (symbol_short!("FEE_COLL"), message_id.clone()),
+                (fee_payment_token, fee_amount),
+            );
+        }
+
         // Store locked asset information
         let locked_asset = LockedAsset {
             owner: sender.clone(),
             asset_address: asset_address.clone(),
-            asset_type: asset_type.clone(),
+            asset_type,
             amount,
             locked_at: env.ledger().timestamp(),
             message_id: message_id.clone(),
@@ -430,7 +790,9 @@ impl BridgeContract {
             recipient: recipient.clone(),
         };
 
-        env.storage().instance().set(&DataKey::LockedAssets(message_id.clone()), &locked_asset);
+        let locked_key = DataKey::LockedAssets(message_id.clone());
+        env.storage().persistent().set(&locked_key, &locked_asset);
+        env.storage().persistent().extend_ttl(&locked_key, MESSAGE_TTL_THRESHOLD, MESSAGE_TTL_EXTEND);
 
         // Create bridge message
         let message = BridgeMessage {
@@ -441,26 +803,28 @@ impl BridgeContract {
             asset_type,
             asset_address,
             asset_amount: amount,
-            sender,
+            sender: sender.clone(),
             recipient,
             fee_amount,
-            fee_token: None, // Using same token as asset
+            fee_token,
             timestamp: env.ledger().timestamp(),
             nonce: Self::get_next_bridge_nonce(&env),
+            validator_set_version: Self::get_validator_set_version(env.clone()),
         };
 
-        // Collect fee
-        if fee_amount > 0 {
-            // For now, fees are collected in the asset token
-            // In production, you might want separate fee tokens
-        }
+        // Keep the message for validators to confirm against
+        let message_key = DataKey::Messages(message_id.clone());
+        env.storage().persistent().set(&message_key, &message);
+        env.storage().persistent().extend_ttl(&message_key, MESSAGE_TTL_THRESHOLD, MESSAGE_TTL_EXTEND);
 
         // Initialize message status
-        let mut processed_messages: Map<BytesN<32>, BridgeStatus> = env.storage().instance()
-            .get(&DataKey::ProcessedMessages)
-            .unwrap_or(Map::new(&env));
-        processed_messages.set(message_id.clone(), BridgeStatus::Pending);
-        env.storage().instance().set(&DataKey::ProcessedMessages, &processed_messages);
+        Self::set_message_status(&env, &message_id, BridgeStatus::Pending);
+
+        let pending_key = DataKey::PendingMessages(sender);
+        let mut pending: Vec<BytesN<32>> = env.storage().persistent().get(&pending_key).unwrap_or(Vec::new(&env));
+        pending.push_back(message_id.clone());
+        env.storage().persistent().set(&pending_key, &pending);
+        env.storage().persistent().extend_ttl(&pending_key, MESSAGE_TTL_THRESHOLD, MESSAGE_TTL_EXTEND);
 
         // Emit bridge initiation event
         env.events().publish(
@@ -482,51 +846,148 @@ impl BridgeContract {
         Self::assert_not_paused(&env)?;
 
         // Verify validator is authorized
-        let validators: Vec<Address> = env.storage().instance().get(&DataKey::Validators).unwrap_or(Vec::new(&env));
-        if !validators.contains(&validator) {
+        if !Self::is_validator(&Self::current_validator_set(&env), &validator) {
             return Err(Error::Unauthorized);
         }
 
-        let config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
-
         // Verify message hasn't been processed
-        let processed: Option<BridgeStatus> = env.storage().instance().get(&DataKey::ProcessedMessages)
-            .and_then(|m: Map<BytesN<32>, BridgeStatus>| m.get(message.message_id.clone()));
-
-        if let Some(status) = processed {
+        if let Some(status) = Self::message_status(&env, &message.message_id) {
             if status != BridgeStatus::Pending {
                 return Err(Error::MessageAlreadyProcessed);
             }
         }
 
-        // Verify signatures
-        Self::verify_signatures(&env, &message, &signatures, &validators, config.required_signatures)?;
+        Self::verify_message(&env, &message, &signatures)?;
 
-        // Process the bridge action
-        match message.action {
-            BridgeAction::Unlock => {
-                Self::process_unlock(&env, &message)?;
-            }
-            BridgeAction::Lock => {
-                // Lock actions are initiated from source, not completed here
-                return Err(Error::InvalidMessage);
+        // Lock actions are initiated from source, not completed here
+        if message.action != BridgeAction::Unlock {
+            return Err(Error::InvalidMessage);
+        }
+
+        // Store signatures for audit
+        Self::set_persistent(&env, &DataKey::MessageSignatures(message.message_id.clone()), &signatures);
+
+        // Large token unlocks wait out the delay so they can be vetoed
+        if message.asset_type == AssetType::Token {
+            let delay: Option<TransferDelay> = env.storage().instance()
+                .get(&DataKey::TransferDelay(message.asset_address.clone()));
+            if let Some(delay) = delay.filter(|d| message.asset_amount >= d.threshold) {
+                Self::queue_unlock(&env, &message, env.ledger().timestamp() + delay.delay);
+                return Ok(());
             }
         }
 
-        // Update message status
-        let mut processed_messages: Map<BytesN<32>, BridgeStatus> = env.storage().instance()
-            .get(&DataKey::ProcessedMessages)
-            .unwrap_or(Map::new(&env));
-        processed_messages.set(message.message_id.clone(), BridgeStatus::Completed);
-        env.storage().instance().set(&DataKey::ProcessedMessages, &processed_messages);
+        Self::release_unlock(&env, &message)
+    }
 
-        // Store signatures for audit
-        env.storage().instance().set(&DataKey::MessageSignatures(message.message_id.clone()), &signatures);
+    /// Execute a queued unlock once its delay has passed (permissionless)
+    ///
+    /// If the unlock would still breach a rate limit, the circuit breaker trips
+    /// again and the unlock stays queued.
+    pub fn execute_unlock(env: Env, message_id: BytesN<32>) -> Result<(), Error> {
+        Self::assert_not_paused(&env)?;
+
+        let queued: QueuedUnlock = env.storage().persistent()
+            .get(&DataKey::QueuedUnlocks(message_id.clone()))
+            .ok_or(Error::UnlockNotQueued)?;
+
+        if env.ledger().timestamp() < queued.release_at {
+            return Err(Error::UnlockNotReady);
+        }
+
+        env.storage().persistent().remove(&DataKey::QueuedUnlocks(message_id));
+        Self::release_unlock(&env, &queued.message)
+    }
+
+    /// Veto a queued unlock (admin or validator); works while paused
+    pub fn veto_unlock(env: Env, caller: Address, message_id: BytesN<32>) -> Result<(), Error> {
+        caller.require_auth();
+
+        let config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
+        if config.admin != caller && !Self::is_validator(&Self::current_validator_set(&env), &caller) {
+            return Err(Error::Unauthorized);
+        }
+
+        if !env.storage().persistent().has(&DataKey::QueuedUnlocks(message_id.clone())) {
+            return Err(Error::UnlockNotQueued);
+        }
+
+        env.storage().persistent().remove(&DataKey::QueuedUnlocks(message_id.clone()));
+        Self::set_message_status(&env, &message_id, BridgeStatus::Failed);
 
-        // Emit completion event
         env.events().publish(
-            (symbol_short!("B_COMP"), message.message_id),
-            (message.action, message.asset_amount),
+            (symbol_short!("UL_VETO"), message_id),
+            caller,
+        );
+
+        Ok(())
+    }
+
+    /// Record that the destination chain has processed an outbound lock - validator only
+    ///
+    /// A confirmed lock keeps backing the assets on the destination chain and
+    /// can no longer be cancelled or refunded, so only from here on can its
+    /// tokens fund inbound unlocks and its NFT be released back home.
+    pub fn confirm_bridge(
+        env: Env,
+        validator: Address,
+        message_id: BytesN<32>,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<(), Error> {
+        validator.require_auth();
+        Self::assert_not_paused(&env)?;
+
+        if !Self::is_validator(&Self::current_validator_set(&env), &validator) {
+            return Err(Error::Unauthorized);
+        }
+
+        let message: BridgeMessage = env.storage().persistent()
+            .get(&DataKey::Messages(message_id.clone()))
+            .ok_or(Error::AssetNotLocked)?;
+
+        if Self::message_status(&env, &message_id) != Some(BridgeStatus::Pending) {
+            return Err(Error::MessageAlreadyProcessed);
+        }
+
+        Self::verify_message(&env, &message, &signatures)?;
+
+        Self::set_message_status(&env, &message_id, BridgeStatus::Confirmed);
+        Self::remove_pending_message(&env, &message.sender, &message_id);
+        if message.asset_type == AssetType::Token {
+            Self::adjust_liquidity(&env, &message.asset_address, message.asset_amount);
+        }
+        Self::set_persistent(&env, &DataKey::MessageSignatures(message_id.clone()), &signatures);
+
+        env.events().publish(
+            (symbol_short!("B_CONF"), message_id),
+            (message.dest_chain, message.asset_amount),
+        );
+
+        Ok(())
+    }
+
+    /// Return an unconfirmed lock to its sender once the confirmation timeout
+    /// has passed (permissionless)
+    pub fn refund_expired(env: Env, message_id: BytesN<32>) -> Result<(), Error> {
+        let locked_asset: LockedAsset = env.storage().persistent()
+            .get(&DataKey::LockedAssets(message_id.clone()))
+            .ok_or(Error::AssetNotLocked)?;
+
+        if Self::message_status(&env, &message_id) != Some(BridgeStatus::Pending) {
+            return Err(Error::MessageAlreadyProcessed);
+        }
+
+        let config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
+        if env.ledger().timestamp() < locked_asset.locked_at + config.confirmation_timeout {
+            return Err(Error::MessageNotExpired);
+        }
+
+        Self::release_locked_asset(&env, &locked_asset)?;
+        Self::set_message_status(&env, &message_id, BridgeStatus::Refunded);
+
+        env.events().publish(
+            (symbol_short!("B_REFUND"), message_id),
+            (locked_asset.owner, locked_asset.amount),
         );
 
         Ok(())
@@ -541,7 +1002,7 @@ impl BridgeContract {
         caller.require_auth();
 
         // Get locked asset info
-        let locked_asset: LockedAsset = env.storage().instance()
+        let locked_asset: LockedAsset = env.storage().persistent()
             .get(&DataKey::LockedAssets(message_id.clone()))
             .ok_or(Error::AssetNotLocked)?;
 
@@ -552,35 +1013,17 @@ impl BridgeContract {
         }
 
         // Check message status
-        let processed: Option<BridgeStatus> = env.storage().instance().get(&DataKey::ProcessedMessages)
-            .and_then(|m: Map<BytesN<32>, BridgeStatus>| m.get(message_id.clone()));
-
-        if let Some(status) = processed {
+        if let Some(status) = Self::message_status(&env, &message_id) {
             if status != BridgeStatus::Pending {
                 return Err(Error::MessageAlreadyProcessed);
             }
         }
 
         // Refund assets to owner
-        match locked_asset.asset_type {
-            AssetType::Token => {
-                let token_client = token::Client::new(&env, &locked_asset.asset_address);
-                token_client.transfer(&env.current_contract_address(), &locked_asset.owner, &locked_asset.amount);
-            }
-            AssetType::NFT => {
-                // Handle NFT refund
-            }
-        }
+        Self::release_locked_asset(&env, &locked_asset)?;
 
         // Update status
-        let mut processed_messages: Map<BytesN<32>, BridgeStatus> = env.storage().instance()
-            .get(&DataKey::ProcessedMessages)
-            .unwrap_or(Map::new(&env));
-        processed_messages.set(message_id.clone(), BridgeStatus::Cancelled);
-        env.storage().instance().set(&DataKey::ProcessedMessages, &processed_messages);
-
-        // Remove locked asset record
-        env.storage().instance().remove(&DataKey::LockedAssets(message_id.clone()));
+        Self::set_message_status(&env, &message_id, BridgeStatus::Cancelled);
 
         env.events().publish(
             (symbol_short!("B_CANCEL"), message_id),
@@ -592,55 +1035,25 @@ impl BridgeContract {
 
     // ───────────── NFT WRAPPING FUNCTIONS ─────────────
 
-    /// Wrap an NFT for cross-chain transfer
-    pub fn wrap_nft(
+    /// Burn a wrapped NFT to send the original back to `recipient` on its
+    /// home chain
+    ///
+    /// The original stays in custody on its home chain; validators release it
+    /// there on seeing the `N_UNWRAP` event.
+    pub fn unwrap_nft(
         env: Env,
         owner: Address,
-        nft_contract: Address,
-        token_id: i128,
-        dest_chain: u32,
+        wrapped_token_id: i128,
         recipient: Bytes,
-    ) -> Result<i128, Error> {
-        owner.require_auth();
-        Self::assert_not_paused(&env)?;
-
-        // Generate wrapped token ID
-        let wrapped_token_id = Self::generate_wrapped_token_id(&env, nft_contract.clone(), token_id, dest_chain);
-
-        // Store wrapping information
-        let wrapped_nft = WrappedNFT {
-            original_token_id: token_id,
-            original_chain: Self::get_chain_id(&env),
-            original_contract: nft_contract.clone(),
-            wrapped_token_id,
-            owner: owner.clone(),
-            wrapped_at: env.ledger().timestamp(),
-        };
-
-        env.storage().instance().set(&DataKey::WrappedNFTs(wrapped_token_id), &wrapped_nft);
-
-        // TODO: Implement actual NFT transfer from owner to bridge
-        // This would require calling the NFT contract's transfer function
-
-        // Emit wrap event
-        env.events().publish(
-            (symbol_short!("NFT_WRAP"), wrapped_token_id),
-            (nft_contract.clone(), token_id, dest_chain),
-        );
-
-        Ok(wrapped_token_id)
-    }
-
-    /// Unwrap an NFT after cross-chain transfer
-    pub fn unwrap_nft(
-        env: Env,
-        owner: Address,
-        wrapped_token_id: i128,
     ) -> Result<(Address, i128), Error> {
         owner.require_auth();
         Self::assert_not_paused(&env)?;
 
-        let wrapped_nft: WrappedNFT = env.storage().instance()
+        if recipient.is_empty() {
+            return Err(Error::InvalidRecipient);
+        }
+
+        let wrapped_nft: WrappedNFT = env.storage().persistent()
             .get(&DataKey::WrappedNFTs(wrapped_token_id))
             .ok_or(Error::NFTNotWrapped)?;
 
@@ -648,18 +1061,15 @@ impl BridgeContract {
             return Err(Error::Unauthorized);
         }
 
-        // TODO: Implement NFT minting/transfer back to owner
-        // This would require calling the original NFT contract
-
-        // Remove wrapped NFT record
-        env.storage().instance().remove(&DataKey::WrappedNFTs(wrapped_token_id));
+        // Burn the wrapped representation
+        env.storage().persistent().remove(&DataKey::WrappedNFTs(wrapped_token_id));
 
         let original_contract = wrapped_nft.original_contract;
         let original_token_id = wrapped_nft.original_token_id;
 
         env.events().publish(
             (symbol_short!("N_UNWRAP"), wrapped_token_id),
-            (original_contract.clone(), original_token_id),
+            (wrapped_nft.original_chain, original_contract.clone(), original_token_id, recipient),
         );
 
         Ok((original_contract, original_token_id))
@@ -672,20 +1082,114 @@ impl BridgeContract {
     }
 
     pub fn get_validators(env: Env) -> Vec<Address> {
-        env.storage().instance().get(&DataKey::Validators).unwrap_or(Vec::new(&env))
+        let mut validators = Vec::new(&env);
+        for v in Self::current_validator_set(&env).validators.iter() {
+            validators.push_back(v.validator);
+        }
+        validators
+    }
+
+    pub fn get_validator_key(env: Env, validator: Address) -> Option<BytesN<32>> {
+        Self::current_validator_set(&env)
+            .validators
+            .iter()
+            .find(|v| v.validator == validator)
+            .map(|v| v.public_key)
+    }
+
+    pub fn get_validator_set_version(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::ValidatorSetVersion).unwrap_or(1)
+    }
+
+    pub fn get_validator_set(env: Env, version: u32) -> Option<ValidatorSet> {
+        env.storage().instance().get(&DataKey::ValidatorSet(version))
+    }
+
+    pub fn get_pending_validator_set(env: Env) -> Option<ValidatorSetProposal> {
+        env.storage().instance().get(&DataKey::PendingValidatorSet)
+    }
+
+    /// Digest validators must sign with their Ed25519 key to approve `message`
+    pub fn message_digest(env: Env, message: BridgeMessage) -> BytesN<32> {
+        Self::hash_message(&env, &message)
     }
 
     pub fn get_message_status(env: Env, message_id: BytesN<32>) -> Option<BridgeStatus> {
-        env.storage().instance().get(&DataKey::ProcessedMessages)
-            .and_then(|m: Map<BytesN<32>, BridgeStatus>| m.get(message_id))
+        Self::message_status(&env, &message_id)
+    }
+
+    pub fn get_message(env: Env, message_id: BytesN<32>) -> Option<BridgeMessage> {
+        env.storage().persistent().get(&DataKey::Messages(message_id))
     }
 
     pub fn get_locked_asset(env: Env, message_id: BytesN<32>) -> Option<LockedAsset> {
-        env.storage().instance().get(&DataKey::LockedAssets(message_id))
+        env.storage().persistent().get(&DataKey::LockedAssets(message_id))
+    }
+
+    /// Outbound locks from `sender` still awaiting confirmation
+    pub fn get_pending_messages(env: Env, sender: Address) -> Vec<BytesN<32>> {
+        env.storage().persistent().get(&DataKey::PendingMessages(sender)).unwrap_or(Vec::new(&env))
     }
 
     pub fn get_wrapped_nft(env: Env, wrapped_token_id: i128) -> Option<WrappedNFT> {
-        env.storage().instance().get(&DataKey::WrappedNFTs(wrapped_token_id))
+        env.storage().persistent().get(&DataKey::WrappedNFTs(wrapped_token_id))
+    }
+
+    /// Wrapped token ID assigned to `token_id` of `nft_contract` bridged over `chain`
+    pub fn wrapped_token_id(env: Env, nft_contract: Address, token_id: i128, chain: u32) -> i128 {
+        Self::generate_wrapped_token_id(&env, nft_contract, token_id, chain)
+    }
+
+    pub fn get_fee_balance(env: Env, token: Address) -> i128 {
+        env.storage().instance().get(&DataKey::FeeBalance(token)).unwrap_or(0)
+    }
+
+    pub fn get_rate_limit(env: Env, asset: Address, chain: Option<u32>) -> Option<RateLimit> {
+        match chain {
+            Some(chain) => env.storage().instance().get(&DataKey::ChainRateLimit(chain, asset)),
+            None => env.storage().instance().get(&DataKey::AssetRateLimit(asset)),
+        }
+    }
+
+    /// Outflow currently counted against the `asset`/`chain` rate limit
+    pub fn get_outflow(env: Env, asset: Address, chain: Option<u32>) -> i128 {
+        let (limit_key, outflow_key) = match chain {
+            Some(chain) => (DataKey::ChainRateLimit(chain, asset.clone()), DataKey::ChainOutflow(chain, asset)),
+            None => (DataKey::AssetRateLimit(asset.clone()), DataKey::AssetOutflow(asset)),
+        };
+        let limit: Option<RateLimit> = env.storage().instance().get(&limit_key);
+        let outflow: Option<Outflow> = env.storage().instance().get(&outflow_key);
+        match (limit, outflow) {
+            (Some(limit), Some(outflow)) => Self::decayed_outflow(&env, &limit, &outflow),
+            _ => 0,
+        }
+    }
+
+    pub fn get_lock_rate_limit(env: Env, asset: Address, dest_chain: u32) -> Option<RateLimit> {
+        env.storage().instance().get(&DataKey::LockRateLimit(dest_chain, asset))
+    }
+
+    /// Locked amount currently counted against the `asset`/`dest_chain` cap
+    pub fn get_lock_outflow(env: Env, asset: Address, dest_chain: u32) -> i128 {
+        let limit: Option<RateLimit> = env.storage().instance().get(&DataKey::LockRateLimit(dest_chain, asset.clone()));
+        let outflow: Option<Outflow> = env.storage().instance().get(&DataKey::LockOutflow(dest_chain, asset));
+        match (limit, outflow) {
+            (Some(limit), Some(outflow)) => Self::decayed_outflow(&env, &limit, &outflow),
+            _ => 0,
+        }
+    }
+
+    pub fn get_transfer_delay(env: Env, asset: Address) -> Option<TransferDelay> {
+        env.storage().instance().get(&DataKey::TransferDelay(asset))
+    }
+
+    pub fn get_queued_unlock(env: Env, message_id: BytesN<32>) -> Option<QueuedUnlock> {
+        env.storage().persistent().get(&DataKey::QueuedUnlocks(message_id))
+    }
+
+    /// Tokens locked in the bridge that inbound transfers can release
+    pub fn get_liquidity(env: Env, asset_address: Address) -> i128 {
+        env.storage().instance().get(&DataKey::Liquidity(asset_address)).unwrap_or(0)
     }
 
     // ───────────── INTERNAL HELPERS ─────────────
@@ -699,8 +1203,7 @@ impl BridgeContract {
     ) -> BytesN<32> {
         let mut data = Bytes::new(env);
         data.extend_from_slice(&env.ledger().timestamp().to_be_bytes());
-        // Placeholder for sender address in hash
-        data.extend_from_slice(&[0u8; 32]);
+        data.append(&sender.to_xdr(env));
         data.extend_from_slice(&(asset_type as u32).to_be_bytes());
         data.extend_from_slice(&amount.to_be_bytes());
         data.extend_from_slice(&dest_chain.to_be_bytes());
@@ -715,14 +1218,11 @@ impl BridgeContract {
         env: &Env,
         nft_contract: Address,
         token_id: i128,
-        dest_chain: u32,
+        chain: u32,
     ) -> i128 {
-        let mut data = Bytes::new(env);
-        // Placeholder for NFT contract address
-        data.extend_from_slice(&[0u8; 32]);
+        let mut data = nft_contract.to_xdr(env);
         data.extend_from_slice(&token_id.to_be_bytes());
-        data.extend_from_slice(&dest_chain.to_be_bytes());
-        data.extend_from_slice(&env.ledger().timestamp().to_be_bytes());
+        data.extend_from_slice(&chain.to_be_bytes());
 
         let hash = env.crypto().sha256(&data);
         // Convert first 16 bytes to i128 for token ID
@@ -746,7 +1246,7 @@ impl BridgeContract {
         next
     }
 
-    fn calculate_fee(env: &Env, amount: i128, config: &BridgeConfig) -> Result<i128, Error> {
+    fn calculate_fee(amount: i128, config: &BridgeConfig) -> Result<i128, Error> {
         let fee = (amount * config.base_fee_bps as i128) / BASIS_POINTS as i128;
         let final_fee = fee.max(config.min_fee).min(config.max_fee);
 
@@ -760,29 +1260,54 @@ impl BridgeContract {
         Ok(final_fee)
     }
 
+    /// Check a message against the validator set of its epoch. A retired
+    /// epoch only vouches for messages created before it was retired, and
+    /// only until its grace period runs out.
+    fn verify_message(env: &Env, message: &BridgeMessage, signatures: &Vec<ValidatorSignature>) -> Result<(), Error> {
+        let validator_set: ValidatorSet = env.storage().instance()
+            .get(&DataKey::ValidatorSet(message.validator_set_version))
+            .ok_or(Error::UnknownValidatorSet)?;
+        if let Some(retired_at) = validator_set.retired_at {
+            if message.timestamp > retired_at
+                || env.ledger().timestamp() > retired_at + EPOCH_GRACE_PERIOD
+            {
+                return Err(Error::ValidatorSetExpired);
+            }
+        }
+        Self::verify_signatures(env, message, signatures, &validator_set)
+    }
+
     fn verify_signatures(
         env: &Env,
         message: &BridgeMessage,
         signatures: &Vec<ValidatorSignature>,
-        validators: &Vec<Address>,
-        required: u32,
+        validator_set: &ValidatorSet,
     ) -> Result<(), Error> {
-        if (signatures.len() as u32) < required {
+        let required = validator_set.required_signatures;
+        if signatures.len() < required {
             return Err(Error::InsufficientSignatures);
         }
 
-        let message_bytes = Self::message_to_bytes(env, message);
-        let message_hash = env.crypto().sha256(&message_bytes);
+        let digest: Bytes = Self::hash_message(env, message).into();
 
         let mut valid_signatures = 0u32;
+        let mut used_validators: Map<Address, bool> = Map::new(env);
 
         for sig in signatures.iter() {
-            if validators.contains(&sig.validator) {
-                // TODO: Implement actual signature verification
-                // For now, we'll assume signatures are valid in tests
-                // In production, this would verify Ed25519 signatures
-                valid_signatures += 1;
+            let public_key = match validator_set.validators.iter().find(|v| v.validator == sig.validator) {
+                Some(v) => v.public_key,
+                None => continue,
+            };
+            // Each validator counts at most once towards the threshold
+            if used_validators.contains_key(sig.validator.clone()) {
+                continue;
             }
+
+            // Traps on a bad signature, aborting the whole call
+            env.crypto().ed25519_verify(&public_key, &digest, &sig.signature);
+
+            used_validators.set(sig.validator.clone(), true);
+            valid_signatures += 1;
         }
 
         if valid_signatures < required {
@@ -792,55 +1317,394 @@ impl BridgeContract {
         Ok(())
     }
 
-    fn message_to_bytes(env: &Env, message: &BridgeMessage) -> Bytes {
-        let mut data = Bytes::new(env);
-        data.extend_from_slice(&message.message_id.to_array());
-        data.extend_from_slice(&message.source_chain.to_be_bytes());
-        data.extend_from_slice(&message.dest_chain.to_be_bytes());
-        data.extend_from_slice(&(message.action as u32).to_be_bytes());
-        data.extend_from_slice(&(message.asset_type as u32).to_be_bytes());
-        // For hashing, we'll use a simple representation
-        // In production, proper address serialization would be needed
-        data.extend_from_slice(&[0u8; 32]); // Placeholder for address bytes
-        data.extend_from_slice(&message.asset_amount.to_be_bytes());
-        data.extend_from_slice(&[0u8; 32]); // Placeholder for sender address
-        data.extend_from_slice(message.recipient.to_buffer::<1024>().as_slice());
-        data.extend_from_slice(&message.fee_amount.to_be_bytes());
-        data.extend_from_slice(&message.timestamp.to_be_bytes());
-        data.extend_from_slice(&message.nonce.to_be_bytes());
-        data
+    fn current_validator_set(env: &Env) -> ValidatorSet {
+        let version: u32 = env.storage().instance().get(&DataKey::ValidatorSetVersion).unwrap_or(1);
+        env.storage().instance().get(&DataKey::ValidatorSet(version)).unwrap()
+    }
+
+    fn is_validator(validator_set: &ValidatorSet, address: &Address) -> bool {
+        validator_set.validators.iter().any(|v| v.validator == *address)
+    }
+
+    /// Reject oversized sets and duplicate validators or keys, since a
+    /// repeated signer would count twice towards the threshold
+    fn validate_validator_set(validators: &Vec<ValidatorInfo>) -> Result<(), Error> {
+        if validators.len() > MAX_VALIDATORS {
+            return Err(Error::InvalidMessage);
+        }
+
+        for (i, a) in validators.iter().enumerate() {
+            for b in validators.iter().skip(i + 1) {
+                if a.validator == b.validator {
+                    return Err(Error::InvalidMessage);
+                }
+                if a.public_key == b.public_key {
+                    return Err(Error::InvalidPublicKey);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validators and threshold the next rotation builds on: the pending
+    /// proposal if there is one, otherwise the current set
+    fn next_validator_set(env: &Env) -> (Vec<ValidatorInfo>, u32) {
+        let pending: Option<ValidatorSetProposal> = env.storage().instance().get(&DataKey::PendingValidatorSet);
+        match pending {
+            Some(proposal) => (proposal.validators, proposal.required_signatures),
+            None => {
+                let current = Self::current_validator_set(env);
+                (current.validators, current.required_signatures)
+            }
+        }
+    }
+
+    /// Store a rotation that can be activated after `ROTATION_DELAY`,
+    /// replacing any earlier proposal, and return its activation time
+    fn propose_epoch(env: &Env, validators: Vec<ValidatorInfo>, required_signatures: u32) -> u64 {
+        let activate_after = env.ledger().timestamp() + ROTATION_DELAY;
+        let proposal = ValidatorSetProposal {
+            validators,
+            required_signatures,
+            activate_after,
+        };
+        env.storage().instance().set(&DataKey::PendingValidatorSet, &proposal);
+
+        env.events().publish(
+            (symbol_short!("VS_PROP"), required_signatures),
+            activate_after,
+        );
+
+        activate_after
+    }
+
+    /// Retire the current epoch and start a new one, returning its version
+    fn activate_epoch(env: &Env, validators: Vec<ValidatorInfo>, required_signatures: u32) -> u32 {
+        let now = env.ledger().timestamp();
+
+        let mut current = Self::current_validator_set(env);
+        current.retired_at = Some(now);
+        env.storage().instance().set(&DataKey::ValidatorSet(current.version), &current);
+
+        let version = current.version + 1;
+        env.storage().instance().set(&DataKey::ValidatorSet(version), &ValidatorSet {
+            version,
+            validators,
+            required_signatures,
+            activated_at: now,
+            retired_at: None,
+        });
+        env.storage().instance().set(&DataKey::ValidatorSetVersion, &version);
+
+        // Keep the config's threshold in line with the active epoch
+        let mut config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
+        config.required_signatures = required_signatures;
+        env.storage().instance().set(&DataKey::Config, &config);
+
+        version
+    }
+
+    /// Canonical message hash: XDR encoding of every `BridgeMessage` field,
+    /// bound to this bridge instance so signatures can't be replayed elsewhere
+    fn hash_message(env: &Env, message: &BridgeMessage) -> BytesN<32> {
+        let payload = (env.current_contract_address(), message.clone()).to_xdr(env);
+        BytesN::from_array(env, &env.crypto().sha256(&payload).to_array())
+    }
+
+    /// Release an approved unlock, or hold it and trip the circuit breaker if
+    /// it would breach a rate limit
+    fn release_unlock(env: &Env, message: &BridgeMessage) -> Result<(), Error> {
+        if message.asset_type == AssetType::Token && !Self::consume_unlock_outflow(env, message) {
+            // Returning Ok keeps the pause in place; the unlock waits for review
+            let mut config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
+            config.paused = true;
+            env.storage().instance().set(&DataKey::Config, &config);
+
+            Self::queue_unlock(env, message, env.ledger().timestamp());
+
+            env.events().publish(
+                (symbol_short!("CB_TRIP"), message.message_id.clone()),
+                (message.asset_address.clone(), message.source_chain, message.asset_amount),
+            );
+            return Ok(());
+        }
+
+        Self::process_unlock(env, message)?;
+        Self::set_message_status(env, &message.message_id, BridgeStatus::Completed);
+
+        // Emit completion event
+        env.events().publish(
+            (symbol_short!("B_COMP"), message.message_id.clone()),
+            (message.action, message.asset_amount),
+        );
+
+        Ok(())
+    }
+
+    fn queue_unlock(env: &Env, message: &BridgeMessage, release_at: u64) {
+        let queued = QueuedUnlock {
+            message: message.clone(),
+            release_at,
+        };
+        Self::set_persistent(env, &DataKey::QueuedUnlocks(message.message_id.clone()), &queued);
+        Self::set_message_status(env, &message.message_id, BridgeStatus::Queued);
+
+        env.events().publish(
+            (symbol_short!("UL_QUEUE"), message.message_id.clone()),
+            release_at,
+        );
+    }
+
+    /// Count an unlock against the asset-wide and per-source-chain limits
+    fn consume_unlock_outflow(env: &Env, message: &BridgeMessage) -> bool {
+        let asset = &message.asset_address;
+        let scopes = [
+            (DataKey::AssetRateLimit(asset.clone()), DataKey::AssetOutflow(asset.clone())),
+            (
+                DataKey::ChainRateLimit(message.source_chain, asset.clone()),
+                DataKey::ChainOutflow(message.source_chain, asset.clone()),
+            ),
+        ];
+        Self::consume_outflow(env, &scopes, message.asset_amount)
+    }
+
+    /// Count `amount` against each (limit, outflow) scope that has a limit set,
+    /// recording nothing and returning false if any would be exceeded
+    fn consume_outflow(env: &Env, scopes: &[(DataKey, DataKey)], amount: i128) -> bool {
+        for (limit_key, outflow_key) in scopes.iter() {
+            if let Some(limit) = env.storage().instance().get::<_, RateLimit>(limit_key) {
+                if Self::current_outflow(env, &limit, outflow_key) + amount > limit.max_amount {
+                    return false;
+                }
+            }
+        }
+
+        for (limit_key, outflow_key) in scopes.iter() {
+            if let Some(limit) = env.storage().instance().get::<_, RateLimit>(limit_key) {
+                let outflow = Outflow {
+                    amount: Self::current_outflow(env, &limit, outflow_key) + amount,
+                    updated_at: env.ledger().timestamp(),
+                };
+                env.storage().instance().set(outflow_key, &outflow);
+            }
+        }
+
+        true
+    }
+
+    fn current_outflow(env: &Env, limit: &RateLimit, outflow_key: &DataKey) -> i128 {
+        env.storage().instance()
+            .get(outflow_key)
+            .map(|outflow: Outflow| Self::decayed_outflow(env, limit, &outflow))
+            .unwrap_or(0)
+    }
+
+    /// Outflow remaining after linear refill since the last update
+    fn decayed_outflow(env: &Env, limit: &RateLimit, outflow: &Outflow) -> i128 {
+        let elapsed = env.ledger().timestamp().saturating_sub(outflow.updated_at);
+        let refill = limit.max_amount * elapsed as i128 / limit.window as i128;
+        (outflow.amount - refill).max(0)
+    }
+
+    fn message_status(env: &Env, message_id: &BytesN<32>) -> Option<BridgeStatus> {
+        env.storage().persistent().get(&DataKey::MessageStatus(message_id.clone()))
+    }
+
+    fn set_message_status(env: &Env, message_id: &BytesN<32>, status: BridgeStatus) {
+        Self::set_persistent(env, &DataKey::MessageStatus(message_id.clone()), &status);
+    }
+
+    /// Store per-message data in its own persistent entry, kept alive as long
+    /// as message records are
+    fn set_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+        env.storage().persistent().set(key, value);
+        env.storage().persistent().extend_ttl(key, MESSAGE_TTL_THRESHOLD, MESSAGE_TTL_EXTEND);
+    }
+
+    fn remove_pending_message(env: &Env, sender: &Address, message_id: &BytesN<32>) {
+        let key = DataKey::PendingMessages(sender.clone());
+        let mut pending: Vec<BytesN<32>> = env.storage().persistent().get(&key).unwrap_or(Vec::new(env));
+        if let Some(index) = pending.first_index_of(message_id) {
+            pending.remove(index);
+        }
+        if pending.is_empty() {
+            env.storage().persistent().remove(&key);
+        } else {
+            env.storage().persistent().set(&key, &pending);
+            env.storage().persistent().extend_ttl(&key, MESSAGE_TTL_THRESHOLD, MESSAGE_TTL_EXTEND);
+        }
+    }
+
+    /// Hand a locked asset back to its owner and drop the lock record
+    fn release_locked_asset(env: &Env, locked_asset: &LockedAsset) -> Result<(), Error> {
+        match locked_asset.asset_type {
+            AssetType::Token => {
+                let token_client = token::Client::new(env, &locked_asset.asset_address);
+                token_client.transfer(&env.current_contract_address(), &locked_asset.owner, &locked_asset.amount);
+            }
+            AssetType::NFT => {
+                env.storage().persistent().remove(&DataKey::NFTCustody(
+                    locked_asset.asset_address.clone(),
+                    locked_asset.amount,
+                ));
+                Self::transfer_nft(
+                    env,
+                    &locked_asset.asset_address,
+                    &env.current_contract_address(),
+                    &locked_asset.owner,
+                    locked_asset.amount,
+                )?;
+            }
+        }
+
+        env.storage().persistent().remove(&DataKey::LockedAssets(locked_asset.message_id.clone()));
+        Self::remove_pending_message(env, &locked_asset.owner, &locked_asset.message_id);
+
+        Ok(())
     }
 
     fn process_unlock(env: &Env, message: &BridgeMessage) -> Result<(), Error> {
-        // For unlock operations, we need to release assets to the recipient
-        // This would typically involve checking if the assets are locked
-        // and then transferring them to the recipient
+        let recipient = Self::bytes_to_address(env, &message.recipient)?;
 
         match message.asset_type {
             AssetType::Token => {
+                // Inbound tokens are released from what confirmed outbound locks hold
+                let liquidity: i128 = env.storage().instance()
+                    .get(&DataKey::Liquidity(message.asset_address.clone()))
+                    .unwrap_or(0);
+                if message.asset_amount <= 0 {
+                    return Err(Error::InvalidAssetAmount);
+                }
+                if liquidity < message.asset_amount {
+                    return Err(Error::InsufficientLiquidity);
+                }
+
+                Self::adjust_liquidity(env, &message.asset_address, -message.asset_amount);
                 let token_client = token::Client::new(env, &message.asset_address);
-                let recipient_addr = Self::bytes_to_address(env, &message.recipient)?;
-                token_client.transfer(&env.current_contract_address(), &recipient_addr, &message.asset_amount);
+                token_client.transfer(&env.current_contract_address(), &recipient, &message.asset_amount);
             }
             AssetType::NFT => {
-                // Handle NFT unlock
-                // This would involve minting or transferring the NFT
+                let custody_key = DataKey::NFTCustody(message.asset_address.clone(), message.asset_amount);
+                let custody: Option<BytesN<32>> = env.storage().persistent().get(&custody_key);
+                if let Some(lock_id) = custody {
+                    // NFT returning home: release it from custody once its
+                    // lock can no longer be cancelled or refunded
+                    if Self::message_status(env, &lock_id) != Some(BridgeStatus::Confirmed) {
+                        return Err(Error::LockNotConfirmed);
+                    }
+                    env.storage().persistent().remove(&custody_key);
+                    Self::transfer_nft(
+                        env,
+                        &message.asset_address,
+                        &env.current_contract_address(),
+                        &recipient,
+                        message.asset_amount,
+                    )?;
+                } else {
+                    // Foreign NFT: mint a wrapped representation for the recipient
+                    Self::mint_wrapped_nft(
+                        env,
+                        &recipient,
+                        &message.asset_address,
+                        message.asset_amount,
+                        message.source_chain,
+                    )?;
+                }
             }
         }
 
         Ok(())
     }
 
-    fn bytes_to_address(_env: &Env, _bytes: &Bytes) -> Result<Address, Error> {
-        // TODO: Implement proper address conversion from bytes
-        // This requires careful handling of different address formats
-        // For now, return an error to indicate this needs proper implementation
-        Err(Error::InvalidRecipient)
+    /// Record a wrapped NFT owned by `owner`, failing if it is already wrapped
+    fn mint_wrapped_nft(
+        env: &Env,
+        owner: &Address,
+        nft_contract: &Address,
+        token_id: i128,
+        original_chain: u32,
+    ) -> Result<i128, Error> {
+        let wrapped_token_id = Self::generate_wrapped_token_id(env, nft_contract.clone(), token_id, original_chain);
+
+        if env.storage().persistent().has(&DataKey::WrappedNFTs(wrapped_token_id)) {
+            return Err(Error::NFTAlreadyWrapped);
+        }
+
+        let wrapped_nft = WrappedNFT {
+            original_token_id: token_id,
+            original_chain,
+            original_contract: nft_contract.clone(),
+            wrapped_token_id,
+            owner: owner.clone(),
+            wrapped_at: env.ledger().timestamp(),
+        };
+
+        Self::set_persistent(env, &DataKey::WrappedNFTs(wrapped_token_id), &wrapped_nft);
+
+        env.events().publish(
+            (symbol_short!("NFT_WRAP"), wrapped_token_id),
+            (nft_contract.clone(), token_id, original_chain),
+        );
+
+        Ok(wrapped_token_id)
     }
 
-    fn get_chain_id(env: &Env) -> u32 {
-        let config: BridgeConfig = env.storage().instance().get(&DataKey::Config).unwrap();
-        config.chain_id
+    /// Move an NFT through the achievement_nft `transfer(from, to, token_id)` interface
+    fn transfer_nft(env: &Env, nft_contract: &Address, from: &Address, to: &Address, token_id: i128) -> Result<(), Error> {
+        let token_id = u32::try_from(token_id).map_err(|_| Error::InvalidAssetAmount)?;
+        let transfer_args = (from.clone(), to.clone(), token_id).into_val(env);
+        env.invoke_contract::<()>(nft_contract, &Symbol::new(env, "transfer"), transfer_args);
+        Ok(())
+    }
+
+    fn adjust_liquidity(env: &Env, asset_address: &Address, delta: i128) {
+        let key = DataKey::Liquidity(asset_address.clone());
+        let current: i128 = env.storage().instance().get(&key).unwrap_or(0);
+        env.storage().instance().set(&key, &(current + delta));
+    }
+
+    /// Decode a recipient given as a Stellar strkey (`G...`/`C...`), an XDR
+    /// `ScAddress`, or an XDR `ScVal::Address`
+    fn bytes_to_address(env: &Env, bytes: &Bytes) -> Result<Address, Error> {
+        if bytes.len() == STRKEY_LEN {
+            return match bytes.get(0) {
+                Some(b'G') | Some(b'C') => Ok(Address::from_string_bytes(bytes)),
+                _ => Err(Error::InvalidRecipient),
+            };
+        }
+
+        let xdr = if Self::read_u32(bytes, 0) == Some(SCV_ADDRESS)
+            && Self::is_sc_address(&bytes.slice(4..))
+        {
+            bytes.clone()
+        } else if Self::is_sc_address(bytes) {
+            let mut xdr = Bytes::from_array(env, &SCV_ADDRESS.to_be_bytes());
+            xdr.append(bytes);
+            xdr
+        } else {
+            return Err(Error::InvalidRecipient);
+        };
+
+        Address::from_xdr(env, &xdr).map_err(|_| Error::InvalidRecipient)
+    }
+
+    /// Whether `bytes` is exactly one XDR `ScAddress` (ed25519 account or contract)
+    fn is_sc_address(bytes: &Bytes) -> bool {
+        match Self::read_u32(bytes, 0) {
+            Some(SC_ADDRESS_ACCOUNT) => bytes.len() == 40 && Self::read_u32(bytes, 4) == Some(0),
+            Some(SC_ADDRESS_CONTRACT) => bytes.len() == 36,
+            _ => false,
+        }
+    }
+
+    fn read_u32(bytes: &Bytes, offset: u32) -> Option<u32> {
+        if bytes.len() < offset + 4 {
+            return None;
+        }
+        let mut buf = [0u8; 4];
+        bytes.slice(offset..offset + 4).copy_into_slice(&mut buf);
+        Some(u32::from_be_bytes(buf))
     }
 
     fn assert_admin(env: &Env, user: &Address) -> Result<(), Error> {
@@ -862,8 +1726,88 @@ impl BridgeContract {
 
 #[cfg(test)]
 mod test {
+    extern crate std;
+
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Env};
+    use ed25519_dalek::{Signer, SigningKey};
+    use soroban_sdk::{testutils::{Address as _, Ledger}, Env};
+
+    fn strkey(env: &Env, address: &Address) -> Bytes {
+        let strkey = address.to_string();
+        let mut buf = [0u8; 56];
+        strkey.copy_into_slice(&mut buf);
+        Bytes::from_array(env, &buf)
+    }
+
+    fn sign_all(env: &Env, client: &BridgeContractClient, validators: &[(Address, SigningKey)], message: &BridgeMessage) -> Vec<ValidatorSignature> {
+        let mut signatures = Vec::new(env);
+        for (validator, key) in validators.iter() {
+            signatures.push_back(ValidatorSignature {
+                validator: validator.clone(),
+                signature: sign(env, client, key, message),
+            });
+        }
+        signatures
+    }
+
+    /// Deterministic test keypair; distinct seeds give distinct keys
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn public_key(env: &Env, key: &SigningKey) -> BytesN<32> {
+        BytesN::from_array(env, &key.verifying_key().to_bytes())
+    }
+
+    fn sign(env: &Env, client: &BridgeContractClient, key: &SigningKey, message: &BridgeMessage) -> BytesN<64> {
+        let digest = client.message_digest(message);
+        BytesN::from_array(env, &key.sign(&digest.to_array()).to_bytes())
+    }
+
+    fn request(recipient: &Bytes, fee_token: Option<Address>) -> BridgeRequest {
+        BridgeRequest { dest_chain: 1, recipient: recipient.clone(), fee_token }
+    }
+
+    fn unlock_message(env: &Env, client: &BridgeContractClient, asset_type: AssetType, asset_address: &Address, amount: i128, recipient: Bytes) -> BridgeMessage {
+        BridgeMessage {
+            message_id: BytesN::from_array(env, &[7u8; 32]),
+            source_chain: 1,
+            dest_chain: 0,
+            action: BridgeAction::Unlock,
+            asset_type,
+            asset_address: asset_address.clone(),
+            asset_amount: amount,
+            sender: Address::generate(env),
+            recipient,
+            fee_amount: 0,
+            fee_token: None,
+            timestamp: 0,
+            nonce: 1,
+            validator_set_version: client.get_validator_set_version(),
+        }
+    }
+
+    /// Registers and activates `count` validators with distinct keys, threshold 2
+    fn setup_validators<'a>(env: &'a Env, count: u32) -> (BridgeContractClient<'a>, std::vec::Vec<(Address, SigningKey)>) {
+        let contract_id = env.register_contract(None, BridgeContract);
+        let client = BridgeContractClient::new(env, &contract_id);
+
+        let admin = Address::generate(env);
+        let fee_collector = Address::generate(env);
+        client.initialize(&admin, &2u32, &0u32, &fee_collector);
+
+        let mut validators = std::vec::Vec::new();
+        for i in 0..count {
+            let validator = Address::generate(env);
+            let key = signing_key(i as u8 + 1);
+            client.add_validator(&admin, &validator, &public_key(env, &key));
+            validators.push((validator, key));
+        }
+        env.ledger().with_mut(|li| li.timestamp += ROTATION_DELAY);
+        client.activate_validator_set();
+
+        (client, validators)
+    }
 
     #[test]
     fn test_bridge_initialization() {
@@ -895,13 +1839,125 @@ mod test {
         let admin = Address::generate(&env);
         let fee_collector = Address::generate(&env);
         let validator = Address::generate(&env);
+        let key = signing_key(1);
 
         client.initialize(&admin, &2u32, &0u32, &fee_collector);
-        client.add_validator(&admin, &validator);
+        let activate_after = client.add_validator(&admin, &validator, &public_key(&env, &key));
+        assert_eq!(activate_after, ROTATION_DELAY);
+
+        // Additions go through the rotation delay like any other change to the set
+        assert!(client.get_validators().is_empty());
+        assert_eq!(client.try_activate_validator_set(), Err(Ok(Error::RotationNotReady)));
+        env.ledger().with_mut(|li| li.timestamp += ROTATION_DELAY);
+        client.activate_validator_set();
 
         let validators = client.get_validators();
         assert_eq!(validators.len(), 1);
         assert_eq!(validators.get(0).unwrap(), validator);
+        assert_eq!(client.get_validator_key(&validator), Some(public_key(&env, &key)));
+
+        // The same key can't back a second validator
+        let result = client.try_add_validator(&admin, &Address::generate(&env), &public_key(&env, &key));
+        assert_eq!(result, Err(Ok(Error::InvalidPublicKey)));
+    }
+
+    #[test]
+    fn test_complete_bridge_valid_signatures() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let nft_contract = Address::generate(&env);
+        let message = unlock_message(&env, &client, AssetType::NFT, &nft_contract, 1, strkey(&env, &Address::generate(&env)));
+
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+
+        client.complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(client.get_message_status(&message.message_id), Some(BridgeStatus::Completed));
+    }
+
+    #[test]
+    fn test_complete_bridge_forged_signature() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let nft_contract = Address::generate(&env);
+        let message = unlock_message(&env, &client, AssetType::NFT, &nft_contract, 1, strkey(&env, &Address::generate(&env)));
+
+        // Second entry claims to be validator 1 but is signed by an unregistered key
+        let forger = signing_key(200);
+        let mut signatures = Vec::new(&env);
+        signatures.push_back(ValidatorSignature {
+            validator: validators[0].0.clone(),
+            signature: sign(&env, &client, &validators[0].1, &message),
+        });
+        signatures.push_back(ValidatorSignature {
+            validator: validators[1].0.clone(),
+            signature: sign(&env, &client, &forger, &message),
+        });
+
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert!(result.is_err());
+        assert_eq!(client.get_message_status(&message.message_id), None);
+
+        // A genuine signature over a tampered message is rejected too
+        let mut tampered = message.clone();
+        tampered.asset_amount = 2;
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+
+        let result = client.try_complete_bridge(&validators[0].0, &tampered, &signatures);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_complete_bridge_duplicate_signatures() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let nft_contract = Address::generate(&env);
+        let message = unlock_message(&env, &client, AssetType::NFT, &nft_contract, 1, strkey(&env, &Address::generate(&env)));
+
+        let signature = ValidatorSignature {
+            validator: validators[0].0.clone(),
+            signature: sign(&env, &client, &validators[0].1, &message),
+        };
+        let mut signatures = Vec::new(&env);
+        signatures.push_back(signature.clone());
+        signatures.push_back(signature);
+
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_complete_bridge_under_threshold() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let nft_contract = Address::generate(&env);
+        let message = unlock_message(&env, &client, AssetType::NFT, &nft_contract, 1, strkey(&env, &Address::generate(&env)));
+
+        let mut signatures = Vec::new(&env);
+        signatures.push_back(ValidatorSignature {
+            validator: validators[0].0.clone(),
+            signature: sign(&env, &client, &validators[0].1, &message),
+        });
+
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::InsufficientSignatures)));
+
+        // Signatures from unregistered signers don't count towards the threshold
+        let outsider = Address::generate(&env);
+        signatures.push_back(ValidatorSignature {
+            validator: outsider,
+            signature: sign(&env, &client, &signing_key(201), &message),
+        });
+
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::InvalidSignature)));
     }
 
     #[test]
@@ -923,11 +1979,11 @@ mod test {
         // For testing, we'll use a mock token - actual minting would be done externally
         // In a real scenario, the user would already have tokens
 
-        token_admin_client.mint(&user, &1000);
+        token_admin_client.mint(&user, &2_000_000);
 
         // Bridge tokens
         let recipient = Bytes::from_array(&env, &[1u8; 32]);
-        let message_id = client.bridge_assets(&user, &token_contract.address(), &AssetType::Token, &500, &1u32, &recipient);
+        let message_id = client.bridge_assets(&user, &token_contract.address(), &AssetType::Token, &500, &request(&recipient, None));
 
         // Verify bridge initiation (actual token transfer would happen in real scenario)
         let locked = client.get_locked_asset(&message_id);
@@ -939,5 +1995,609 @@ mod test {
         let locked_asset = locked.unwrap();
         assert_eq!(locked_asset.amount, 500);
         assert_eq!(locked_asset.owner, user);
+
+        // Minimum fee is charged on top, in the bridged token
+        let token_client = token::Client::new(&env, &token_contract.address());
+        assert_eq!(token_client.balance(&user), 2_000_000 - 500 - 1_000_000);
+        assert_eq!(client.get_fee_balance(&token_contract.address()), 1_000_000);
+    }
+
+    #[test]
+    fn test_same_lock_from_two_users_gets_distinct_ids() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, BridgeContract);
+        let client = BridgeContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let fee_collector = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let token_contract = env.register_stellar_asset_contract_v2(admin.clone());
+        let token_admin_client = token::StellarAssetClient::new(&env, &token_contract.address());
+
+        client.initialize(&admin, &1u32, &0u32, &fee_collector);
+        token_admin_client.mint(&alice, &2_000_000);
+        token_admin_client.mint(&bob, &2_000_000);
+
+        // Same amount, chain and ledger time, and both users' first nonce
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        let first = client.bridge_assets(&alice, &token_contract.address(), &AssetType::Token, &500, &request(&recipient, None));
+        let second = client.bridge_assets(&bob, &token_contract.address(), &AssetType::Token, &500, &request(&recipient, None));
+
+        assert_ne!(first, second);
+        assert_eq!(client.get_locked_asset(&first).unwrap().owner, alice);
+        assert_eq!(client.get_locked_asset(&second).unwrap().owner, bob);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bridge_assets_nft_custody() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let user = Address::generate(&env);
+        let fee_token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        token::StellarAssetClient::new(&env, &fee_token).mint(&user, &5_000_000);
+
+        let nft_contract = env.register_contract(None, MockNft);
+        let nft_client = MockNftClient::new(&env, &nft_contract);
+        nft_client.mint(&user, &7);
+        nft_client.mint(&user, &8);
+
+        // NFTs need a separate fee token
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        let result = client.try_bridge_assets(&user, &nft_contract, &AssetType::NFT, &7, &request(&recipient, None));
+        assert_eq!(result, Err(Ok(Error::FeeTokenRequired)));
+
+        let message_id = client.bridge_assets(&user, &nft_contract, &AssetType::NFT, &7, &request(&recipient, Some(fee_token.clone())));
+        assert_eq!(nft_client.owner_of(&7), client.address);
+        assert_eq!(client.get_fee_balance(&fee_token), 1_000_000);
+        assert_eq!(token::Client::new(&env, &fee_token).balance(&user), 4_000_000);
+
+        // Cancelling hands the NFT back
+        client.cancel_bridge(&user, &message_id);
+        assert_eq!(nft_client.owner_of(&7), user);
+
+        // An inbound unlock releases a custodied NFT instead of wrapping it,
+        // but only once its lock is confirmed and can no longer be cancelled
+        let lock_id = client.bridge_assets(&user, &nft_contract, &AssetType::NFT, &8, &request(&recipient, Some(fee_token.clone())));
+        let receiver = Address::generate(&env);
+        let message = unlock_message(&env, &client, AssetType::NFT, &nft_contract, 8, strkey(&env, &receiver));
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::LockNotConfirmed)));
+        assert_eq!(nft_client.owner_of(&8), client.address);
+
+        confirm_lock(&env, &client, &validators, &lock_id);
+        client.complete_bridge(&validators[0].0, &message, &signatures);
+
+        assert_eq!(nft_client.owner_of(&8), receiver);
+        let wrapped_token_id = client.wrapped_token_id(&nft_contract, &8, &message.source_chain);
+        assert!(client.get_wrapped_nft(&wrapped_token_id).is_none());
+    }
+
+    #[test]
+    fn test_withdraw_fees() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, BridgeContract);
+        let client = BridgeContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let fee_collector = Address::generate(&env);
+        let user = Address::generate(&env);
+        let token_address = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_client = token::Client::new(&env, &token_address);
+        token::StellarAssetClient::new(&env, &token_address).mint(&user, &1_000_000_000);
+
+        client.initialize(&admin, &1u32, &0u32, &fee_collector);
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        client.bridge_assets(&user, &token_address, &AssetType::Token, &500_000_000, &request(&recipient, None));
+        assert_eq!(token_client.balance(&client.address), 501_500_000);
+
+        // 0.3% of 500 XLM
+        assert_eq!(client.get_fee_balance(&token_address), 1_500_000);
+
+        let result = client.try_withdraw_fees(&admin, &token_address, &1_000_000);
+        assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+        let result = client.try_withdraw_fees(&fee_collector, &token_address, &1_500_001);
+        assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+
+        client.withdraw_fees(&fee_collector, &token_address, &1_000_000);
+        assert_eq!(token_client.balance(&fee_collector), 1_000_000);
+        assert_eq!(client.get_fee_balance(&token_address), 500_000);
+
+        // The locked amount is untouched by fee withdrawals
+        assert_eq!(token_client.balance(&client.address), 500_500_000);
+        assert_eq!(client.get_liquidity(&token_address), 0);
+    }
+
+    #[test]
+    fn test_recipient_address_formats() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, BridgeContract);
+
+        let account = Address::from_string_bytes(&Bytes::from_slice(
+            &env,
+            b"GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ",
+        ));
+        let contract = Address::generate(&env);
+
+        env.as_contract(&contract_id, || {
+            for address in [account, contract] {
+                // Strkey
+                let decoded = BridgeContract::bytes_to_address(&env, &strkey(&env, &address));
+                assert_eq!(decoded, Ok(address.clone()));
+
+                // XDR ScVal::Address
+                let scval = address.clone().to_xdr(&env);
+                assert_eq!(BridgeContract::bytes_to_address(&env, &scval), Ok(address.clone()));
+
+                // Bare XDR ScAddress
+                let sc_address = scval.slice(4..);
+                assert_eq!(BridgeContract::bytes_to_address(&env, &sc_address), Ok(address.clone()));
+            }
+
+            let garbage = Bytes::from_array(&env, &[1u8; 32]);
+            assert_eq!(BridgeContract::bytes_to_address(&env, &garbage), Err(Error::InvalidRecipient));
+            let bad_strkey = Bytes::from_array(&env, &[b'S'; 56]);
+            assert_eq!(BridgeContract::bytes_to_address(&env, &bad_strkey), Err(Error::InvalidRecipient));
+        });
+    }
+
+    #[test]
+    fn test_complete_bridge_token_unlock() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let token_admin = Address::generate(&env);
+        let token_contract = env.register_stellar_asset_contract_v2(token_admin);
+        let token_address = token_contract.address();
+        let token_client = token::Client::new(&env, &token_address);
+
+        // A confirmed outbound transfer provides the liquidity inbound transfers draw on
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_address).mint(&user, &2_000_000);
+        let lock_id = client.bridge_assets(&user, &token_address, &AssetType::Token, &500, &request(&Bytes::from_array(&env, &[1u8; 32]), None));
+        assert_eq!(client.get_liquidity(&token_address), 0);
+
+        let recipient = Address::generate(&env);
+        let message = unlock_message(&env, &client, AssetType::Token, &token_address, 300, recipient.clone().to_xdr(&env));
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::InsufficientLiquidity)));
+
+        confirm_lock(&env, &client, &validators, &lock_id);
+        assert_eq!(client.get_liquidity(&token_address), 500);
+        client.complete_bridge(&validators[0].0, &message, &signatures);
+
+        assert_eq!(token_client.balance(&recipient), 300);
+        assert_eq!(client.get_liquidity(&token_address), 200);
+        assert_eq!(client.get_message_status(&message.message_id), Some(BridgeStatus::Completed));
+
+        // Can't release more than is locked
+        let mut message = unlock_message(&env, &client, AssetType::Token, &token_address, 201, strkey(&env, &recipient));
+        message.message_id = BytesN::from_array(&env, &[8u8; 32]);
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+        let result = client.try_complete_bridge(&validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::InsufficientLiquidity)));
+    }
+
+    #[test]
+    fn test_complete_bridge_nft_unlock_wraps_foreign_nft() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let nft_contract = Address::generate(&env);
+        let recipient = Address::generate(&env);
+
+        let message = unlock_message(&env, &client, AssetType::NFT, &nft_contract, 42, strkey(&env, &recipient));
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+        client.complete_bridge(&validators[0].0, &message, &signatures);
+
+        let wrapped_token_id = client.wrapped_token_id(&nft_contract, &42, &message.source_chain);
+        let wrapped = client.get_wrapped_nft(&wrapped_token_id).unwrap();
+        assert_eq!(wrapped.owner, recipient);
+        assert_eq!(wrapped.original_token_id, 42);
+        assert_eq!(wrapped.original_chain, message.source_chain);
+        assert_eq!(wrapped.original_contract, nft_contract);
+
+        // Burning the wrapped NFT sends the original back home
+        let home_recipient = Bytes::from_array(&env, &[2u8; 32]);
+        let outsider = Address::generate(&env);
+        let result = client.try_unwrap_nft(&outsider, &wrapped_token_id, &home_recipient);
+        assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+        let original = client.unwrap_nft(&recipient, &wrapped_token_id, &home_recipient);
+        assert_eq!(original, (nft_contract, 42));
+        assert!(client.get_wrapped_nft(&wrapped_token_id).is_none());
+        let result = client.try_unwrap_nft(&recipient, &wrapped_token_id, &home_recipient);
+        assert_eq!(result, Err(Ok(Error::NFTNotWrapped)));
+    }
+
+    /// Creates a token and locks and confirms `amount` of it in the bridge as unlock liquidity
+    fn fund_liquidity(env: &Env, client: &BridgeContractClient, validators: &[(Address, SigningKey)], amount: i128) -> Address {
+        let token_address = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+        let user = Address::generate(env);
+        token::StellarAssetClient::new(env, &token_address).mint(&user, &(amount + 1_000_000_000));
+        let message_id = client.bridge_assets(&user, &token_address, &AssetType::Token, &amount, &request(&Bytes::from_array(env, &[1u8; 32]), None));
+        confirm_lock(env, client, validators, &message_id);
+        token_address
+    }
+
+    fn confirm_lock(env: &Env, client: &BridgeContractClient, validators: &[(Address, SigningKey)], message_id: &BytesN<32>) {
+        let message = client.get_message(message_id).unwrap();
+        let signatures = sign_all(env, client, &validators[..2], &message);
+        client.confirm_bridge(&validators[0].0, message_id, &signatures);
+    }
+
+    fn token_unlock(env: &Env, client: &BridgeContractClient, token_address: &Address, id: u8, source_chain: u32, amount: i128, recipient: &Address) -> BridgeMessage {
+        let mut message = unlock_message(env, client, AssetType::Token, token_address, amount, strkey(env, recipient));
+        message.message_id = BytesN::from_array(env, &[id; 32]);
+        message.source_chain = source_chain;
+        message
+    }
+
+    #[test]
+    fn test_rate_limit_trips_circuit_breaker() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let admin = client.get_config().admin;
+        let token_address = fund_liquidity(&env, &client, &validators, 5_000);
+        let token_client = token::Client::new(&env, &token_address);
+        let recipient = Address::generate(&env);
+
+        let limit = RateLimit { max_amount: 1_000, window: 3_600 };
+        client.set_rate_limit(&admin, &token_address, &None, &Some(limit.clone()));
+        assert_eq!(client.get_rate_limit(&token_address, &None), Some(limit));
+
+        let first = token_unlock(&env, &client, &token_address, 10, 1, 600, &recipient);
+        client.complete_bridge(&validators[0].0, &first, &sign_all(&env, &client, &validators[..2], &first));
+        assert_eq!(token_client.balance(&recipient), 600);
+        assert_eq!(client.get_outflow(&token_address, &None), 600);
+
+        // Breaching the cap pauses the bridge and holds the unlock
+        let second = token_unlock(&env, &client, &token_address, 11, 1, 500, &recipient);
+        client.complete_bridge(&validators[0].0, &second, &sign_all(&env, &client, &validators[..2], &second));
+        assert!(client.get_config().paused);
+        assert_eq!(client.get_message_status(&second.message_id), Some(BridgeStatus::Queued));
+        assert!(client.get_queued_unlock(&second.message_id).is_some());
+        assert_eq!(token_client.balance(&recipient), 600);
+        assert_eq!(client.get_outflow(&token_address, &None), 600);
+
+        let third = token_unlock(&env, &client, &token_address, 12, 1, 10, &recipient);
+        let result = client.try_complete_bridge(&validators[0].0, &third, &sign_all(&env, &client, &validators[..2], &third));
+        assert_eq!(result, Err(Ok(Error::ContractPaused)));
+
+        // Half a window later the cap has refilled enough once the admin resumes
+        env.ledger().with_mut(|li| li.timestamp += 1_800);
+        assert_eq!(client.get_outflow(&token_address, &None), 100);
+        client.set_paused(&admin, &false);
+        client.execute_unlock(&second.message_id);
+
+        assert_eq!(token_client.balance(&recipient), 1_100);
+        assert_eq!(client.get_message_status(&second.message_id), Some(BridgeStatus::Completed));
+        assert!(client.get_queued_unlock(&second.message_id).is_none());
+    }
+
+    #[test]
+    fn test_chain_rate_limit_is_scoped_to_source_chain() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let admin = client.get_config().admin;
+        let token_address = fund_liquidity(&env, &client, &validators, 5_000);
+        let recipient = Address::generate(&env);
+
+        let result = client.try_set_rate_limit(&admin, &token_address, &Some(1), &Some(RateLimit { max_amount: 100, window: 0 }));
+        assert_eq!(result, Err(Ok(Error::InvalidRateLimit)));
+        client.set_rate_limit(&admin, &token_address, &Some(1), &Some(RateLimit { max_amount: 100, window: 3_600 }));
+
+        let other_chain = token_unlock(&env, &client, &token_address, 10, 2, 500, &recipient);
+        client.complete_bridge(&validators[0].0, &other_chain, &sign_all(&env, &client, &validators[..2], &other_chain));
+        assert_eq!(client.get_message_status(&other_chain.message_id), Some(BridgeStatus::Completed));
+        assert_eq!(client.get_outflow(&token_address, &Some(2)), 0);
+
+        let limited_chain = token_unlock(&env, &client, &token_address, 11, 1, 500, &recipient);
+        client.complete_bridge(&validators[0].0, &limited_chain, &sign_all(&env, &client, &validators[..2], &limited_chain));
+        assert_eq!(client.get_message_status(&limited_chain.message_id), Some(BridgeStatus::Queued));
+        assert!(client.get_config().paused);
+    }
+
+    #[test]
+    fn test_lock_rate_limit_is_scoped_to_destination_chain() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, BridgeContract);
+        let client = BridgeContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let user = Address::generate(&env);
+        let token_contract = env.register_stellar_asset_contract_v2(admin.clone());
+        let token_address = token_contract.address();
+        token::StellarAssetClient::new(&env, &token_address).mint(&user, &10_000_000);
+
+        client.initialize(&admin, &1u32, &0u32, &Address::generate(&env));
+        client.set_lock_rate_limit(&admin, &token_address, &1, &Some(RateLimit { max_amount: 1_000, window: 3_600 }));
+
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        client.bridge_assets(&user, &token_address, &AssetType::Token, &600, &request(&recipient, None));
+        assert_eq!(client.get_lock_outflow(&token_address, &1), 600);
+
+        // A second lock to chain 1 would breach its cap; chain 2 is unlimited
+        let result = client.try_bridge_assets(&user, &token_address, &AssetType::Token, &500, &request(&recipient, None));
+        assert_eq!(result, Err(Ok(Error::RateLimitExceeded)));
+        let other_chain = BridgeRequest { dest_chain: 2, recipient: recipient.clone(), fee_token: None };
+        client.bridge_assets(&user, &token_address, &AssetType::Token, &500, &other_chain);
+        assert_eq!(client.get_lock_outflow(&token_address, &1), 600);
+
+        // The cap refills over its window
+        env.ledger().with_mut(|li| li.timestamp += 1_800);
+        client.bridge_assets(&user, &token_address, &AssetType::Token, &500, &request(&recipient, None));
+        assert_eq!(client.get_lock_outflow(&token_address, &1), 600);
+    }
+
+    #[test]
+    fn test_large_transfer_delay_and_veto() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let admin = client.get_config().admin;
+        let token_address = fund_liquidity(&env, &client, &validators, 5_000);
+        let token_client = token::Client::new(&env, &token_address);
+        let recipient = Address::generate(&env);
+
+        client.set_transfer_delay(&admin, &token_address, &Some(TransferDelay { threshold: 1_000, delay: 3_600 }));
+
+        // Below the threshold settles immediately
+        let small = token_unlock(&env, &client, &token_address, 10, 1, 999, &recipient);
+        client.complete_bridge(&validators[0].0, &small, &sign_all(&env, &client, &validators[..2], &small));
+        assert_eq!(token_client.balance(&recipient), 999);
+
+        let vetoed = token_unlock(&env, &client, &token_address, 11, 1, 1_000, &recipient);
+        client.complete_bridge(&validators[0].0, &vetoed, &sign_all(&env, &client, &validators[..2], &vetoed));
+        assert_eq!(client.get_message_status(&vetoed.message_id), Some(BridgeStatus::Queued));
+        assert_eq!(client.try_execute_unlock(&vetoed.message_id), Err(Ok(Error::UnlockNotReady)));
+
+        let outsider = Address::generate(&env);
+        assert_eq!(client.try_veto_unlock(&outsider, &vetoed.message_id), Err(Ok(Error::Unauthorized)));
+        client.veto_unlock(&validators[2].0, &vetoed.message_id);
+        assert_eq!(client.get_message_status(&vetoed.message_id), Some(BridgeStatus::Failed));
+
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+        assert_eq!(client.try_execute_unlock(&vetoed.message_id), Err(Ok(Error::UnlockNotQueued)));
+        assert_eq!(token_client.balance(&recipient), 999);
+        assert_eq!(client.get_liquidity(&token_address), 4_001);
+
+        // A large unlock that isn't vetoed goes through after the delay
+        let delayed = token_unlock(&env, &client, &token_address, 12, 1, 2_000, &recipient);
+        client.complete_bridge(&validators[0].0, &delayed, &sign_all(&env, &client, &validators[..2], &delayed));
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+        client.execute_unlock(&delayed.message_id);
+        assert_eq!(token_client.balance(&recipient), 2_999);
+        assert_eq!(client.get_message_status(&delayed.message_id), Some(BridgeStatus::Completed));
+    }
+
+    fn validator_infos(env: &Env, validators: &[(Address, SigningKey)]) -> Vec<ValidatorInfo> {
+        let mut infos = Vec::new(env);
+        for (validator, key) in validators.iter() {
+            infos.push_back(ValidatorInfo {
+                validator: validator.clone(),
+                public_key: public_key(env, key),
+            });
+        }
+        infos
+    }
+
+    #[test]
+    fn test_remove_validator_respects_threshold() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let admin = client.get_config().admin;
+
+        client.remove_validator(&admin, &validators[2].0);
+        assert_eq!(client.get_validators().len(), 3);
+        assert_eq!(client.get_pending_validator_set().unwrap().validators.len(), 2);
+
+        // Threshold is 2, so the pending set can't shrink further
+        let result = client.try_remove_validator(&admin, &validators[1].0);
+        assert_eq!(result, Err(Ok(Error::InsufficientValidators)));
+
+        env.ledger().with_mut(|li| li.timestamp += ROTATION_DELAY);
+        client.activate_validator_set();
+        assert_eq!(client.get_validators().len(), 2);
+        assert_eq!(client.get_validator_key(&validators[2].0), None);
+    }
+
+    #[test]
+    fn test_validator_set_rotation() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, old_validators) = setup_validators(&env, 3);
+        let admin = client.get_config().admin;
+        let old_version = client.get_validator_set_version();
+
+        // Message created under the old epoch, still in flight during rotation
+        let in_flight = unlock_message(&env, &client, AssetType::NFT, &Address::generate(&env), 1, strkey(&env, &Address::generate(&env)));
+
+        let mut new_validators = std::vec::Vec::new();
+        for i in 0..4 {
+            new_validators.push((Address::generate(&env), signing_key(100 + i)));
+        }
+
+        let result = client.try_propose_validator_set(&admin, &validator_infos(&env, &new_validators), &5u32);
+        assert_eq!(result, Err(Ok(Error::InsufficientValidators)));
+
+        client.propose_validator_set(&admin, &validator_infos(&env, &new_validators), &3u32);
+        assert_eq!(client.try_activate_validator_set(), Err(Ok(Error::RotationNotReady)));
+
+        env.ledger().with_mut(|li| li.timestamp += ROTATION_DELAY);
+        let version = client.activate_validator_set();
+        assert_eq!(version, old_version + 1);
+        assert_eq!(client.get_config().required_signatures, 3);
+        assert_eq!(client.get_validators().len(), 4);
+        assert!(client.get_pending_validator_set().is_none());
+        assert_eq!(client.get_validator_set(&old_version).unwrap().retired_at, Some(env.ledger().timestamp()));
+
+        // The in-flight message is still verified against the set it was created under
+        let signatures = sign_all(&env, &client, &old_validators[..2], &in_flight);
+        client.complete_bridge(&new_validators[0].0, &in_flight, &signatures);
+        assert_eq!(client.get_message_status(&in_flight.message_id), Some(BridgeStatus::Completed));
+
+        // Old keys can't sign for the new epoch
+        let mut message = unlock_message(&env, &client, AssetType::NFT, &Address::generate(&env), 2, strkey(&env, &Address::generate(&env)));
+        message.message_id = BytesN::from_array(&env, &[9u8; 32]);
+        let signatures = sign_all(&env, &client, &old_validators, &message);
+        let result = client.try_complete_bridge(&new_validators[0].0, &message, &signatures);
+        assert_eq!(result, Err(Ok(Error::InvalidSignature)));
+
+        let signatures = sign_all(&env, &client, &new_validators[..3], &message);
+        client.complete_bridge(&new_validators[0].0, &message, &signatures);
+
+        // The old epoch can't vouch for messages stamped after it was retired
+        let mut forged = in_flight.clone();
+        forged.message_id = BytesN::from_array(&env, &[11u8; 32]);
+        forged.timestamp = env.ledger().timestamp() + 1;
+        let signatures = sign_all(&env, &client, &old_validators[..2], &forged);
+        let result = client.try_complete_bridge(&new_validators[0].0, &forged, &signatures);
+        assert_eq!(result, Err(Ok(Error::ValidatorSetExpired)));
+
+        // Once the grace period is over the old epoch is no longer accepted
+        let mut late = in_flight.clone();
+        late.message_id = BytesN::from_array(&env, &[10u8; 32]);
+        env.ledger().with_mut(|li| li.timestamp += EPOCH_GRACE_PERIOD + 1);
+        let signatures = sign_all(&env, &client, &old_validators[..2], &late);
+        let result = client.try_complete_bridge(&new_validators[0].0, &late, &signatures);
+        assert_eq!(result, Err(Ok(Error::ValidatorSetExpired)));
+    }
+
+    #[test]
+    fn test_refund_expired_lock() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, _) = setup_validators(&env, 3);
+        let token_address = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        let token_client = token::Client::new(&env, &token_address);
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_address).mint(&user, &2_000_000);
+
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        let message_id = client.bridge_assets(&user, &token_address, &AssetType::Token, &500, &request(&recipient, None));
+        assert_eq!(client.get_pending_messages(&user), Vec::from_array(&env, [message_id.clone()]));
+        assert_eq!(client.get_message(&message_id).unwrap().sender, user);
+
+        assert_eq!(client.try_refund_expired(&message_id), Err(Ok(Error::MessageNotExpired)));
+
+        env.ledger().with_mut(|li| li.timestamp += DEFAULT_CONFIRMATION_TIMEOUT);
+        client.refund_expired(&message_id);
+
+        // The locked amount comes back; the fee is kept
+        assert_eq!(token_client.balance(&user), 2_000_000 - 1_000_000);
+        assert_eq!(client.get_message_status(&message_id), Some(BridgeStatus::Refunded));
+        assert!(client.get_locked_asset(&message_id).is_none());
+        assert!(client.get_pending_messages(&user).is_empty());
+        assert_eq!(client.get_liquidity(&token_address), 0);
+
+        assert_eq!(client.try_refund_expired(&message_id), Err(Ok(Error::AssetNotLocked)));
+    }
+
+    #[test]
+    fn test_confirmed_lock_is_not_refundable() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let admin = client.get_config().admin;
+        client.set_confirmation_timeout(&admin, &600);
+
+        let token_address = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_address).mint(&user, &3_000_000);
+
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        let message_id = client.bridge_assets(&user, &token_address, &AssetType::Token, &500, &request(&recipient, None));
+        let other_id = client.bridge_assets(&user, &token_address, &AssetType::Token, &100, &request(&recipient, None));
+
+        let message = client.get_message(&message_id).unwrap();
+        let signatures = sign_all(&env, &client, &validators[..2], &message);
+        client.confirm_bridge(&validators[0].0, &message_id, &signatures);
+
+        assert_eq!(client.get_message_status(&message_id), Some(BridgeStatus::Confirmed));
+        assert_eq!(client.get_pending_messages(&user), Vec::from_array(&env, [other_id]));
+
+        env.ledger().with_mut(|li| li.timestamp += 600);
+        assert_eq!(client.try_refund_expired(&message_id), Err(Ok(Error::MessageAlreadyProcessed)));
+        assert_eq!(client.try_cancel_bridge(&user, &message_id), Err(Ok(Error::MessageAlreadyProcessed)));
+        assert_eq!(client.get_locked_asset(&message_id).unwrap().amount, 500);
+    }
+
+    #[test]
+    fn test_cancel_after_refused_unlock_returns_full_lock() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, validators) = setup_validators(&env, 3);
+        let token_address = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        let token_client = token::Client::new(&env, &token_address);
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_address).mint(&user, &2_000_000);
+
+        let recipient = Bytes::from_array(&env, &[1u8; 32]);
+        let message_id = client.bridge_assets(&user, &token_address, &AssetType::Token, &500, &request(&recipient, None));
+
+        // An unlock can't draw on a lock its sender may still take back
+        let receiver = Address::generate(&env);
+        let message = token_unlock(&env, &client, &token_address, 10, 1, 300, &receiver);
+        let result = client.try_complete_bridge(&validators[0].0, &message, &sign_all(&env, &client, &validators[..2], &message));
+        assert_eq!(result, Err(Ok(Error::InsufficientLiquidity)));
+        assert_eq!(token_client.balance(&receiver), 0);
+
+        client.cancel_bridge(&user, &message_id);
+        assert_eq!(token_client.balance(&user), 2_000_000 - 1_000_000);
+        assert_eq!(token_client.balance(&client.address), 1_000_000);
+        assert_eq!(client.get_liquidity(&token_address), 0);
+    }
+
+    #[contracttype]
+    enum MockNftDataKey {
+        Owner(u32),
+    }
+
+    #[contract]
+    struct MockNft;
+
+    #[contractimpl]
+    impl MockNft {
+        pub fn mint(env: Env, to: Address, token_id: u32) {
+            env.storage().persistent().set(&MockNftDataKey::Owner(token_id), &to);
+        }
+
+        pub fn transfer(env: Env, from: Address, to: Address, token_id: u32) {
+            from.require_auth();
+            let owner: Address = env.storage().persistent().get(&MockNftDataKey::Owner(token_id)).unwrap();
+            if owner != from {
+                panic!("Not owner");
+            }
+            env.storage().persistent().set(&MockNftDataKey::Owner(token_id), &to);
+        }
+
+        pub fn owner_of(env: Env, token_id: u32) -> Address {
+            env.storage().persistent().get(&MockNftDataKey::Owner(token_id)).unwrap()
+        }
+    }
+}
@@ -1,10 +1,16 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token, Address, Env, IntoVal, Symbol,
+    contract, contractimpl, contracttype, token, Address, Env, IntoVal, Symbol, Vec,
 };
 
+mod pool;
+
 const BASIS_POINTS: i128 = 10_000;
+const RATE_SCALE: i128 = 1_000_000_000_000;
+const MAX_LATE_PENALTY_BPS: u32 = 100_000;
+// Accrual stops growing a debt here rather than overflowing
+const MAX_DEBT: i128 = i128::MAX / RATE_SCALE;
 
 #[contracttype]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -22,12 +28,56 @@ pub struct Asset {
     pub nft_id: u32,  // Only used for NFT assets
 }
 
+/// How `interest_bps` accrues on a loan
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InterestModel {
+    Simple,        // interest_bps over the whole loan duration, on principal only
+    Compound(u64), // interest_bps per period of this many seconds, on principal plus unpaid interest
+}
+
+/// What `liquidate` does with the collateral of a defaulted loan
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LiquidationMode {
+    Seize, // all collateral goes to the lender
+    // Token collateral only: a descending auction over this many seconds, from
+    // a start price per unit of collateral down to a floor price per unit.
+    // Buyers pay at most the debt, and the rest goes back to the borrower
+    Auction(i128, i128, u64),
+}
+
+/// Oracle pricing for a token-collateralized loan. The loan is healthy while
+/// its collateral value times `liquidation_threshold_bps` covers the debt;
+/// below that anyone may liquidate it for `liquidator_bonus_bps` extra collateral.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MarginTerms {
+    pub oracle: Address,
+    pub loan_feed: Symbol, // prices the repayment token
+    pub collateral_feed: Symbol,
+    pub liquidation_threshold_bps: u32,
+    pub liquidator_bonus_bps: u32,
+}
+
+/// Whether a loan is watched for collateral health
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Margin {
+    None,
+    Oracle(MarginTerms),
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct LoanTerms {
     pub duration_secs: u64,
     pub interest_bps: u32,
     pub max_extension_secs: u64,
+    pub interest_model: InterestModel,
+    pub late_penalty_bps: u32, // replaces interest_bps once the loan is past due
+    pub liquidation: LiquidationMode,
+    pub margin: Margin,
 }
 
 #[contracttype]
@@ -44,6 +94,7 @@ pub enum LoanStatus {
     Active = 1,
     Repaid = 2,
     Liquidated = 3,
+    Auctioning = 4,
 }
 
 #[contracttype]
@@ -90,6 +141,86 @@ pub struct ExtensionRequest {
     pub requested_time: u64,
 }
 
+/// Collateral of a defaulted loan for sale. Buyers pay the lender in the
+/// repayment token; once the debt is covered the rest of the collateral goes
+/// back to the borrower, and whatever is unsold at `end_time` goes to the lender.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollateralAuction {
+    pub loan_id: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub start_price: i128, // per unit of collateral at start_time
+    pub floor_price: i128, // per unit of collateral from end_time
+    pub collateral_remaining: i128,
+}
+
+/// Mirrors `oracle::PriceData` so `get_price` results decode here
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+    pub round_id: u64,
+}
+
+/// Annual borrow rate of a market in bps: `base_bps` when idle, plus
+/// `slope1_bps` by the optimal utilization, plus `slope2_bps` when fully lent
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateCurve {
+    pub base_bps: u32,
+    pub slope1_bps: u32,
+    pub slope2_bps: u32,
+    pub optimal_utilization_bps: u32,
+}
+
+/// Collateral a market lends against. `feed` prices one token, or one NFT of
+/// the collection, in the same reference unit as the market's own feed.
+/// Borrows open up to `ltv_bps` and can be liquidated past `liquidation_ltv_bps`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollateralConfig {
+    pub asset_type: AssetType,
+    pub contract: Address,
+    pub feed: Symbol,
+    pub ltv_bps: u32,
+    pub liquidation_ltv_bps: u32,
+    pub liquidator_bonus_bps: u32,
+}
+
+/// A pool of one token. Lenders own shares of `cash + total_borrows`, which
+/// grows as `borrow_index` accrues interest on what is lent out.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Market {
+    pub market_id: u64,
+    pub creator: Address,
+    pub token: Address,
+    pub oracle: Address,
+    pub feed: Symbol,
+    pub rate_curve: RateCurve,
+    pub collateral: Vec<CollateralConfig>,
+    pub cash: i128,
+    pub total_borrows: i128,
+    pub total_shares: i128,
+    pub borrow_index: i128, // RATE_SCALE at creation
+    pub last_update: u64,
+}
+
+/// A draw on a market. Debt is `scaled_debt` at the market's borrow index.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolLoan {
+    pub loan_id: u64,
+    pub market_id: u64,
+    pub borrower: Address,
+    pub collateral: Asset,
+    pub scaled_debt: i128,
+    pub start_time: u64,
+    pub status: LoanStatus,
+}
+
 #[contracttype]
 pub enum DataKey {
     Offer(u64),
@@ -97,6 +228,12 @@ pub enum DataKey {
     Loan(u64),
     LoanCount,
     ExtensionRequest(u64),
+    CollateralAuction(u64),
+    Market(u64),
+    MarketCount,
+    Shares(u64, Address),
+    PoolLoan(u64),
+    PoolLoanCount,
 }
 
 #[contract]
@@ -124,6 +261,41 @@ impl LendingContract {
         if principal <= 0 {
             panic!("Principal must be greater than zero");
         }
+        if let InterestModel::Compound(period) = terms.interest_model {
+            if period == 0 {
+                panic!("Compounding period must be greater than zero");
+            }
+        }
+        if terms.late_penalty_bps > MAX_LATE_PENALTY_BPS {
+            panic!("Late penalty rate is too high");
+        }
+        if let LiquidationMode::Auction(start_price, floor_price, duration) = terms.liquidation {
+            if collateral_asset.asset_type != AssetType::Token {
+                panic!("Auction liquidation requires token collateral");
+            }
+            if floor_price <= 0 {
+                panic!("Auction floor price must be greater than zero");
+            }
+            if start_price < floor_price {
+                panic!("Auction must start at or above its floor price");
+            }
+            if duration == 0 {
+                panic!("Auction duration must be greater than zero");
+            }
+        }
+        if let Margin::Oracle(margin) = &terms.margin {
+            if collateral_asset.asset_type != AssetType::Token {
+                panic!("Margin terms require token collateral");
+            }
+            if margin.liquidation_threshold_bps == 0
+                || margin.liquidation_threshold_bps as i128 > BASIS_POINTS
+            {
+                panic!("Liquidation threshold must be between zero and 100%");
+            }
+            if margin.liquidator_bonus_bps as i128 >= BASIS_POINTS {
+                panic!("Liquidator bonus must be below 100%");
+            }
+        }
 
         match loan_asset.asset_type {
             AssetType::Token => {
@@ -156,14 +328,11 @@ impl LendingContract {
             created_time,
         };
 
-        Self::transfer_asset(
-            &env,
-            &loan_asset,
-            &lender,
-            &env.current_contract_address(),
-        );
+        Self::transfer_asset(&env, &loan_asset, &lender, &env.current_contract_address());
 
-        env.storage().persistent().set(&DataKey::Offer(offer_id), &offer);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Offer(offer_id), &offer);
         offer_id
     }
 
@@ -184,7 +353,9 @@ impl LendingContract {
         }
 
         offer.status = OfferStatus::Cancelled;
-        env.storage().persistent().set(&DataKey::Offer(offer_id), &offer);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Offer(offer_id), &offer);
 
         Self::transfer_asset(
             &env,
@@ -214,32 +385,7 @@ impl LendingContract {
             &env.current_contract_address(),
         );
 
-        let loan_id = Self::next_loan_id(&env);
-        let start_time = env.ledger().timestamp();
-        let due_time = start_time + offer.terms.duration_secs;
-
-        let loan = Loan {
-            loan_id,
-            offer_id,
-            lender: offer.lender.clone(),
-            borrower: borrower.clone(),
-            loan_asset: offer.loan_asset.clone(),
-            collateral_asset: offer.collateral_asset.clone(),
-            repayment_token: offer.repayment_token.clone(),
-            principal: offer.principal,
-            outstanding_principal: offer.principal,
-            accrued_interest: 0,
-            start_time,
-            due_time,
-            last_accrual_time: start_time,
-            status: LoanStatus::Active,
-            terms: offer.terms.clone(),
-            extension_secs: 0,
-        };
-
-        offer.status = OfferStatus::Accepted;
-        env.storage().persistent().set(&DataKey::Offer(offer_id), &offer);
-        env.storage().persistent().set(&DataKey::Loan(loan_id), &loan);
+        let loan_id = Self::open_loan(&env, &mut offer, &borrower);
 
         Self::transfer_asset(
             &env,
@@ -281,19 +427,7 @@ impl LendingContract {
         let token_client = token::Client::new(&env, &loan.repayment_token);
         token_client.transfer(&borrower, &loan.lender, &amount);
 
-        let mut remaining = amount;
-        if loan.accrued_interest > 0 {
-            let pay_interest = if remaining < loan.accrued_interest {
-                remaining
-            } else {
-                loan.accrued_interest
-            };
-            loan.accrued_interest -= pay_interest;
-            remaining -= pay_interest;
-        }
-        if remaining > 0 {
-            loan.outstanding_principal -= remaining;
-        }
+        Self::apply_payment(&mut loan, amount);
 
         if loan.outstanding_principal == 0 && loan.accrued_interest == 0 {
             loan.status = LoanStatus::Repaid;
@@ -310,7 +444,9 @@ impl LendingContract {
             }
         }
 
-        env.storage().persistent().set(&DataKey::Loan(loan_id), &loan);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
     }
 
     pub fn liquidate(env: Env, lender: Address, loan_id: u64) {
@@ -332,20 +468,250 @@ impl LendingContract {
             panic!("Loan is not in default");
         }
 
+        match loan.terms.liquidation {
+            LiquidationMode::Seize => {
+                loan.status = LoanStatus::Liquidated;
+                Self::transfer_asset(
+                    &env,
+                    &loan.collateral_asset,
+                    &env.current_contract_address(),
+                    &loan.lender,
+                );
+            }
+            LiquidationMode::Auction(start_price, floor_price, duration) => {
+                // Only an auction needs the debt, to know when buyers have covered it
+                Self::accrue_interest(&env, &mut loan);
+                let start_time = env.ledger().timestamp();
+                let auction = CollateralAuction {
+                    loan_id,
+                    start_time,
+                    end_time: start_time + duration,
+                    start_price,
+                    floor_price,
+                    collateral_remaining: loan.collateral_asset.amount,
+                };
+                loan.status = LoanStatus::Auctioning;
+                env.storage()
+                    .persistent()
+                    .set(&DataKey::CollateralAuction(loan_id), &auction);
+            }
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+        env.storage()
+            .persistent()
+            .remove(&DataKey::ExtensionRequest(loan_id));
+    }
+
+    /// Collateral value times the liquidation threshold over the debt, both
+    /// at oracle prices, in bps: below 10_000 the loan can be liquidated
+    pub fn health_factor(env: Env, loan_id: u64) -> i128 {
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+        Self::accrue_interest(&env, &mut loan);
+        Self::health(&env, &loan)
+    }
+
+    /// Lets anyone close an unhealthy loan by paying off its debt. The
+    /// liquidator receives collateral worth the debt plus the bonus and the
+    /// borrower gets back the rest.
+    pub fn liquidate_unhealthy(env: Env, liquidator: Address, loan_id: u64) {
+        liquidator.require_auth();
+
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Active {
+            panic!("Loan is not active");
+        }
+
         Self::accrue_interest(&env, &mut loan);
+        if Self::health(&env, &loan) >= BASIS_POINTS {
+            panic!("Loan is healthy");
+        }
+
+        let margin = match &loan.terms.margin {
+            Margin::Oracle(margin) => margin,
+            Margin::None => panic!("Loan has no margin terms"),
+        };
+        let debt = loan.outstanding_principal + loan.accrued_interest;
+        let debt_value = debt * pool::price(&env, &margin.oracle, &margin.loan_feed);
+        let collateral_price = pool::price(&env, &margin.oracle, &margin.collateral_feed);
+        let seized = (debt_value * (BASIS_POINTS + margin.liquidator_bonus_bps as i128)
+            / BASIS_POINTS
+            / collateral_price)
+            .min(loan.collateral_asset.amount);
+
+        token::Client::new(&env, &loan.repayment_token).transfer(&liquidator, &loan.lender, &debt);
+
+        let collateral = token::Client::new(&env, &loan.collateral_asset.contract);
+        collateral.transfer(&env.current_contract_address(), &liquidator, &seized);
+        let surplus = loan.collateral_asset.amount - seized;
+        if surplus > 0 {
+            collateral.transfer(&env.current_contract_address(), &loan.borrower, &surplus);
+        }
+
+        Self::apply_payment(&mut loan, debt);
         loan.status = LoanStatus::Liquidated;
 
-        Self::transfer_asset(
-            &env,
-            &loan.collateral_asset,
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+        env.storage()
+            .persistent()
+            .remove(&DataKey::ExtensionRequest(loan_id));
+    }
+
+    /// Adds token collateral to an active loan
+    pub fn top_up_collateral(env: Env, borrower: Address, loan_id: u64, amount: i128) {
+        borrower.require_auth();
+
+        if amount <= 0 {
+            panic!("Amount must be greater than zero");
+        }
+
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Active {
+            panic!("Loan is not active");
+        }
+        if loan.borrower != borrower {
+            panic!("Only borrower can top up collateral");
+        }
+        if loan.collateral_asset.asset_type != AssetType::Token {
+            panic!("Only token collateral can be topped up");
+        }
+
+        token::Client::new(&env, &loan.collateral_asset.contract).transfer(
+            &borrower,
             &env.current_contract_address(),
-            &loan.lender,
+            &amount,
         );
+        loan.collateral_asset.amount += amount;
 
-        env.storage().persistent().set(&DataKey::Loan(loan_id), &loan);
         env.storage()
             .persistent()
-            .remove(&DataKey::ExtensionRequest(loan_id));
+            .set(&DataKey::Loan(loan_id), &loan);
+    }
+
+    /// Buys up to `amount` units of a defaulted loan's collateral at the
+    /// current auction price, never more than it takes to cover the debt.
+    /// Returns the price paid.
+    pub fn buy_collateral(env: Env, buyer: Address, loan_id: u64, amount: i128) -> i128 {
+        buyer.require_auth();
+
+        if amount <= 0 {
+            panic!("Amount must be greater than zero");
+        }
+
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+        let mut auction: CollateralAuction = env
+            .storage()
+            .persistent()
+            .get(&DataKey::CollateralAuction(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Auctioning {
+            panic!("Collateral is not being auctioned");
+        }
+        if env.ledger().timestamp() >= auction.end_time {
+            panic!("Auction has ended");
+        }
+
+        let unit_price = Self::auction_price(&env, &auction);
+        let debt = loan.outstanding_principal + loan.accrued_interest;
+
+        let mut amount = amount.min(auction.collateral_remaining);
+        let mut cost = amount * unit_price;
+        if cost > debt {
+            amount = Self::ceil_div(debt, unit_price).min(auction.collateral_remaining);
+            cost = debt;
+        }
+
+        token::Client::new(&env, &loan.repayment_token).transfer(&buyer, &loan.lender, &cost);
+        token::Client::new(&env, &loan.collateral_asset.contract).transfer(
+            &env.current_contract_address(),
+            &buyer,
+            &amount,
+        );
+
+        Self::apply_payment(&mut loan, cost);
+        auction.collateral_remaining -= amount;
+
+        if loan.outstanding_principal == 0 && loan.accrued_interest == 0 {
+            loan.status = LoanStatus::Liquidated;
+            if auction.collateral_remaining > 0 {
+                token::Client::new(&env, &loan.collateral_asset.contract).transfer(
+                    &env.current_contract_address(),
+                    &loan.borrower,
+                    &auction.collateral_remaining,
+                );
+                auction.collateral_remaining = 0;
+            }
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+        env.storage()
+            .persistent()
+            .set(&DataKey::CollateralAuction(loan_id), &auction);
+        cost
+    }
+
+    /// Closes a collateral auction that ran out of time, handing the unsold
+    /// collateral to the lender
+    pub fn settle_liquidation(env: Env, loan_id: u64) {
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+        let mut auction: CollateralAuction = env
+            .storage()
+            .persistent()
+            .get(&DataKey::CollateralAuction(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Auctioning {
+            panic!("Collateral is not being auctioned");
+        }
+        if env.ledger().timestamp() < auction.end_time {
+            panic!("Auction has not ended");
+        }
+
+        loan.status = LoanStatus::Liquidated;
+        if auction.collateral_remaining > 0 {
+            token::Client::new(&env, &loan.collateral_asset.contract).transfer(
+                &env.current_contract_address(),
+                &loan.lender,
+                &auction.collateral_remaining,
+            );
+            auction.collateral_remaining = 0;
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+        env.storage()
+            .persistent()
+            .set(&DataKey::CollateralAuction(loan_id), &auction);
     }
 
     pub fn request_extension(env: Env, borrower: Address, loan_id: u64, extra_secs: u64) {
@@ -416,47 +782,638 @@ impl LendingContract {
         loan.due_time += request.requested_extension_secs;
         loan.extension_secs += request.requested_extension_secs;
 
-        env.storage().persistent().set(&DataKey::Loan(loan_id), &loan);
         env.storage()
             .persistent()
-            .remove(&DataKey::ExtensionRequest(loan_id));
-    }
-
-    pub fn get_offer(env: Env, offer_id: u64) -> Option<LoanOffer> {
-        env.storage().persistent().get(&DataKey::Offer(offer_id))
-    }
-
-    pub fn get_loan(env: Env, loan_id: u64) -> Option<Loan> {
-        env.storage().persistent().get(&DataKey::Loan(loan_id))
-    }
-
-    pub fn get_extension_request(env: Env, loan_id: u64) -> Option<ExtensionRequest> {
+            .set(&DataKey::Loan(loan_id), &loan);
         env.storage()
             .persistent()
-            .get(&DataKey::ExtensionRequest(loan_id))
+            .remove(&DataKey::ExtensionRequest(loan_id));
     }
 
-    fn next_offer_id(env: &Env) -> u64 {
-        let mut id: u64 = env
+    /// Moves an active token loan onto `new_offer_id`. The new principal pays
+    /// off the old lender, the borrower covers any shortfall and receives any
+    /// excess, and the collateral stays in escrow throughout. Returns the new
+    /// loan id.
+    pub fn refinance(env: Env, borrower: Address, loan_id: u64, new_offer_id: u64) -> u64 {
+        borrower.require_auth();
+
+        let mut loan: Loan = env
             .storage()
             .persistent()
-            .get(&DataKey::OfferCount)
-            .unwrap_or(0);
-        id += 1;
-        env.storage().persistent().set(&DataKey::OfferCount, &id);
-        id
-    }
-
-    fn next_loan_id(env: &Env) -> u64 {
-        let mut id: u64 = env
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+        let mut offer: LoanOffer = env
             .storage()
             .persistent()
-            .get(&DataKey::LoanCount)
-            .unwrap_or(0);
-        id += 1;
-        env.storage().persistent().set(&DataKey::LoanCount, &id);
-        id
-    }
+            .get(&DataKey::Offer(new_offer_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Active {
+            panic!("Loan is not active");
+        }
+        if loan.borrower != borrower {
+            panic!("Only borrower can refinance");
+        }
+        if offer.status != OfferStatus::Open {
+            panic!("Offer is not open");
+        }
+        if loan.loan_asset.asset_type != AssetType::Token
+            || offer.loan_asset.asset_type != AssetType::Token
+        {
+            panic!("Only token loans can be refinanced");
+        }
+        if offer.loan_asset.contract != loan.repayment_token {
+            panic!("Offer must lend the repayment token");
+        }
+
+        let held = loan.collateral_asset.clone();
+        let wanted = &offer.collateral_asset;
+        if wanted.asset_type != held.asset_type
+            || wanted.contract != held.contract
+            || wanted.nft_id != held.nft_id
+            || wanted.amount > held.amount
+        {
+            panic!("Offer collateral does not match loan");
+        }
+        let released = held.amount - wanted.amount;
+
+        Self::accrue_interest(&env, &mut loan);
+        let debt = loan.outstanding_principal + loan.accrued_interest;
+
+        let token_client = token::Client::new(&env, &loan.repayment_token);
+        let contract = env.current_contract_address();
+        if offer.principal >= debt {
+            token_client.transfer(&contract, &loan.lender, &debt);
+            if offer.principal > debt {
+                token_client.transfer(&contract, &borrower, &(offer.principal - debt));
+            }
+        } else {
+            token_client.transfer(&contract, &loan.lender, &offer.principal);
+            token_client.transfer(&borrower, &loan.lender, &(debt - offer.principal));
+        }
+
+        if released > 0 {
+            token::Client::new(&env, &held.contract).transfer(&contract, &borrower, &released);
+        }
+
+        Self::apply_payment(&mut loan, debt);
+        loan.status = LoanStatus::Repaid;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+        env.storage()
+            .persistent()
+            .remove(&DataKey::ExtensionRequest(loan_id));
+
+        Self::open_loan(&env, &mut offer, &borrower)
+    }
+
+    /// Sells the lender's side of an active loan to `new_lender` for `price`
+    /// of `payment_token`, paid to the lender in the same call. The new lender
+    /// then receives repayments and decides on extensions and liquidation.
+    pub fn transfer_loan(
+        env: Env,
+        lender: Address,
+        loan_id: u64,
+        new_lender: Address,
+        payment_token: Address,
+        price: i128,
+    ) {
+        lender.require_auth();
+        new_lender.require_auth();
+
+        if price < 0 {
+            panic!("Price cannot be negative");
+        }
+
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Active {
+            panic!("Loan is not active");
+        }
+        if loan.lender != lender {
+            panic!("Only lender can transfer loan");
+        }
+        if new_lender == lender {
+            panic!("Loan already belongs to this lender");
+        }
+
+        if price > 0 {
+            token::Client::new(&env, &payment_token).transfer(&new_lender, &lender, &price);
+        }
+
+        loan.lender = new_lender;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+    }
+
+    /// Opens a lending pool in `token`. Borrows are valued with `oracle`,
+    /// `feed` pricing the pool token, and capped at each collateral's LTV.
+    pub fn create_market(
+        env: Env,
+        creator: Address,
+        token: Address,
+        oracle: Address,
+        feed: Symbol,
+        rate_curve: RateCurve,
+        collateral: Vec<CollateralConfig>,
+    ) -> u64 {
+        creator.require_auth();
+
+        if collateral.is_empty() {
+            panic!("Market must accept collateral");
+        }
+        for config in collateral.iter() {
+            if config.ltv_bps == 0 || config.ltv_bps as i128 >= BASIS_POINTS {
+                panic!("LTV must be between zero and 100%");
+            }
+            if config.liquidation_ltv_bps < config.ltv_bps
+                || config.liquidation_ltv_bps as i128 >= BASIS_POINTS
+            {
+                panic!("Liquidation LTV must be between the LTV and 100%");
+            }
+        }
+        if rate_curve.optimal_utilization_bps as i128 >= BASIS_POINTS {
+            panic!("Optimal utilization must be below 100%");
+        }
+
+        let mut market_id: u64 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::MarketCount)
+            .unwrap_or(0);
+        market_id += 1;
+        env.storage()
+            .persistent()
+            .set(&DataKey::MarketCount, &market_id);
+
+        let market = Market {
+            market_id,
+            creator,
+            token,
+            oracle,
+            feed,
+            rate_curve,
+            collateral,
+            cash: 0,
+            total_borrows: 0,
+            total_shares: 0,
+            borrow_index: RATE_SCALE,
+            last_update: env.ledger().timestamp(),
+        };
+        env.storage()
+            .persistent()
+            .set(&DataKey::Market(market_id), &market);
+        market_id
+    }
+
+    /// Adds `amount` to a market and returns the shares minted for it
+    pub fn deposit(env: Env, lender: Address, market_id: u64, amount: i128) -> i128 {
+        lender.require_auth();
+
+        if amount <= 0 {
+            panic!("Deposit must be greater than zero");
+        }
+
+        let mut market = Self::load_market(&env, market_id);
+        pool::accrue(&env, &mut market);
+
+        let assets = pool::total_assets(&market);
+        let shares = if market.total_shares == 0 || assets == 0 {
+            amount
+        } else {
+            amount * market.total_shares / assets
+        };
+        if shares == 0 {
+            panic!("Deposit too small");
+        }
+
+        token::Client::new(&env, &market.token).transfer(
+            &lender,
+            &env.current_contract_address(),
+            &amount,
+        );
+
+        market.cash += amount;
+        market.total_shares += shares;
+        let key = DataKey::Shares(market_id, lender);
+        let held: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        env.storage().persistent().set(&key, &(held + shares));
+        env.storage()
+            .persistent()
+            .set(&DataKey::Market(market_id), &market);
+        shares
+    }
+
+    /// Redeems `shares` for their part of the market, as far as the cash
+    /// not lent out allows. Returns the amount paid out.
+    pub fn withdraw(env: Env, lender: Address, market_id: u64, shares: i128) -> i128 {
+        lender.require_auth();
+
+        if shares <= 0 {
+            panic!("Shares must be greater than zero");
+        }
+
+        let key = DataKey::Shares(market_id, lender.clone());
+        let held: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        if shares > held {
+            panic!("Insufficient shares");
+        }
+
+        let mut market = Self::load_market(&env, market_id);
+        pool::accrue(&env, &mut market);
+
+        let amount = shares * pool::total_assets(&market) / market.total_shares;
+        if amount > market.cash {
+            panic!("Insufficient liquidity");
+        }
+
+        market.cash -= amount;
+        market.total_shares -= shares;
+        env.storage().persistent().set(&key, &(held - shares));
+        env.storage()
+            .persistent()
+            .set(&DataKey::Market(market_id), &market);
+
+        token::Client::new(&env, &market.token).transfer(
+            &env.current_contract_address(),
+            &lender,
+            &amount,
+        );
+        amount
+    }
+
+    /// Escrows `collateral` and lends `amount` from the market, up to the
+    /// collateral's oracle value times its LTV. Returns the pool loan id.
+    pub fn borrow(
+        env: Env,
+        borrower: Address,
+        market_id: u64,
+        collateral: Asset,
+        amount: i128,
+    ) -> u64 {
+        borrower.require_auth();
+
+        if amount <= 0 {
+            panic!("Borrow must be greater than zero");
+        }
+        Self::validate_asset(&collateral);
+
+        let mut market = Self::load_market(&env, market_id);
+        pool::accrue(&env, &mut market);
+
+        let config = pool::find_collateral(&market, &collateral)
+            .unwrap_or_else(|| panic!("Collateral not accepted"));
+        let limit = pool::collateral_value(&env, &market, &config, &collateral)
+            * config.ltv_bps as i128
+            / BASIS_POINTS;
+        if pool::loan_value(&env, &market, amount) > limit {
+            panic!("Borrow exceeds loan-to-value limit");
+        }
+        if amount > market.cash {
+            panic!("Insufficient liquidity");
+        }
+
+        Self::transfer_asset(
+            &env,
+            &collateral,
+            &borrower,
+            &env.current_contract_address(),
+        );
+
+        let mut loan_id: u64 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::PoolLoanCount)
+            .unwrap_or(0);
+        loan_id += 1;
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolLoanCount, &loan_id);
+
+        let loan = PoolLoan {
+            loan_id,
+            market_id,
+            borrower: borrower.clone(),
+            collateral,
+            // Round up so the recorded debt never falls below what was lent
+            scaled_debt: Self::ceil_div(amount * RATE_SCALE, market.borrow_index),
+            start_time: env.ledger().timestamp(),
+            status: LoanStatus::Active,
+        };
+
+        market.cash -= amount;
+        market.total_borrows += amount;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Market(market_id), &market);
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolLoan(loan_id), &loan);
+
+        token::Client::new(&env, &market.token).transfer(
+            &env.current_contract_address(),
+            &borrower,
+            &amount,
+        );
+        loan_id
+    }
+
+    /// Pays down a pool loan. The collateral is released once it is cleared.
+    pub fn repay_borrow(env: Env, borrower: Address, loan_id: u64, amount: i128) {
+        borrower.require_auth();
+
+        if amount <= 0 {
+            panic!("Repayment amount must be greater than zero");
+        }
+
+        let mut loan: PoolLoan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::PoolLoan(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Active {
+            panic!("Loan is not active");
+        }
+        if loan.borrower != borrower {
+            panic!("Only borrower can repay");
+        }
+
+        let mut market = Self::load_market(&env, loan.market_id);
+        pool::accrue(&env, &mut market);
+
+        let debt = pool::debt(&market, loan.scaled_debt);
+        if amount > debt {
+            panic!("Repayment exceeds amount due");
+        }
+
+        token::Client::new(&env, &market.token).transfer(
+            &borrower,
+            &env.current_contract_address(),
+            &amount,
+        );
+
+        if amount == debt {
+            loan.scaled_debt = 0;
+            loan.status = LoanStatus::Repaid;
+        } else {
+            loan.scaled_debt -= amount * RATE_SCALE / market.borrow_index;
+        }
+        market.cash += amount;
+        market.total_borrows = (market.total_borrows - amount).max(0);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Market(loan.market_id), &market);
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolLoan(loan_id), &loan);
+
+        if loan.status == LoanStatus::Repaid {
+            Self::transfer_asset(
+                &env,
+                &loan.collateral,
+                &env.current_contract_address(),
+                &loan.borrower,
+            );
+        }
+    }
+
+    /// Collateral value times its liquidation LTV over the debt of a pool
+    /// loan, both at oracle prices, in bps: below 10_000 it can be liquidated
+    pub fn get_borrow_health(env: Env, loan_id: u64) -> i128 {
+        let loan: PoolLoan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::PoolLoan(loan_id))
+            .unwrap();
+        let market = Self::get_market(env.clone(), loan.market_id);
+        pool::health(&env, &market, &loan)
+    }
+
+    /// Lets anyone close an unhealthy pool loan by repaying its debt to the
+    /// market. The liquidator receives token collateral worth the debt plus
+    /// the bonus, the borrower the rest; an NFT goes to the liquidator whole.
+    pub fn liquidate_borrow(env: Env, liquidator: Address, loan_id: u64) {
+        liquidator.require_auth();
+
+        let mut loan: PoolLoan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::PoolLoan(loan_id))
+            .unwrap();
+
+        if loan.status != LoanStatus::Active {
+            panic!("Loan is not active");
+        }
+
+        let mut market = Self::load_market(&env, loan.market_id);
+        pool::accrue(&env, &mut market);
+        if pool::health(&env, &market, &loan) >= BASIS_POINTS {
+            panic!("Loan is healthy");
+        }
+
+        let debt = pool::debt(&market, loan.scaled_debt);
+        token::Client::new(&env, &market.token).transfer(
+            &liquidator,
+            &env.current_contract_address(),
+            &debt,
+        );
+
+        match loan.collateral.asset_type {
+            AssetType::Token => {
+                let config = pool::find_collateral(&market, &loan.collateral).unwrap();
+                let collateral_price = pool::price(&env, &market.oracle, &config.feed);
+                let seized = (pool::loan_value(&env, &market, debt)
+                    * (BASIS_POINTS + config.liquidator_bonus_bps as i128)
+                    / BASIS_POINTS
+                    / collateral_price)
+                    .min(loan.collateral.amount);
+
+                let collateral = token::Client::new(&env, &loan.collateral.contract);
+                collateral.transfer(&env.current_contract_address(), &liquidator, &seized);
+                let surplus = loan.collateral.amount - seized;
+                if surplus > 0 {
+                    collateral.transfer(&env.current_contract_address(), &loan.borrower, &surplus);
+                }
+            }
+            AssetType::NFT => Self::transfer_asset(
+                &env,
+                &loan.collateral,
+                &env.current_contract_address(),
+                &liquidator,
+            ),
+        }
+
+        loan.scaled_debt = 0;
+        loan.status = LoanStatus::Liquidated;
+        market.cash += debt;
+        market.total_borrows = (market.total_borrows - debt).max(0);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Market(loan.market_id), &market);
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolLoan(loan_id), &loan);
+    }
+
+    pub fn get_offer(env: Env, offer_id: u64) -> Option<LoanOffer> {
+        env.storage().persistent().get(&DataKey::Offer(offer_id))
+    }
+
+    pub fn get_loan(env: Env, loan_id: u64) -> Option<Loan> {
+        env.storage().persistent().get(&DataKey::Loan(loan_id))
+    }
+
+    pub fn get_extension_request(env: Env, loan_id: u64) -> Option<ExtensionRequest> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::ExtensionRequest(loan_id))
+    }
+
+    /// Principal plus interest accrued up to now
+    pub fn get_amount_due(env: Env, loan_id: u64) -> i128 {
+        let mut loan: Loan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Loan(loan_id))
+            .unwrap();
+        if loan.status == LoanStatus::Active {
+            Self::accrue_interest(&env, &mut loan);
+        }
+        loan.outstanding_principal + loan.accrued_interest
+    }
+
+    pub fn get_collateral_auction(env: Env, loan_id: u64) -> Option<CollateralAuction> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::CollateralAuction(loan_id))
+    }
+
+    /// Current price of one unit of the auctioned collateral
+    pub fn get_auction_price(env: Env, loan_id: u64) -> i128 {
+        let auction: CollateralAuction = env
+            .storage()
+            .persistent()
+            .get(&DataKey::CollateralAuction(loan_id))
+            .unwrap();
+        Self::auction_price(&env, &auction)
+    }
+
+    /// Market with interest accrued up to now
+    pub fn get_market(env: Env, market_id: u64) -> Market {
+        let mut market = Self::load_market(&env, market_id);
+        pool::accrue(&env, &mut market);
+        market
+    }
+
+    /// Current annual borrow rate of a market in bps
+    pub fn get_borrow_rate(env: Env, market_id: u64) -> i128 {
+        pool::borrow_rate(&Self::load_market(&env, market_id))
+    }
+
+    pub fn get_shares(env: Env, market_id: u64, lender: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Shares(market_id, lender))
+            .unwrap_or(0)
+    }
+
+    /// What a lender's shares are worth now
+    pub fn get_deposit_value(env: Env, market_id: u64, lender: Address) -> i128 {
+        let market = Self::get_market(env.clone(), market_id);
+        if market.total_shares == 0 {
+            return 0;
+        }
+        Self::get_shares(env, market_id, lender) * pool::total_assets(&market) / market.total_shares
+    }
+
+    pub fn get_pool_loan(env: Env, loan_id: u64) -> Option<PoolLoan> {
+        env.storage().persistent().get(&DataKey::PoolLoan(loan_id))
+    }
+
+    /// Amount needed to clear a pool loan now
+    pub fn get_borrow_debt(env: Env, loan_id: u64) -> i128 {
+        let loan: PoolLoan = env
+            .storage()
+            .persistent()
+            .get(&DataKey::PoolLoan(loan_id))
+            .unwrap();
+        let market = Self::get_market(env, loan.market_id);
+        pool::debt(&market, loan.scaled_debt)
+    }
+
+    fn load_market(env: &Env, market_id: u64) -> Market {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Market(market_id))
+            .unwrap_or_else(|| panic!("Market not found"))
+    }
+
+    /// Records a loan on the terms of `offer` and marks the offer accepted.
+    /// Moving the assets is left to the caller.
+    fn open_loan(env: &Env, offer: &mut LoanOffer, borrower: &Address) -> u64 {
+        let loan_id = Self::next_loan_id(env);
+        let start_time = env.ledger().timestamp();
+        let due_time = start_time + offer.terms.duration_secs;
+
+        let loan = Loan {
+            loan_id,
+            offer_id: offer.offer_id,
+            lender: offer.lender.clone(),
+            borrower: borrower.clone(),
+            loan_asset: offer.loan_asset.clone(),
+            collateral_asset: offer.collateral_asset.clone(),
+            repayment_token: offer.repayment_token.clone(),
+            principal: offer.principal,
+            outstanding_principal: offer.principal,
+            accrued_interest: 0,
+            start_time,
+            due_time,
+            last_accrual_time: start_time,
+            status: LoanStatus::Active,
+            terms: offer.terms.clone(),
+            extension_secs: 0,
+        };
+
+        offer.status = OfferStatus::Accepted;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Offer(offer.offer_id), offer);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Loan(loan_id), &loan);
+        loan_id
+    }
+
+    fn next_offer_id(env: &Env) -> u64 {
+        let mut id: u64 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::OfferCount)
+            .unwrap_or(0);
+        id += 1;
+        env.storage().persistent().set(&DataKey::OfferCount, &id);
+        id
+    }
+
+    fn next_loan_id(env: &Env) -> u64 {
+        let mut id: u64 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::LoanCount)
+            .unwrap_or(0);
+        id += 1;
+        env.storage().persistent().set(&DataKey::LoanCount, &id);
+        id
+    }
 
     fn validate_asset(asset: &Asset) {
         match asset.asset_type {
@@ -493,29 +1450,135 @@ impl LendingContract {
         }
     }
 
+    fn health(env: &Env, loan: &Loan) -> i128 {
+        let margin = match &loan.terms.margin {
+            Margin::Oracle(margin) => margin,
+            Margin::None => panic!("Loan has no margin terms"),
+        };
+        let debt = loan.outstanding_principal + loan.accrued_interest;
+        if debt == 0 {
+            return i128::MAX;
+        }
+        let debt_value = debt * pool::price(env, &margin.oracle, &margin.loan_feed);
+        let collateral_value = loan.collateral_asset.amount
+            * pool::price(env, &margin.oracle, &margin.collateral_feed);
+        collateral_value * margin.liquidation_threshold_bps as i128 / debt_value
+    }
+
+    /// Pays accrued interest first, then principal
+    fn apply_payment(loan: &mut Loan, amount: i128) {
+        let pay_interest = amount.min(loan.accrued_interest);
+        loan.accrued_interest -= pay_interest;
+        loan.outstanding_principal -= amount - pay_interest;
+    }
+
+    fn auction_price(env: &Env, auction: &CollateralAuction) -> i128 {
+        let now = env.ledger().timestamp();
+        if now >= auction.end_time {
+            return auction.floor_price;
+        }
+        let elapsed = (now - auction.start_time) as i128;
+        let duration = (auction.end_time - auction.start_time) as i128;
+        auction.start_price - (auction.start_price - auction.floor_price) * elapsed / duration
+    }
+
+    fn ceil_div(a: i128, b: i128) -> i128 {
+        (a + b - 1) / b
+    }
+
+    /// Accrues at `interest_bps` up to `due_time` and at `late_penalty_bps`
+    /// after it. With no penalty rate, accrual stops at `due_time`.
     fn accrue_interest(env: &Env, loan: &mut Loan) {
         let now = env.ledger().timestamp();
-        let accrual_end = if now < loan.due_time {
-            now
-        } else {
-            loan.due_time
-        };
-        if accrual_end <= loan.last_accrual_time {
+        if loan.last_accrual_time < loan.due_time {
+            let end = now.min(loan.due_time);
+            Self::accrue_span(loan, end, loan.terms.interest_bps);
+        }
+        if now > loan.due_time && loan.terms.late_penalty_bps > 0 {
+            Self::accrue_span(loan, now, loan.terms.late_penalty_bps);
+        }
+    }
+
+    fn accrue_span(loan: &mut Loan, end: u64, rate_bps: u32) {
+        if end <= loan.last_accrual_time {
             return;
         }
-        let duration = loan.due_time - loan.start_time;
-        if duration == 0 || loan.outstanding_principal == 0 {
-            loan.last_accrual_time = accrual_end;
+        if loan.outstanding_principal == 0 {
+            loan.last_accrual_time = end;
             return;
         }
-        let delta = accrual_end - loan.last_accrual_time;
-        let interest = (loan.outstanding_principal
-            * loan.terms.interest_bps as i128
-            * delta as i128)
-            / duration as i128
-            / BASIS_POINTS;
-        loan.accrued_interest += interest;
-        loan.last_accrual_time = accrual_end;
+        let from = loan.last_accrual_time;
+        let rate = rate_bps as i128;
+
+        match loan.terms.interest_model {
+            InterestModel::Simple => {
+                let duration = loan.due_time - loan.start_time;
+                if duration > 0 {
+                    let delta = end - from;
+                    let interest = Self::mul_div_capped(
+                        loan.outstanding_principal,
+                        rate * delta as i128,
+                        duration as i128 * BASIS_POINTS,
+                    );
+                    loan.accrued_interest = (loan.accrued_interest + interest).min(MAX_DEBT);
+                }
+            }
+            InterestModel::Compound(period) => {
+                // Pro rata up to the next period boundary, whole periods
+                // compounded, then pro rata for the tail
+                let mut balance = loan.outstanding_principal + loan.accrued_interest;
+                let boundary = loan.start_time + ((from - loan.start_time) / period + 1) * period;
+                if end <= boundary {
+                    balance = Self::grow(balance, rate, end - from, period);
+                } else {
+                    balance = Self::grow(balance, rate, boundary - from, period);
+                    let whole = (end - boundary) / period;
+                    balance = match Self::compound_factor(rate, whole) {
+                        Some(factor) => Self::mul_div_capped(balance, factor, RATE_SCALE),
+                        None => MAX_DEBT,
+                    };
+                    let tail = (end - boundary) % period;
+                    balance = Self::grow(balance, rate, tail, period);
+                }
+                loan.accrued_interest =
+                    (balance - loan.outstanding_principal).max(loan.accrued_interest);
+            }
+        }
+        loan.last_accrual_time = end;
+    }
+
+    /// `balance` plus `rate_bps` pro rata for `secs` of a `period`
+    fn grow(balance: i128, rate_bps: i128, secs: u64, period: u64) -> i128 {
+        let interest = Self::mul_div_capped(
+            balance,
+            rate_bps * secs as i128,
+            period as i128 * BASIS_POINTS,
+        );
+        (balance + interest).min(MAX_DEBT)
+    }
+
+    /// a * b / c, or MAX_DEBT if that is larger or overflows
+    fn mul_div_capped(a: i128, b: i128, c: i128) -> i128 {
+        a.checked_mul(b)
+            .map_or(MAX_DEBT, |product| (product / c).min(MAX_DEBT))
+    }
+
+    /// (1 + rate)^periods scaled by RATE_SCALE, by repeated squaring. None
+    /// if it overflows.
+    fn compound_factor(rate_bps: i128, periods: u64) -> Option<i128> {
+        let mut result = RATE_SCALE;
+        let mut base = RATE_SCALE + RATE_SCALE * rate_bps / BASIS_POINTS;
+        let mut n = periods;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.checked_mul(base)? / RATE_SCALE;
+            }
+            n >>= 1;
+            if n > 0 {
+                base = base.checked_mul(base)? / RATE_SCALE;
+            }
+        }
+        Some(result)
     }
 }
 
@@ -0,0 +1,113 @@
+use soroban_sdk::{Address, Env, IntoVal, Symbol};
+
+use crate::{
+    Asset, AssetType, CollateralConfig, Market, PoolLoan, PriceData, BASIS_POINTS, RATE_SCALE,
+};
+
+pub const SECONDS_PER_YEAR: i128 = 31_536_000;
+
+/// Annual borrow rate in bps at the market's current utilization. Rises
+/// along `slope1_bps` up to the optimal utilization and `slope2_bps` past it.
+pub fn borrow_rate(market: &Market) -> i128 {
+    let curve = &market.rate_curve;
+    let utilization = utilization(market);
+    let optimal = curve.optimal_utilization_bps as i128;
+
+    let mut rate = curve.base_bps as i128;
+    if utilization <= optimal {
+        if optimal > 0 {
+            rate += curve.slope1_bps as i128 * utilization / optimal;
+        }
+    } else {
+        rate += curve.slope1_bps as i128;
+        rate += curve.slope2_bps as i128 * (utilization - optimal) / (BASIS_POINTS - optimal);
+    }
+    rate
+}
+
+/// Share of the market's assets lent out, in bps
+pub fn utilization(market: &Market) -> i128 {
+    let assets = total_assets(market);
+    if assets == 0 {
+        return 0;
+    }
+    market.total_borrows * BASIS_POINTS / assets
+}
+
+pub fn total_assets(market: &Market) -> i128 {
+    market.cash + market.total_borrows
+}
+
+/// Grows the borrow index and outstanding borrows by the interest since the
+/// last update, at the rate set by utilization over that span
+pub fn accrue(e: &Env, market: &mut Market) {
+    let now = e.ledger().timestamp();
+    if now <= market.last_update {
+        return;
+    }
+    let elapsed = (now - market.last_update) as i128;
+    let rate = borrow_rate(market);
+
+    let growth = market.borrow_index * rate * elapsed / SECONDS_PER_YEAR / BASIS_POINTS;
+    if growth > 0 {
+        let old_index = market.borrow_index;
+        market.borrow_index += growth;
+        market.total_borrows = market.total_borrows * market.borrow_index / old_index;
+    }
+    market.last_update = now;
+}
+
+/// Debt of a borrow holding `scaled_debt` at the current index, rounded up
+pub fn debt(market: &Market, scaled_debt: i128) -> i128 {
+    (scaled_debt * market.borrow_index + RATE_SCALE - 1) / RATE_SCALE
+}
+
+/// Oracle value of the collateral, in the feeds' reference unit. An NFT is
+/// valued at its feed price.
+pub fn collateral_value(
+    e: &Env,
+    market: &Market,
+    config: &CollateralConfig,
+    asset: &Asset,
+) -> i128 {
+    let price = price(e, &market.oracle, &config.feed);
+    match asset.asset_type {
+        AssetType::Token => asset.amount * price,
+        AssetType::NFT => price,
+    }
+}
+
+/// Oracle value of `amount` of the market's token
+pub fn loan_value(e: &Env, market: &Market, amount: i128) -> i128 {
+    amount * price(e, &market.oracle, &market.feed)
+}
+
+/// Collateral value times its liquidation LTV over the loan's debt, in bps
+pub fn health(e: &Env, market: &Market, loan: &PoolLoan) -> i128 {
+    let debt = debt(market, loan.scaled_debt);
+    if debt == 0 {
+        return i128::MAX;
+    }
+    let config = find_collateral(market, &loan.collateral).unwrap();
+    collateral_value(e, market, &config, &loan.collateral) * config.liquidation_ltv_bps as i128
+        / loan_value(e, market, debt)
+}
+
+pub fn find_collateral(market: &Market, asset: &Asset) -> Option<CollateralConfig> {
+    market
+        .collateral
+        .iter()
+        .find(|c| c.contract == asset.contract && c.asset_type == asset.asset_type)
+}
+
+pub fn price(e: &Env, oracle: &Address, feed: &Symbol) -> i128 {
+    let data: PriceData = e.invoke_contract(
+        oracle,
+        &Symbol::new(e, "get_price"),
+        (feed.clone(),).into_val(e),
+    );
+    if data.price <= 0 {
+        panic!("Invalid oracle price");
+    }
+    data.price
+}
@@ -4,7 +4,7 @@ use super::*;
 use soroban_sdk::{
     contract, contractimpl, contracttype,
     testutils::{Address as _, Ledger},
-    token, Address, Env,
+    token, vec, Address, Env, Symbol,
 };
 
 fn setup_token<'a>(
@@ -59,6 +59,10 @@ fn test_token_loan_partial_repayment() {
             duration_secs: 1_000,
             interest_bps: 1_000,
             max_extension_secs: 500,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
         },
     );
 
@@ -127,6 +131,10 @@ fn test_liquidation_on_default() {
             duration_secs: 100,
             interest_bps: 1_000,
             max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
         },
     );
 
@@ -180,6 +188,10 @@ fn test_extension_request_and_approval() {
             duration_secs: 100,
             interest_bps: 500,
             max_extension_secs: 200,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
         },
     );
 
@@ -241,6 +253,10 @@ fn test_nft_loan_repayment_flow() {
             duration_secs: 100,
             interest_bps: 1_000,
             max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
         },
     );
 
@@ -257,6 +273,895 @@ fn test_nft_loan_repayment_flow() {
     assert_eq!(repayment_client.balance(&lender), 330);
 }
 
+struct TokenLoan<'a> {
+    client: LendingContractClient<'a>,
+    loan_id: u64,
+    lender: Address,
+    borrower: Address,
+    loan_client: token::Client<'a>,
+    loan_admin_client: token::StellarAssetClient<'a>,
+    collateral_client: token::Client<'a>,
+}
+
+/// Lends 10_000 of one token against 1_000 of another
+fn open_token_loan(env: &Env, terms: LoanTerms) -> TokenLoan<'_> {
+    let lender = Address::generate(env);
+    let borrower = Address::generate(env);
+    let loan_admin = Address::generate(env);
+    let collateral_admin = Address::generate(env);
+
+    let (loan_token, _, _) = setup_token(env, &loan_admin);
+    let (collateral_token, _, collateral_admin_client) = setup_token(env, &collateral_admin);
+    let loan_admin_client = token::StellarAssetClient::new(env, &loan_token);
+
+    loan_admin_client.mint(&lender, &10_000);
+    collateral_admin_client.mint(&borrower, &1_000);
+
+    let contract_id = env.register_contract(None, LendingContract);
+    let client = LendingContractClient::new(env, &contract_id);
+
+    let offer_id = client.create_offer(
+        &lender,
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: loan_token.clone(),
+            amount: 10_000,
+            nft_id: 0,
+        },
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: collateral_token.clone(),
+            amount: 1_000,
+            nft_id: 0,
+        },
+        &loan_token,
+        &10_000,
+        &terms,
+    );
+    let loan_id = client.accept_offer(&borrower, &offer_id);
+
+    TokenLoan {
+        client,
+        loan_id,
+        lender,
+        borrower,
+        loan_client: token::Client::new(env, &loan_token),
+        loan_admin_client,
+        collateral_client: token::Client::new(env, &collateral_token),
+    }
+}
+
+#[test]
+fn test_compound_interest_per_period() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 300,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Compound(100),
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
+        },
+    );
+
+    // One full period, then half of the next on the compounded balance
+    env.ledger().set_timestamp(150);
+    assert_eq!(t.client.get_amount_due(&t.loan_id), 11_550);
+
+    env.ledger().set_timestamp(300);
+    assert_eq!(t.client.get_amount_due(&t.loan_id), 13_310);
+
+    // No penalty rate, so nothing accrues past due
+    env.ledger().set_timestamp(1_000);
+    assert_eq!(t.client.get_amount_due(&t.loan_id), 13_310);
+
+    t.loan_admin_client.mint(&t.borrower, &3_310);
+    t.client.repay(&t.borrower, &t.loan_id, &13_310);
+    assert_eq!(
+        t.client.get_loan(&t.loan_id).unwrap().status,
+        LoanStatus::Repaid
+    );
+    assert_eq!(t.loan_client.balance(&t.lender), 13_310);
+}
+
+#[test]
+fn test_late_penalty_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 2_000,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
+        },
+    );
+
+    env.ledger().set_timestamp(100);
+    assert_eq!(t.client.get_amount_due(&t.loan_id), 11_000);
+
+    // Half a loan duration late at twice the rate
+    env.ledger().set_timestamp(150);
+    t.client.repay(&t.borrower, &t.loan_id, &5_000);
+    let loan = t.client.get_loan(&t.loan_id).unwrap();
+    assert_eq!(loan.accrued_interest, 0);
+    assert_eq!(loan.outstanding_principal, 7_000);
+}
+
+#[test]
+fn test_late_penalty_debt_is_capped() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Compound(1),
+            late_penalty_bps: MAX_LATE_PENALTY_BPS,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
+        },
+    );
+
+    // Growing elevenfold a second for 900 seconds stops at the cap
+    env.ledger().set_timestamp(1_000);
+    assert_eq!(t.client.get_amount_due(&t.loan_id), MAX_DEBT);
+
+    t.client.liquidate(&t.lender, &t.loan_id);
+    assert_eq!(
+        t.client.get_loan(&t.loan_id).unwrap().status,
+        LoanStatus::Liquidated
+    );
+    assert_eq!(t.collateral_client.balance(&t.lender), 1_000);
+}
+
+#[test]
+#[should_panic(expected = "Late penalty rate is too high")]
+fn test_late_penalty_rate_is_bounded() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: MAX_LATE_PENALTY_BPS + 1,
+            liquidation: LiquidationMode::Seize,
+            margin: Margin::None,
+        },
+    );
+}
+
+#[test]
+fn test_liquidation_auction_returns_surplus() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Auction(20, 10, 1_000),
+            margin: Margin::None,
+        },
+    );
+
+    env.ledger().set_timestamp(200);
+    t.client.liquidate(&t.lender, &t.loan_id);
+    assert_eq!(
+        t.client.get_loan(&t.loan_id).unwrap().status,
+        LoanStatus::Auctioning
+    );
+    assert_eq!(t.client.get_auction_price(&t.loan_id), 20);
+
+    // Halfway down to the floor
+    env.ledger().set_timestamp(700);
+    assert_eq!(t.client.get_auction_price(&t.loan_id), 15);
+
+    let buyer = Address::generate(&env);
+    t.loan_admin_client.mint(&buyer, &20_000);
+    let paid = t.client.buy_collateral(&buyer, &t.loan_id, &1_000);
+
+    // 11_000 of debt at 15 a unit
+    assert_eq!(paid, 11_000);
+    assert_eq!(t.collateral_client.balance(&buyer), 734);
+    assert_eq!(t.collateral_client.balance(&t.borrower), 266);
+    assert_eq!(t.loan_client.balance(&t.lender), 11_000);
+
+    let loan = t.client.get_loan(&t.loan_id).unwrap();
+    assert_eq!(loan.status, LoanStatus::Liquidated);
+    assert_eq!(loan.outstanding_principal, 0);
+    assert_eq!(
+        t.client
+            .get_collateral_auction(&t.loan_id)
+            .unwrap()
+            .collateral_remaining,
+        0
+    );
+}
+
+#[test]
+fn test_liquidation_auction_of_valuable_collateral_returns_most_to_borrower() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    // 1_000 units priced from 200 down to 100 against 11_000 of debt
+    let t = open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Auction(200, 100, 1_000),
+            margin: Margin::None,
+        },
+    );
+
+    env.ledger().set_timestamp(200);
+    t.client.liquidate(&t.lender, &t.loan_id);
+
+    // Even a buyer waiting for the last moment pays close to the floor, 101 a unit
+    env.ledger().set_timestamp(1_199);
+    let buyer = Address::generate(&env);
+    t.loan_admin_client.mint(&buyer, &20_000);
+    assert_eq!(t.client.buy_collateral(&buyer, &t.loan_id, &1_000), 11_000);
+
+    assert_eq!(t.collateral_client.balance(&buyer), 109);
+    assert_eq!(t.collateral_client.balance(&t.borrower), 891);
+    assert_eq!(t.loan_client.balance(&t.lender), 11_000);
+    assert_eq!(
+        t.client.get_loan(&t.loan_id).unwrap().status,
+        LoanStatus::Liquidated
+    );
+}
+
+#[test]
+fn test_liquidation_auction_unsold_collateral_to_lender() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(
+        &env,
+        LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Auction(22, 11, 1_000),
+            margin: Margin::None,
+        },
+    );
+
+    env.ledger().set_timestamp(200);
+    t.client.liquidate(&t.lender, &t.loan_id);
+
+    let buyer = Address::generate(&env);
+    t.loan_admin_client.mint(&buyer, &5_000);
+    assert_eq!(t.client.buy_collateral(&buyer, &t.loan_id, &100), 2_200);
+
+    let result = t.client.try_settle_liquidation(&t.loan_id);
+    assert!(result.is_err());
+
+    env.ledger().set_timestamp(1_200);
+    t.client.settle_liquidation(&t.loan_id);
+
+    let loan = t.client.get_loan(&t.loan_id).unwrap();
+    assert_eq!(loan.status, LoanStatus::Liquidated);
+    assert_eq!(loan.outstanding_principal, 8_800);
+    assert_eq!(t.collateral_client.balance(&t.lender), 900);
+    assert_eq!(t.collateral_client.balance(&t.borrower), 0);
+    assert_eq!(t.loan_client.balance(&t.lender), 2_200);
+}
+
+#[test]
+#[should_panic(expected = "Auction liquidation requires token collateral")]
+fn test_auction_liquidation_rejects_nft_collateral() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let lender = Address::generate(&env);
+    let loan_admin = Address::generate(&env);
+    let (loan_token, _loan_client, loan_admin_client) = setup_token(&env, &loan_admin);
+    loan_admin_client.mint(&lender, &1_000);
+
+    let nft_contract_id = env.register_contract(None, MockNft);
+    let contract_id = env.register_contract(None, LendingContract);
+    let client = LendingContractClient::new(&env, &contract_id);
+
+    client.create_offer(
+        &lender,
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: loan_token.clone(),
+            amount: 1_000,
+            nft_id: 0,
+        },
+        &Asset {
+            asset_type: AssetType::NFT,
+            contract: nft_contract_id,
+            amount: 1,
+            nft_id: 1,
+        },
+        &loan_token,
+        &1_000,
+        &LoanTerms {
+            duration_secs: 100,
+            interest_bps: 1_000,
+            max_extension_secs: 0,
+            interest_model: InterestModel::Simple,
+            late_penalty_bps: 0,
+            liquidation: LiquidationMode::Auction(15, 10, 1_000),
+            margin: Margin::None,
+        },
+    );
+}
+
+/// Margin terms with an 80% threshold and a 5% liquidator bonus. The loan
+/// token is priced at 1 and the collateral at 20.
+fn margin_terms(env: &Env) -> (MockOracleClient<'_>, LoanTerms) {
+    let oracle_id = env.register_contract(None, MockOracle);
+    let oracle = MockOracleClient::new(env, &oracle_id);
+    oracle.set_price(&Symbol::new(env, "LOAN"), &10_000_000);
+    oracle.set_price(&Symbol::new(env, "COLL"), &200_000_000);
+
+    let terms = LoanTerms {
+        duration_secs: 1_000,
+        interest_bps: 0,
+        max_extension_secs: 0,
+        interest_model: InterestModel::Simple,
+        late_penalty_bps: 0,
+        liquidation: LiquidationMode::Seize,
+        margin: Margin::Oracle(MarginTerms {
+            oracle: oracle_id,
+            loan_feed: Symbol::new(env, "LOAN"),
+            collateral_feed: Symbol::new(env, "COLL"),
+            liquidation_threshold_bps: 8_000,
+            liquidator_bonus_bps: 500,
+        }),
+    };
+    (oracle, terms)
+}
+
+#[test]
+fn test_unhealthy_loan_liquidated_by_third_party() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (oracle, terms) = margin_terms(&env);
+    let t = open_token_loan(&env, terms);
+
+    // 20_000 of collateral at 80% against 10_000 of debt
+    assert_eq!(t.client.health_factor(&t.loan_id), 16_000);
+
+    let liquidator = Address::generate(&env);
+    t.loan_admin_client.mint(&liquidator, &10_000);
+    assert!(t
+        .client
+        .try_liquidate_unhealthy(&liquidator, &t.loan_id)
+        .is_err());
+
+    oracle.set_price(&Symbol::new(&env, "COLL"), &120_000_000);
+    assert_eq!(t.client.health_factor(&t.loan_id), 9_600);
+
+    env.ledger().set_timestamp(100);
+    t.client.liquidate_unhealthy(&liquidator, &t.loan_id);
+
+    // The debt plus 5% buys 875 of the collateral at 12
+    assert_eq!(t.loan_client.balance(&t.lender), 10_000);
+    assert_eq!(t.collateral_client.balance(&liquidator), 875);
+    assert_eq!(t.collateral_client.balance(&t.borrower), 125);
+
+    let loan = t.client.get_loan(&t.loan_id).unwrap();
+    assert_eq!(loan.status, LoanStatus::Liquidated);
+    assert_eq!(loan.outstanding_principal, 0);
+}
+
+#[test]
+fn test_top_up_collateral_restores_health() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (oracle, terms) = margin_terms(&env);
+    let t = open_token_loan(&env, terms);
+
+    oracle.set_price(&Symbol::new(&env, "COLL"), &120_000_000);
+    assert!(t.client.health_factor(&t.loan_id) < 10_000);
+
+    let collateral_admin_client =
+        token::StellarAssetClient::new(&env, &t.collateral_client.address);
+    collateral_admin_client.mint(&t.borrower, &100);
+    t.client.top_up_collateral(&t.borrower, &t.loan_id, &100);
+    assert_eq!(t.client.health_factor(&t.loan_id), 10_560);
+
+    let liquidator = Address::generate(&env);
+    t.loan_admin_client.mint(&liquidator, &10_000);
+    assert!(t
+        .client
+        .try_liquidate_unhealthy(&liquidator, &t.loan_id)
+        .is_err());
+
+    // Repaying returns the topped-up collateral too
+    t.client.repay(&t.borrower, &t.loan_id, &10_000);
+    assert_eq!(t.collateral_client.balance(&t.borrower), 1_100);
+}
+
+/// An open offer lending `principal` of `token` against 1_000 of `collateral`
+fn refinance_offer(
+    env: &Env,
+    client: &LendingContractClient,
+    token: &Address,
+    collateral: &Address,
+    principal: i128,
+    interest_bps: u32,
+) -> (Address, u64) {
+    let lender = Address::generate(env);
+    token::StellarAssetClient::new(env, token).mint(&lender, &principal);
+    let offer_id = client.create_offer(
+        &lender,
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: token.clone(),
+            amount: principal,
+            nft_id: 0,
+        },
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: collateral.clone(),
+            amount: 1_000,
+            nft_id: 0,
+        },
+        token,
+        &principal,
+        &simple_terms(1_000, interest_bps),
+    );
+    (lender, offer_id)
+}
+
+fn simple_terms(duration_secs: u64, interest_bps: u32) -> LoanTerms {
+    LoanTerms {
+        duration_secs,
+        interest_bps,
+        max_extension_secs: 0,
+        interest_model: InterestModel::Simple,
+        late_penalty_bps: 0,
+        liquidation: LiquidationMode::Seize,
+        margin: Margin::None,
+    }
+}
+
+#[test]
+fn test_refinance_repays_old_lender_and_keeps_collateral() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(&env, simple_terms(100, 1_000));
+    let loan_token = t.loan_client.address.clone();
+    let collateral_token = t.collateral_client.address.clone();
+
+    // Halfway through, 10_500 is owed; the new offer lends 12_000 at 2%
+    env.ledger().set_timestamp(50);
+    let (new_lender, offer_id) =
+        refinance_offer(&env, &t.client, &loan_token, &collateral_token, 12_000, 200);
+    let new_loan_id = t.client.refinance(&t.borrower, &t.loan_id, &offer_id);
+
+    assert_eq!(t.loan_client.balance(&t.lender), 10_500);
+    assert_eq!(t.loan_client.balance(&t.borrower), 11_500);
+    assert_eq!(t.collateral_client.balance(&t.client.address), 1_000);
+    assert_eq!(
+        t.client.get_loan(&t.loan_id).unwrap().status,
+        LoanStatus::Repaid
+    );
+
+    let new_loan = t.client.get_loan(&new_loan_id).unwrap();
+    assert_eq!(new_loan.lender, new_lender);
+    assert_eq!(new_loan.outstanding_principal, 12_000);
+    assert_eq!(new_loan.start_time, 50);
+    assert_eq!(
+        t.client.get_offer(&offer_id).unwrap().status,
+        OfferStatus::Accepted
+    );
+
+    t.loan_admin_client.mint(&t.borrower, &500);
+    t.client.repay(&t.borrower, &new_loan_id, &12_000);
+    assert_eq!(t.collateral_client.balance(&t.borrower), 1_000);
+}
+
+#[test]
+fn test_refinance_borrower_covers_shortfall() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(&env, simple_terms(100, 1_000));
+    let loan_token = t.loan_client.address.clone();
+    let collateral_token = t.collateral_client.address.clone();
+
+    env.ledger().set_timestamp(100);
+    let (_, offer_id) =
+        refinance_offer(&env, &t.client, &loan_token, &collateral_token, 8_000, 500);
+
+    t.client.refinance(&t.borrower, &t.loan_id, &offer_id);
+
+    // 8_000 from the new lender plus 3_000 from the borrower
+    assert_eq!(t.loan_client.balance(&t.lender), 11_000);
+    assert_eq!(t.loan_client.balance(&t.borrower), 7_000);
+    assert_eq!(t.collateral_client.balance(&t.client.address), 1_000);
+}
+
+#[test]
+fn test_transfer_loan_redirects_repayment() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let t = open_token_loan(&env, simple_terms(100, 1_000));
+    let buyer = Address::generate(&env);
+    t.loan_admin_client.mint(&buyer, &9_000);
+    let payment_token = t.loan_client.address.clone();
+
+    let stranger = Address::generate(&env);
+    assert!(t
+        .client
+        .try_transfer_loan(&stranger, &t.loan_id, &buyer, &payment_token, &9_000)
+        .is_err());
+
+    t.client
+        .transfer_loan(&t.lender, &t.loan_id, &buyer, &payment_token, &9_000);
+    assert!(env.auths().iter().any(|(address, _)| *address == buyer));
+    assert_eq!(t.client.get_loan(&t.loan_id).unwrap().lender, buyer);
+
+    // The position is paid for in the same call
+    assert_eq!(t.loan_client.balance(&t.lender), 9_000);
+    assert_eq!(t.loan_client.balance(&buyer), 0);
+
+    env.ledger().set_timestamp(100);
+    t.loan_admin_client.mint(&t.borrower, &1_000);
+    t.client.repay(&t.borrower, &t.loan_id, &11_000);
+
+    assert_eq!(t.loan_client.balance(&buyer), 11_000);
+    assert_eq!(t.loan_client.balance(&t.lender), 9_000);
+}
+
+struct PoolSetup<'a> {
+    client: LendingContractClient<'a>,
+    market_id: u64,
+    oracle: MockOracleClient<'a>,
+    token_client: token::Client<'a>,
+    token_admin_client: token::StellarAssetClient<'a>,
+    collateral_token: Address,
+    collateral_admin_client: token::StellarAssetClient<'a>,
+    nft_contract_id: Address,
+}
+
+/// A market lending one token against another token (LTV 50%, liquidated past
+/// 80% with a 5% bonus) or NFTs of a mock collection (LTV 40%, liquidated past
+/// 60%). Every asset is priced at 1.
+fn setup_pool(env: &Env) -> PoolSetup<'_> {
+    let admin = Address::generate(env);
+    let (token, _, _) = setup_token(env, &admin);
+    let (collateral_token, _, _) = setup_token(env, &admin);
+    let nft_contract_id = env.register_contract(None, MockNft);
+
+    let oracle_id = env.register_contract(None, MockOracle);
+    let oracle = MockOracleClient::new(env, &oracle_id);
+    oracle.set_price(&Symbol::new(env, "LOAN"), &10_000_000);
+    oracle.set_price(&Symbol::new(env, "COLL"), &10_000_000);
+    oracle.set_price(&Symbol::new(env, "NFT"), &10_000_000);
+
+    let contract_id = env.register_contract(None, LendingContract);
+    let client = LendingContractClient::new(env, &contract_id);
+
+    let market_id = client.create_market(
+        &Address::generate(env),
+        &token,
+        &oracle_id,
+        &Symbol::new(env, "LOAN"),
+        &RateCurve {
+            base_bps: 0,
+            slope1_bps: 1_000,
+            slope2_bps: 10_000,
+            optimal_utilization_bps: 5_000,
+        },
+        &vec![
+            env,
+            CollateralConfig {
+                asset_type: AssetType::Token,
+                contract: collateral_token.clone(),
+                feed: Symbol::new(env, "COLL"),
+                ltv_bps: 5_000,
+                liquidation_ltv_bps: 8_000,
+                liquidator_bonus_bps: 500,
+            },
+            CollateralConfig {
+                asset_type: AssetType::NFT,
+                contract: nft_contract_id.clone(),
+                feed: Symbol::new(env, "NFT"),
+                ltv_bps: 4_000,
+                liquidation_ltv_bps: 6_000,
+                liquidator_bonus_bps: 0,
+            },
+        ],
+    );
+
+    PoolSetup {
+        client,
+        market_id,
+        oracle,
+        token_client: token::Client::new(env, &token),
+        token_admin_client: token::StellarAssetClient::new(env, &token),
+        collateral_token: collateral_token.clone(),
+        collateral_admin_client: token::StellarAssetClient::new(env, &collateral_token),
+        nft_contract_id,
+    }
+}
+
+#[test]
+fn test_pool_interest_and_liquidity_limited_withdrawal() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let p = setup_pool(&env);
+
+    let lender = Address::generate(&env);
+    let borrower = Address::generate(&env);
+    p.token_admin_client.mint(&lender, &10_000);
+    p.collateral_admin_client.mint(&borrower, &20_000);
+
+    assert_eq!(p.client.deposit(&lender, &p.market_id, &10_000), 10_000);
+    assert_eq!(p.client.get_borrow_rate(&p.market_id), 0);
+
+    let loan_id = p.client.borrow(
+        &borrower,
+        &p.market_id,
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: p.collateral_token.clone(),
+            amount: 10_000,
+            nft_id: 0,
+        },
+        &5_000,
+    );
+    assert_eq!(p.token_client.balance(&borrower), 5_000);
+
+    // Half lent out sits at the optimal utilization
+    assert_eq!(p.client.get_borrow_rate(&p.market_id), 1_000);
+
+    env.ledger().set_timestamp(31_536_000);
+    assert_eq!(p.client.get_borrow_debt(&loan_id), 5_500);
+    assert_eq!(p.client.get_deposit_value(&p.market_id, &lender), 10_500);
+
+    // Only the 5_000 not lent out can leave
+    assert!(p
+        .client
+        .try_withdraw(&lender, &p.market_id, &10_000)
+        .is_err());
+    assert_eq!(p.client.withdraw(&lender, &p.market_id, &4_000), 4_200);
+
+    p.token_admin_client.mint(&borrower, &500);
+    p.client.repay_borrow(&borrower, &loan_id, &5_500);
+    let loan = p.client.get_pool_loan(&loan_id).unwrap();
+    assert_eq!(loan.status, LoanStatus::Repaid);
+    assert_eq!(
+        token::Client::new(&env, &p.collateral_token).balance(&borrower),
+        20_000
+    );
+
+    assert_eq!(p.client.withdraw(&lender, &p.market_id, &6_000), 6_300);
+    assert_eq!(p.token_client.balance(&lender), 10_500);
+    assert_eq!(p.client.get_market(&p.market_id).total_shares, 0);
+}
+
+#[test]
+fn test_pool_shares_price_in_accrued_interest() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let p = setup_pool(&env);
+
+    let first = Address::generate(&env);
+    let second = Address::generate(&env);
+    let borrower = Address::generate(&env);
+    p.token_admin_client.mint(&first, &10_000);
+    p.token_admin_client.mint(&second, &10_500);
+    p.collateral_admin_client.mint(&borrower, &20_000);
+
+    p.client.deposit(&first, &p.market_id, &10_000);
+    p.client.borrow(
+        &borrower,
+        &p.market_id,
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: p.collateral_token.clone(),
+            amount: 10_000,
+            nft_id: 0,
+        },
+        &5_000,
+    );
+
+    env.ledger().set_timestamp(31_536_000);
+
+    // A late depositor pays for the interest already earned
+    assert_eq!(p.client.deposit(&second, &p.market_id, &10_500), 10_000);
+    assert_eq!(p.client.get_deposit_value(&p.market_id, &first), 10_500);
+    assert_eq!(p.client.get_deposit_value(&p.market_id, &second), 10_500);
+}
+
+#[test]
+fn test_pool_borrow_respects_oracle_ltv() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let p = setup_pool(&env);
+
+    let lender = Address::generate(&env);
+    let borrower = Address::generate(&env);
+    p.token_admin_client.mint(&lender, &10_000);
+    p.client.deposit(&lender, &p.market_id, &10_000);
+
+    let nft_client = MockNftClient::new(&env, &p.nft_contract_id);
+    nft_client.mint(&borrower, &7);
+    let nft = Asset {
+        asset_type: AssetType::NFT,
+        contract: p.nft_contract_id.clone(),
+        amount: 1,
+        nft_id: 7,
+    };
+
+    // The collection is worth 5_000 loan tokens, so 40% allows 2_000
+    p.oracle
+        .set_price(&Symbol::new(&env, "NFT"), &50_000_000_000);
+    assert!(p
+        .client
+        .try_borrow(&borrower, &p.market_id, &nft, &2_001)
+        .is_err());
+
+    let loan_id = p.client.borrow(&borrower, &p.market_id, &nft, &2_000);
+    assert_eq!(nft_client.owner_of(&7), p.client.address);
+    assert_eq!(p.token_client.balance(&borrower), 2_000);
+
+    p.client.repay_borrow(&borrower, &loan_id, &2_000);
+    assert_eq!(nft_client.owner_of(&7), borrower);
+
+    // Collateral the market does not list is refused
+    let other_nft = Asset {
+        asset_type: AssetType::NFT,
+        contract: env.register_contract(None, MockNft),
+        amount: 1,
+        nft_id: 1,
+    };
+    assert!(p
+        .client
+        .try_borrow(&borrower, &p.market_id, &other_nft, &1)
+        .is_err());
+}
+
+#[test]
+fn test_unhealthy_pool_loan_liquidated() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let p = setup_pool(&env);
+
+    let lender = Address::generate(&env);
+    let borrower = Address::generate(&env);
+    let liquidator = Address::generate(&env);
+    p.token_admin_client.mint(&lender, &10_000);
+    p.token_admin_client.mint(&liquidator, &5_000);
+    p.collateral_admin_client.mint(&borrower, &10_000);
+    p.client.deposit(&lender, &p.market_id, &10_000);
+
+    let loan_id = p.client.borrow(
+        &borrower,
+        &p.market_id,
+        &Asset {
+            asset_type: AssetType::Token,
+            contract: p.collateral_token.clone(),
+            amount: 10_000,
+            nft_id: 0,
+        },
+        &5_000,
+    );
+
+    // 10_000 of collateral at 80% against 5_000 of debt
+    assert_eq!(p.client.get_borrow_health(&loan_id), 16_000);
+    assert!(p
+        .client
+        .try_liquidate_borrow(&liquidator, &loan_id)
+        .is_err());
+
+    p.oracle.set_price(&Symbol::new(&env, "COLL"), &6_000_000);
+    assert_eq!(p.client.get_borrow_health(&loan_id), 9_600);
+
+    p.client.liquidate_borrow(&liquidator, &loan_id);
+
+    // The debt plus 5% buys 8_750 of the collateral at 0.6
+    let collateral_client = token::Client::new(&env, &p.collateral_token);
+    assert_eq!(p.token_client.balance(&liquidator), 0);
+    assert_eq!(collateral_client.balance(&liquidator), 8_750);
+    assert_eq!(collateral_client.balance(&borrower), 1_250);
+
+    let loan = p.client.get_pool_loan(&loan_id).unwrap();
+    assert_eq!(loan.status, LoanStatus::Liquidated);
+    assert_eq!(p.client.get_market(&p.market_id).total_borrows, 0);
+    assert_eq!(p.client.withdraw(&lender, &p.market_id, &10_000), 10_000);
+}
+
+#[test]
+fn test_unhealthy_nft_pool_loan_goes_to_liquidator() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let p = setup_pool(&env);
+
+    let lender = Address::generate(&env);
+    let borrower = Address::generate(&env);
+    let liquidator = Address::generate(&env);
+    p.token_admin_client.mint(&lender, &10_000);
+    p.token_admin_client.mint(&liquidator, &2_000);
+    p.client.deposit(&lender, &p.market_id, &10_000);
+
+    let nft_client = MockNftClient::new(&env, &p.nft_contract_id);
+    nft_client.mint(&borrower, &7);
+    p.oracle
+        .set_price(&Symbol::new(&env, "NFT"), &50_000_000_000);
+    let loan_id = p.client.borrow(
+        &borrower,
+        &p.market_id,
+        &Asset {
+            asset_type: AssetType::NFT,
+            contract: p.nft_contract_id.clone(),
+            amount: 1,
+            nft_id: 7,
+        },
+        &2_000,
+    );
+
+    // The collection falls to 3_000, and 60% of it no longer covers 2_000
+    p.oracle
+        .set_price(&Symbol::new(&env, "NFT"), &30_000_000_000);
+    assert_eq!(p.client.get_borrow_health(&loan_id), 9_000);
+
+    p.client.liquidate_borrow(&liquidator, &loan_id);
+    assert_eq!(nft_client.owner_of(&7), liquidator);
+    assert_eq!(p.token_client.balance(&liquidator), 0);
+    assert_eq!(p.client.get_market(&p.market_id).cash, 10_000);
+}
+
+#[contract]
+struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, feed: Symbol, price: i128) {
+        let data = PriceData {
+            price,
+            timestamp: env.ledger().timestamp(),
+            round_id: 1,
+        };
+        env.storage().instance().set(&feed, &data);
+    }
+
+    pub fn get_price(env: Env, feed: Symbol) -> PriceData {
+        env.storage().instance().get(&feed).unwrap()
+    }
+}
+
 #[contracttype]
 enum MockNftDataKey {
     Owner(u32),
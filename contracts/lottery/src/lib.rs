@@ -1,12 +1,47 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype,
-    Env, Address, Vec, Bytes,
+    contract, contracterror, contractimpl, contracttype, token, xdr::ToXdr,
+    Env, Address, Vec, Bytes, BytesN,
 };
 
+// How long after `end_time` the owner has to reveal the round seed
+const REVEAL_WINDOW: u64 = 24 * 60 * 60;
+// How long winners have to claim before their prize rolls over
+const CLAIM_WINDOW: u64 = 7 * 24 * 60 * 60;
+const BPS_DENOMINATOR: u32 = 10_000;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    NotInitialized = 1,
+    AlreadyInitialized = 2,
+    RoundNotFound = 3,
+    RoundNotOpen = 4,
+    RoundStillActive = 5,
+    RevealWindowPassed = 6,
+    RevealWindowOpen = 7,
+    SeedMismatch = 8,
+    NoPlayers = 9,
+    RoundNotCompleted = 10,
+    NotWinner = 11,
+    AlreadyClaimed = 12,
+    ClaimWindowPassed = 13,
+    ClaimWindowOpen = 14,
+    AlreadyRolledOver = 15,
+    RoundNotCancelled = 16,
+    AlreadyRefunded = 17,
+    NoTickets = 18,
+    InvalidPrizeTiers = 19,
+    InvalidHouseFee = 20,
+    InvalidTicketCount = 21,
+    InvalidTicketPrice = 22,
+    RoundInProgress = 23,
+}
+
 #[contracttype]
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum RoundStatus {
     Open,
     Drawing,
@@ -15,16 +50,33 @@ pub enum RoundStatus {
 }
 
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct LotteryRound {
     pub id: u32,
     pub ticket_price: i128,
     pub prize_pool: i128,
     pub start_time: u64,
     pub end_time: u64,
-    pub winner: Option<Address>,
     pub status: RoundStatus,
-    pub claimed: bool, // 👈 prevents double-claim
+    pub seed_hash: BytesN<32>, // owner's commitment: sha256(seed)
+    pub entropy: BytesN<32>,   // accumulated from ticket purchases
+    pub reveal_deadline: u64,
+    pub prize_tiers: Vec<u32>, // share of the net pot per place, in bps
+    pub house_fee_bps: u32,
+    pub rollover: i128, // carried in from earlier rounds
+    pub winners: Vec<Address>, // distinct, in tier order
+    pub prizes: Vec<i128>,
+    pub claimed: Vec<bool>, // 👈 prevents double-claim
+    pub claim_deadline: u64,
+    pub rolled_over: bool,
+}
+
+/// Tickets one player holds in a round
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TicketEntry {
+    pub player: Address,
+    pub tickets: u32,
 }
 
 #[contracttype]
@@ -33,7 +85,9 @@ pub enum DataKey {
     Token,
     CurrentRound,
     Round(u32),
-    Players(u32),
+    Players(u32), // Vec<TicketEntry>, one entry per player
+    Refunded(u32, Address),
+    Rollover,
 }
 
 #[contract]
@@ -41,163 +95,434 @@ pub struct LotteryContract;
 
 #[contractimpl]
 impl LotteryContract {
-    pub fn init(env: Env, owner: Address, token: Address) {
+    pub fn init(env: Env, owner: Address, token: Address) -> Result<(), Error> {
+        if env.storage().instance().has(&DataKey::Owner) {
+            return Err(Error::AlreadyInitialized);
+        }
+
         owner.require_auth();
 
         env.storage().instance().set(&DataKey::Owner, &owner);
         env.storage().instance().set(&DataKey::Token, &token);
         env.storage().instance().set(&DataKey::CurrentRound, &0u32);
-    }
-}
 
-pub fn start_round(env: Env, ticket_price: i128, duration: u64) {
-    let owner: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
-    owner.require_auth();
+        Ok(())
+    }
 
-    let mut round_id: u32 = env.storage().instance().get(&DataKey::CurrentRound).unwrap();
-    round_id += 1;
-
-    let now = env.ledger().timestamp();
-
-    let round = LotteryRound {
-        id: round_id,
-        ticket_price,
-        prize_pool: 0,
-        start_time: now,
-        end_time: now + duration,
-        winner: None,
-        status: RoundStatus::Open,
-        claimed: false,
-    };
-
-    env.storage().persistent().set(&DataKey::Round(round_id), &round);
-    env.storage().persistent().set(&DataKey::Players(round_id), &Vec::<Address>::new(&env));
-    env.storage().instance().set(&DataKey::CurrentRound, &round_id);
-}
+    /// Opens a new round. `seed_hash` commits the owner to the seed that must be
+    /// revealed in `draw_winner`. `prize_tiers` splits the pot (after the house
+    /// fee) between distinct winners, e.g. `[6000, 2500, 1500]`, and must sum to
+    /// 10_000 bps. Any pending rollover is added to the new round's pool. The
+    /// previous round must have been drawn or cancelled.
+    pub fn start_round(
+        env: Env,
+        ticket_price: i128,
+        duration: u64,
+        seed_hash: BytesN<32>,
+        prize_tiers: Vec<u32>,
+        house_fee_bps: u32,
+    ) -> Result<u32, Error> {
+        require_owner(&env)?;
+
+        if ticket_price <= 0 {
+            return Err(Error::InvalidTicketPrice);
+        }
+
+        if prize_tiers.is_empty()
+            || prize_tiers.iter().any(|t| t == 0)
+            || prize_tiers.iter().sum::<u32>() != BPS_DENOMINATOR
+        {
+            return Err(Error::InvalidPrizeTiers);
+        }
+
+        if house_fee_bps > BPS_DENOMINATOR {
+            return Err(Error::InvalidHouseFee);
+        }
+
+        let mut round_id: u32 = env.storage().instance().get(&DataKey::CurrentRound).unwrap();
+        if round_id > 0 {
+            let previous = load_round(&env, round_id)?;
+            if previous.status != RoundStatus::Completed && previous.status != RoundStatus::Cancelled {
+                return Err(Error::RoundInProgress);
+            }
+        }
+        round_id += 1;
+
+        let now = env.ledger().timestamp();
+        let rollover = take_rollover(&env);
+
+        let round = LotteryRound {
+            id: round_id,
+            ticket_price,
+            prize_pool: rollover,
+            start_time: now,
+            end_time: now + duration,
+            status: RoundStatus::Open,
+            seed_hash: seed_hash.clone(),
+            entropy: seed_hash,
+            reveal_deadline: now + duration + REVEAL_WINDOW,
+            prize_tiers,
+            house_fee_bps,
+            rollover,
+            winners: Vec::new(&env),
+            prizes: Vec::new(&env),
+            claimed: Vec::new(&env),
+            claim_deadline: 0,
+            rolled_over: false,
+        };
+
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+        env.storage().persistent().set(&DataKey::Players(round_id), &Vec::<TicketEntry>::new(&env));
+        env.storage().instance().set(&DataKey::CurrentRound, &round_id);
+
+        Ok(round_id)
+    }
 
-pub fn buy_ticket(env: Env, user: Address) {
-    user.require_auth();
+    pub fn buy_ticket(env: Env, user: Address) -> Result<(), Error> {
+        Self::buy_tickets(env, user, 1)
+    }
 
-    let round_id: u32 = env.storage().instance().get(&DataKey::CurrentRound).unwrap();
-    let mut round: LotteryRound =
-        env.storage().persistent().get(&DataKey::Round(round_id)).unwrap();
+    /// Buys `count` tickets at once. Each ticket is an entry in the draw, so odds
+    /// scale with the number of tickets held.
+    pub fn buy_tickets(env: Env, user: Address, count: u32) -> Result<(), Error> {
+        user.require_auth();
+
+        if count == 0 {
+            return Err(Error::InvalidTicketCount);
+        }
+
+        let round_id = current_round_id(&env)?;
+        let mut round = load_round(&env, round_id)?;
+
+        if round.status != RoundStatus::Open || env.ledger().timestamp() >= round.end_time {
+            return Err(Error::RoundNotOpen);
+        }
+
+        let cost = round.ticket_price * count as i128;
+        token_client(&env).transfer(&user, env.current_contract_address(), &cost);
+        round.prize_pool += cost;
+
+        // Every purchase is folded into the round entropy
+        let mut data = Bytes::from_array(&env, &round.entropy.to_array());
+        data.append(&user.clone().to_xdr(&env));
+        data.extend_from_slice(&env.ledger().sequence().to_be_bytes());
+        data.extend_from_slice(&env.ledger().timestamp().to_be_bytes());
+        data.extend_from_slice(&count.to_be_bytes());
+        round.entropy = env.crypto().sha256(&data).into();
+
+        let mut players: Vec<TicketEntry> =
+            env.storage().persistent().get(&DataKey::Players(round_id)).unwrap();
+
+        match players.iter().position(|entry| entry.player == user) {
+            Some(index) => {
+                let mut entry = players.get(index as u32).unwrap();
+                entry.tickets += count;
+                players.set(index as u32, entry);
+            }
+            None => players.push_back(TicketEntry { player: user, tickets: count }),
+        }
+
+        env.storage().persistent().set(&DataKey::Players(round_id), &players);
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+
+        Ok(())
+    }
 
-    if round.status != RoundStatus::Open {
-        panic!("Round not open");
+    /// Reveals the seed committed in `start_round` and draws one distinct winner
+    /// per prize tier, weighted by tickets held. The house fee is paid to the
+    /// owner; shares of tiers left unfilled (fewer distinct players than tiers)
+    /// and rounding dust roll over to the next round.
+    pub fn draw_winner(env: Env, seed: BytesN<32>) -> Result<(), Error> {
+        let owner = require_owner(&env)?;
+
+        let round_id = current_round_id(&env)?;
+        let mut round = load_round(&env, round_id)?;
+
+        if round.status != RoundStatus::Open {
+            return Err(Error::RoundNotOpen);
+        }
+
+        if env.ledger().timestamp() < round.end_time {
+            return Err(Error::RoundStillActive);
+        }
+
+        if env.ledger().timestamp() > round.reveal_deadline {
+            return Err(Error::RevealWindowPassed);
+        }
+
+        let seed_hash: BytesN<32> =
+            env.crypto().sha256(&Bytes::from_array(&env, &seed.to_array())).into();
+        if seed_hash != round.seed_hash {
+            return Err(Error::SeedMismatch);
+        }
+
+        let players: Vec<TicketEntry> =
+            env.storage().persistent().get(&DataKey::Players(round_id)).unwrap();
+
+        if players.is_empty() {
+            return Err(Error::NoPlayers);
+        }
+
+        let base = generate_random(&env, &seed, &round.entropy);
+
+        // Each tier draws from the tickets not held by an earlier winner
+        let mut remaining = players;
+        let mut remaining_tickets: u64 = remaining.iter().map(|entry| entry.tickets as u64).sum();
+        let mut tier = 0;
+        while tier < round.prize_tiers.len() && !remaining.is_empty() {
+            let mut ticket = tier_random(&env, &base, tier) % remaining_tickets;
+            let mut index = 0;
+            let mut entry = remaining.get(0).unwrap();
+            while ticket >= entry.tickets as u64 {
+                ticket -= entry.tickets as u64;
+                index += 1;
+                entry = remaining.get(index).unwrap();
+            }
+
+            remaining.remove(index);
+            remaining_tickets -= entry.tickets as u64;
+
+            round.winners.push_back(entry.player);
+            tier += 1;
+        }
+
+        let house_fee = round.prize_pool * round.house_fee_bps as i128 / BPS_DENOMINATOR as i128;
+        let net = round.prize_pool - house_fee;
+
+        let mut awarded = 0;
+        for i in 0..round.winners.len() {
+            let share = round.prize_tiers.get(i).unwrap();
+            let prize = net * share as i128 / BPS_DENOMINATOR as i128;
+            round.prizes.push_back(prize);
+            round.claimed.push_back(false);
+            awarded += prize;
+        }
+
+        add_rollover(&env, net - awarded);
+
+        if house_fee > 0 {
+            token_client(&env).transfer(&env.current_contract_address(), &owner, &house_fee);
+        }
+
+        round.prize_pool = awarded;
+        round.status = RoundStatus::Completed;
+        round.claim_deadline = env.ledger().timestamp() + CLAIM_WINDOW;
+
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+
+        Ok(())
     }
 
-    let token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
-    let client = soroban_sdk::token::Client::new(&env, &token);
+    pub fn claim_prize(env: Env, user: Address, round_id: u32) -> Result<i128, Error> {
+        user.require_auth();
 
-    client.transfer(&user, &env.current_contract_address(), &round.ticket_price);
-    round.prize_pool += round.ticket_price;
+        let mut round = load_round(&env, round_id)?;
 
-    let mut players: Vec<Address> =
-        env.storage().persistent().get(&DataKey::Players(round_id)).unwrap();
+        if round.status != RoundStatus::Completed {
+            return Err(Error::RoundNotCompleted);
+        }
 
-    players.push_back(user);
+        let index = round.winners.first_index_of(&user).ok_or(Error::NotWinner)?;
 
-    env.storage().persistent().set(&DataKey::Players(round_id), &players);
-    env.storage().persistent().set(&DataKey::Round(round_id), &round);
-}
+        if round.claimed.get(index).unwrap() {
+            return Err(Error::AlreadyClaimed);
+        }
 
-fn generate_random(env: &Env) -> u64 {
-    let seed = env.ledger().sequence();
-    let hash = env.crypto().sha256(&Bytes::from_array(env, &seed.to_be_bytes()));
-    let bytes = hash.to_array();
-    u64::from_be_bytes(bytes[..8].try_into().unwrap())
-}
+        if env.ledger().timestamp() > round.claim_deadline {
+            return Err(Error::ClaimWindowPassed);
+        }
+
+        let amount = round.prizes.get(index).unwrap();
+        round.prize_pool -= amount;
+        round.claimed.set(index, true);
+
+        token_client(&env).transfer(&env.current_contract_address(), &user, &amount);
+
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+
+        Ok(amount)
+    }
+
+    /// Moves prizes left unclaimed after the claim deadline into the next round.
+    /// Callable by anyone.
+    pub fn rollover_unclaimed(env: Env, round_id: u32) -> Result<i128, Error> {
+        let mut round = load_round(&env, round_id)?;
+
+        if round.status != RoundStatus::Completed {
+            return Err(Error::RoundNotCompleted);
+        }
+
+        if env.ledger().timestamp() <= round.claim_deadline {
+            return Err(Error::ClaimWindowOpen);
+        }
+
+        if round.rolled_over {
+            return Err(Error::AlreadyRolledOver);
+        }
 
-pub fn draw_winner(env: Env) {
-    let round_id: u32 = env.storage().instance().get(&DataKey::CurrentRound).unwrap();
-    let mut round: LotteryRound =
-        env.storage().persistent().get(&DataKey::Round(round_id)).unwrap();
+        let amount = round.prize_pool;
+        add_rollover(&env, amount);
 
-    if round.status != RoundStatus::Open {
-        panic!("Winner already drawn");
+        round.prize_pool = 0;
+        round.rolled_over = true;
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+
+        Ok(amount)
     }
 
-    if env.ledger().timestamp() < round.end_time {
-        panic!("Round still active");
+    pub fn cancel_round(env: Env) -> Result<(), Error> {
+        require_owner(&env)?;
+
+        let round_id = current_round_id(&env)?;
+        let mut round = load_round(&env, round_id)?;
+
+        if round.status != RoundStatus::Open {
+            return Err(Error::RoundNotOpen);
+        }
+
+        round.status = RoundStatus::Cancelled;
+        release_rollover(&env, &mut round);
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+
+        Ok(())
     }
 
-    let players: Vec<Address> =
-        env.storage().persistent().get(&DataKey::Players(round_id)).unwrap();
+    /// Fallback when the owner never reveals: once the reveal window has passed
+    /// anyone can cancel the round so players can claim refunds.
+    pub fn cancel_unrevealed(env: Env) -> Result<(), Error> {
+        let round_id = current_round_id(&env)?;
+        let mut round = load_round(&env, round_id)?;
+
+        if round.status != RoundStatus::Open {
+            return Err(Error::RoundNotOpen);
+        }
+
+        if env.ledger().timestamp() <= round.reveal_deadline {
+            return Err(Error::RevealWindowOpen);
+        }
 
-    if players.len() == 0 {
-        panic!("No players");
+        round.status = RoundStatus::Cancelled;
+        release_rollover(&env, &mut round);
+        env.storage().persistent().set(&DataKey::Round(round_id), &round);
+
+        Ok(())
     }
 
-    let rand = generate_random(&env);
-    let index = (rand % players.len() as u64) as u32;
+    pub fn refund(env: Env, round_id: u32, user: Address) -> Result<i128, Error> {
+        user.require_auth();
 
-    round.winner = Some(players.get(index).unwrap());
-    round.status = RoundStatus::Completed;
+        let round = load_round(&env, round_id)?;
 
-    env.storage().persistent().set(&DataKey::Round(round_id), &round);
-}
+        if round.status != RoundStatus::Cancelled {
+            return Err(Error::RoundNotCancelled);
+        }
+
+        let refunded_key = DataKey::Refunded(round_id, user.clone());
+        if env.storage().persistent().has(&refunded_key) {
+            return Err(Error::AlreadyRefunded);
+        }
+
+        let players: Vec<TicketEntry> =
+            env.storage().persistent().get(&DataKey::Players(round_id)).unwrap();
+        let tickets = players
+            .iter()
+            .find(|entry| entry.player == user)
+            .map(|entry| entry.tickets as i128)
+            .ok_or(Error::NoTickets)?;
 
-pub fn claim_prize(env: Env, user: Address, round_id: u32) {
-    user.require_auth();
+        env.storage().persistent().set(&refunded_key, &true);
 
-    let mut round: LotteryRound =
-        env.storage().persistent().get(&DataKey::Round(round_id)).unwrap();
+        let amount = round.ticket_price * tickets;
+        token_client(&env).transfer(&env.current_contract_address(), &user, &amount);
 
-    if round.status != RoundStatus::Completed {
-        panic!("Round not completed");
+        Ok(amount)
     }
 
-    if round.claimed {
-        panic!("Prize already claimed");
+    pub fn get_round(env: Env, round_id: u32) -> Result<LotteryRound, Error> {
+        load_round(&env, round_id)
     }
 
-    if round.winner != Some(user.clone()) {
-        panic!("Not winner");
+    pub fn get_current_round(env: Env) -> Result<u32, Error> {
+        current_round_id(&env)
     }
 
-    let token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
-    let client = soroban_sdk::token::Client::new(&env, &token);
+    pub fn get_players(env: Env, round_id: u32) -> Vec<TicketEntry> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Players(round_id))
+            .unwrap_or(Vec::new(&env))
+    }
 
-    let amount = round.prize_pool;
-    round.prize_pool = 0;
-    round.claimed = true;
+    pub fn get_rollover(env: Env) -> i128 {
+        env.storage().instance().get(&DataKey::Rollover).unwrap_or(0)
+    }
+}
 
-    client.transfer(&env.current_contract_address(), &user, &amount);
+fn require_owner(env: &Env) -> Result<Address, Error> {
+    let owner: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::Owner)
+        .ok_or(Error::NotInitialized)?;
+    owner.require_auth();
+    Ok(owner)
+}
 
-    env.storage().persistent().set(&DataKey::Round(round_id), &round);
+fn current_round_id(env: &Env) -> Result<u32, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::CurrentRound)
+        .ok_or(Error::NotInitialized)
 }
 
-pub fn cancel_round(env: Env) {
-    let owner: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
-    owner.require_auth();
+fn load_round(env: &Env, round_id: u32) -> Result<LotteryRound, Error> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Round(round_id))
+        .ok_or(Error::RoundNotFound)
+}
 
-    let round_id: u32 = env.storage().instance().get(&DataKey::CurrentRound).unwrap();
-    let mut round: LotteryRound =
-        env.storage().persistent().get(&DataKey::Round(round_id)).unwrap();
+fn token_client(env: &Env) -> token::Client<'_> {
+    let token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+    token::Client::new(env, &token)
+}
 
-    round.status = RoundStatus::Cancelled;
-    env.storage().persistent().set(&DataKey::Round(round_id), &round);
+/// Mixes the revealed seed with purchase entropy and the host PRNG, so the
+/// result is unknown until the seed is revealed and the draw executes.
+fn generate_random(env: &Env, seed: &BytesN<32>, entropy: &BytesN<32>) -> BytesN<32> {
+    let mut data = Bytes::from_array(env, &seed.to_array());
+    data.extend_from_slice(&entropy.to_array());
+    data.append(&env.prng().gen_len::<Bytes>(32));
+    env.crypto().sha256(&data).into()
 }
 
-pub fn refund(env: Env, round_id: u32, user: Address) {
-    user.require_auth();
+/// Derives an independent draw for each prize tier from the round randomness.
+fn tier_random(env: &Env, base: &BytesN<32>, tier: u32) -> u64 {
+    let mut data = Bytes::from_array(env, &base.to_array());
+    data.extend_from_slice(&tier.to_be_bytes());
+    let bytes = env.crypto().sha256(&data).to_array();
+    u64::from_be_bytes(bytes[..8].try_into().unwrap())
+}
 
-    let round = get_round(env.clone(), round_id);
+fn take_rollover(env: &Env) -> i128 {
+    let amount: i128 = env.storage().instance().get(&DataKey::Rollover).unwrap_or(0);
+    env.storage().instance().remove(&DataKey::Rollover);
+    amount
+}
 
-    if round.status != RoundStatus::Cancelled {
-        panic!("Round not cancelled");
+fn add_rollover(env: &Env, amount: i128) {
+    if amount > 0 {
+        let pending: i128 = env.storage().instance().get(&DataKey::Rollover).unwrap_or(0);
+        env.storage().instance().set(&DataKey::Rollover, &(pending + amount));
     }
+}
 
-    let token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
-    let client = soroban_sdk::token::Client::new(&env, &token);
-
-    client.transfer(
-        &env.current_contract_address(),
-        &user,
-        &round.ticket_price,
-    );
+/// Carried-over funds a cancelled round can't refund go back to the pending
+/// rollover for the next round.
+fn release_rollover(env: &Env, round: &mut LotteryRound) {
+    add_rollover(env, round.rollover);
+    round.prize_pool -= round.rollover;
+    round.rollover = 0;
 }
 
-pub fn get_round(env: Env, round_id: u32) -> LotteryRound {
-    env.storage().persistent().get(&DataKey::Round(round_id)).unwrap()
-}
\ No newline at end of file
+mod test;
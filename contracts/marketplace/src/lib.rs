@@ -1,13 +1,19 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, contracterror, token, Address, Env, IntoVal, Symbol, Vec,
+    contract, contractimpl, contracttype, contracterror, token, Address, Env, IntoVal, String, Symbol, Val, Vec,
 };
 
 // ──────────────────────────────────────────────────────────
 // DATA STRUCTURES
 // ──────────────────────────────────────────────────────────
 
+const ACTIVE_PAGE_SIZE: u32 = 100; // Ids per page of the active listings and collection offer indexes
+const HISTORY_CAPACITY: u32 = 100; // Sales kept per asset and per collection
+const ORACLE_PRICE_SCALE: i128 = 10_000_000; // Oracle prices carry 7 decimals
+const RECORD_TTL_THRESHOLD: u32 = 17_280; // ~1 day
+const RECORD_TTL_EXTEND: u32 = 518_400; // ~30 days
+
 #[contracttype]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum AssetType {
@@ -24,12 +30,61 @@ pub struct Asset {
     pub token_id: u32,
 }
 
+/// Unit a listing's price is quoted in
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PriceUnit {
+    PaymentToken,
+    // Reference unit converted at purchase with OracleContract::get_price(feed),
+    // the price of one payment token in reference units
+    Reference(Address, Symbol), // oracle, feed
+}
+
+/// Mirrors `oracle::PriceData` so `get_price` results decode here
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+    pub round_id: u64,
+}
+
+/// A completed sale of `quantity` units for a total of `price`. A bundle sale
+/// is recorded once per asset, at that asset's equal share of the price.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SaleRecord {
+    pub token_id: u32,
+    pub price: i128,
+    pub quantity: u32,
+    pub timestamp: u64,
+}
+
+/// One constituent of a bundle listing, with its own creator royalty
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BundleItem {
+    pub asset: Asset,
+    pub creator: Option<Address>,
+    pub royalty_bps: u32,
+}
+
 #[contracttype]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ListingStatus {
     Active = 1,
     Sold = 2,
     Cancelled = 3,
+    Expired = 4,
+}
+
+/// A declining price schedule, running from the listing's creation time
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PriceDecay {
+    None,                  // fixed price
+    Linear(i128, u64),     // floor price, time the floor is reached
+    Step(i128, i128, u64), // floor price, drop per step, step length in seconds
 }
 
 #[contracttype]
@@ -44,6 +99,10 @@ pub struct Listing {
     pub created_time: u64,
     pub creator: Option<Address>, // For royalty payments
     pub royalty_bps: u32, // Royalty in basis points (10000 = 100%)
+    pub bundle: Vec<BundleItem>, // Every asset of a bundle listing; empty for single assets
+    pub end_time: Option<u64>,   // No sales after this; sweep_expired returns the assets
+    pub decay: PriceDecay,       // Lowers the asking price over time from `price`
+    pub price_unit: PriceUnit,
 }
 
 #[contracttype]
@@ -79,6 +138,49 @@ pub struct CounterOffer {
     pub expiration_time: Option<u64>,
 }
 
+/// Mirrors `achievement_nft::Achievement` so `get_achievement` results decode here
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Achievement {
+    pub owner: Address,
+    pub puzzle_id: u32,
+    pub metadata: String,
+    pub timestamp: u64,
+}
+
+/// Achievement metadata a token must match; unset fields match anything
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraitFilter {
+    pub puzzle_id: Option<u32>,
+    pub metadata: Option<String>,
+}
+
+/// Which tokens a collection offer will buy
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OfferTarget {
+    Collection(Address),          // Any token from this NFT contract
+    Traits(Address, TraitFilter), // Any achievement token matching the filter
+}
+
+/// A standing bid for up to `quantity` tokens at `price` each, fully escrowed
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollectionOffer {
+    pub offer_id: u64,
+    pub buyer: Address,
+    pub target: OfferTarget,
+    pub payment_token: Address,
+    pub price: i128,
+    pub quantity: u32, // Tokens still wanted
+    pub creator: Option<Address>, // Royalty recipient, paid from each sale
+    pub royalty_bps: u32,
+    pub status: OfferStatus,
+    pub created_time: u64,
+    pub expiration_time: Option<u64>,
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MarketplaceConfig {
@@ -102,8 +204,47 @@ pub enum DataKey {
     CounterOffersByOffer(u64),       // Vec<u64> - counter offer IDs for an offer
     ListingsBySeller(Address),       // Vec<u64> - listing IDs by seller
     ListingsByAsset(Address, u32),   // Vec<u64> - listing IDs by asset
-    ActiveListings,                  // Vec<u64> - all active listings
-    PriceHistory(Address, u32),      // Vec<i128> - price history for an asset
+    ActivePage(u32),                 // Vec<u64> - one page of the active listings index
+    ActiveCount,                     // u32 - number of active listings
+    ActivePosition(u64),             // u32 - a listing's position in the active index
+    SweepCursor,                     // u32 - where the next sweep_expired resumes
+    Sale(Address, Option<u32>, u32), // SaleRecord - ring buffer slot for an asset, or a collection if None
+    SaleCount(Address, Option<u32>), // u32 - sales ever recorded for an asset or collection
+    CollectionOffer(u64),            // CollectionOffer
+    CollectionOfferCount,            // u64
+    ContractOfferPage(Address, u32), // Vec<u64> - one page of an NFT contract's open collection offers
+    ContractOfferCount(Address),     // u32 - open collection offers for an NFT contract
+    ContractOfferPosition(u64),      // u32 - a collection offer's position in its contract's index
+}
+
+/// A list of ids kept in pages of ACTIVE_PAGE_SIZE, with each id's position
+/// stored so it can be swap-removed
+enum PagedIndex {
+    Active,                  // Active listings
+    ContractOffers(Address), // Open collection offers for an NFT contract
+}
+
+impl PagedIndex {
+    fn page_key(&self, page: u32) -> DataKey {
+        match self {
+            PagedIndex::Active => DataKey::ActivePage(page),
+            PagedIndex::ContractOffers(contract) => DataKey::ContractOfferPage(contract.clone(), page),
+        }
+    }
+
+    fn count_key(&self) -> DataKey {
+        match self {
+            PagedIndex::Active => DataKey::ActiveCount,
+            PagedIndex::ContractOffers(contract) => DataKey::ContractOfferCount(contract.clone()),
+        }
+    }
+
+    fn position_key(&self, id: u64) -> DataKey {
+        match self {
+            PagedIndex::Active => DataKey::ActivePosition(id),
+            PagedIndex::ContractOffers(_) => DataKey::ContractOfferPosition(id),
+        }
+    }
 }
 
 #[contracterror]
@@ -220,9 +361,7 @@ impl MarketplaceContract {
             panic!("Price must be positive");
         }
 
-        if royalty_bps > 10000 {
-            panic!("Royalty cannot exceed 100%");
-        }
+        Self::check_fee_and_royalty(&env, royalty_bps);
 
         // Verify seller owns the asset
         Self::verify_asset_ownership(&env, &seller, &asset);
@@ -250,101 +389,135 @@ impl MarketplaceContract {
             created_time: env.ledger().timestamp(),
             creator,
             royalty_bps,
+            bundle: Vec::new(&env),
+            end_time: None,
+            decay: PriceDecay::None,
+            price_unit: PriceUnit::PaymentToken,
         };
 
         // Save listing
-        env.storage()
-            .instance()
-            .set(&DataKey::Listing(listing_id), &listing);
+        Self::set_persistent(&env, &DataKey::Listing(listing_id), &listing);
 
         // Update indexes
-        let mut seller_listings = Self::get_listings_by_seller(&env, &seller);
+        let mut seller_listings = Self::get_listings_by_seller(env.clone(), seller.clone());
         seller_listings.push_back(listing_id);
-        env.storage()
-            .instance()
-            .set(&DataKey::ListingsBySeller(seller.clone()), &seller_listings);
+        Self::set_persistent(&env, &DataKey::ListingsBySeller(seller.clone()), &seller_listings);
 
-        let mut asset_listings = Self::get_listings_by_asset(&env, &asset.contract, &asset.token_id);
+        let mut asset_listings = Self::get_listings_by_asset(env.clone(), asset.contract.clone(), asset.token_id);
         asset_listings.push_back(listing_id);
-        env.storage()
-            .instance()
-            .set(&DataKey::ListingsByAsset(asset.contract.clone(), asset.token_id), &asset_listings);
+        Self::set_persistent(&env, &DataKey::ListingsByAsset(asset.contract.clone(), asset.token_id), &asset_listings);
 
-        let mut active_listings = Self::get_active_listings(&env);
-        active_listings.push_back(listing_id);
-        env.storage()
-            .instance()
-            .set(&DataKey::ActiveListings, &active_listings);
+        Self::add_to_active_listings(&env, listing_id);
 
         listing_id
     }
 
-    /// Buy a listed item directly
-    pub fn buy(env: Env, buyer: Address, listing_id: u64) {
-        buyer.require_auth();
+    /// Create a bundle listing: several assets sold together at one price.
+    /// Each asset keeps its own creator royalty.
+    pub fn create_bundle_listing(
+        env: Env,
+        seller: Address,
+        items: Vec<BundleItem>,
+        payment_token: Address,
+        price: i128,
+    ) -> u64 {
+        seller.require_auth();
 
-        let mut listing: Listing = env
-            .storage()
-            .instance()
-            .get(&DataKey::Listing(listing_id))
-            .expect("Listing not found");
+        if price <= 0 {
+            panic!("Price must be positive");
+        }
 
-        if listing.status != ListingStatus::Active {
-            panic!("Listing is not active");
+        if items.len() < 2 {
+            panic!("Bundle needs at least two assets");
         }
 
-        if listing.seller == buyer {
-            panic!("Cannot buy your own listing");
+        // Each item's royalty applies to its equal share of the price, so the
+        // bundle pays the average rate
+        let mut total_royalty_bps = 0;
+        for item in items.iter() {
+            if item.royalty_bps > 10000 {
+                panic!("Royalty cannot exceed 100%");
+            }
+            total_royalty_bps += item.royalty_bps;
         }
+        Self::check_fee_and_royalty(&env, total_royalty_bps.div_ceil(items.len()));
 
-        let config: MarketplaceConfig = env
+        // Verify ownership and escrow every asset
+        for item in items.iter() {
+            Self::verify_asset_ownership(&env, &seller, &item.asset);
+            Self::transfer_asset_to_contract(&env, &seller, &item.asset);
+        }
+
+        // Generate listing ID
+        let mut listing_id: u64 = env
             .storage()
             .instance()
-            .get(&DataKey::Config)
-            .expect("Not initialized");
+            .get(&DataKey::ListingCount)
+            .unwrap_or(0);
+        listing_id += 1;
+        env.storage().instance().set(&DataKey::ListingCount, &listing_id);
 
-        // Calculate fees and royalties
-        let (seller_amount, fee_amount, royalty_amount) = Self::calculate_payouts(
-            &env,
-            listing.price,
-            config.fee_bps,
-            listing.royalty_bps,
-        );
+        // The first asset stands in for the bundle where a single asset is expected
+        let listing = Listing {
+            listing_id,
+            seller: seller.clone(),
+            asset: items.get(0).unwrap().asset,
+            payment_token,
+            price,
+            status: ListingStatus::Active,
+            created_time: env.ledger().timestamp(),
+            creator: None,
+            royalty_bps: 0,
+            bundle: items.clone(),
+            end_time: None,
+            decay: PriceDecay::None,
+            price_unit: PriceUnit::PaymentToken,
+        };
 
-        // Transfer payment from buyer to contract
-        let token_client = token::Client::new(&env, &listing.payment_token);
-        token_client.transfer(&buyer, &env.current_contract_address(), &listing.price);
+        Self::set_persistent(&env, &DataKey::Listing(listing_id), &listing);
 
-        // Distribute payments
-        // 1. Pay seller (after fees and royalties)
-        token_client.transfer(&env.current_contract_address(), &listing.seller, &seller_amount);
+        // Update indexes
+        let mut seller_listings = Self::get_listings_by_seller(env.clone(), seller.clone());
+        seller_listings.push_back(listing_id);
+        Self::set_persistent(&env, &DataKey::ListingsBySeller(seller), &seller_listings);
 
-        // 2. Pay marketplace fee
-        if fee_amount > 0 {
-            token_client.transfer(&env.current_contract_address(), &config.fee_recipient, &fee_amount);
+        for item in items.iter() {
+            let asset = item.asset;
+            let mut asset_listings = Self::get_listings_by_asset(env.clone(), asset.contract.clone(), asset.token_id);
+            asset_listings.push_back(listing_id);
+            Self::set_persistent(&env, &DataKey::ListingsByAsset(asset.contract, asset.token_id), &asset_listings);
         }
 
-        // 3. Pay royalty to creator
-        if royalty_amount > 0 {
-            if let Some(creator) = listing.creator.clone() {
-                token_client.transfer(&env.current_contract_address(), &creator, &royalty_amount);
-            }
-        }
+        Self::add_to_active_listings(&env, listing_id);
 
-        // Transfer asset from contract to buyer
-        Self::transfer_asset_from_contract(&env, &buyer, &listing.asset);
+        listing_id
+    }
 
-        // Update listing status
-        listing.status = ListingStatus::Sold;
-        env.storage()
-            .instance()
-            .set(&DataKey::Listing(listing_id), &listing);
+    /// Buy a listed item directly
+    pub fn buy(env: Env, buyer: Address, listing_id: u64) {
+        buyer.require_auth();
+        Self::purchase(&env, &buyer, listing_id, None);
+    }
 
-        // Remove from active listings
-        Self::remove_from_active_listings(&env, listing_id);
+    /// Buy a listing, paying at most `max_price` in the payment token. Needed
+    /// for listings priced in a reference unit, whose cost follows the oracle.
+    pub fn buy_with_limit(env: Env, buyer: Address, listing_id: u64, max_price: i128) {
+        buyer.require_auth();
+        Self::purchase(&env, &buyer, listing_id, Some(max_price));
+    }
 
-        // Record price in history
-        Self::record_price_history(&env, &listing.asset.contract, &listing.asset.token_id, listing.price);
+    /// Buy several listings in one call. Either every purchase settles or the
+    /// whole call reverts.
+    pub fn buy_many(env: Env, buyer: Address, listing_ids: Vec<u64>) {
+        buyer.require_auth();
+
+        if listing_ids.is_empty() {
+            panic!("No listings to buy");
+        }
+
+        for listing_id in listing_ids.iter() {
+            Self::purchase(&env, &buyer, listing_id, None);
+        }
     }
 
     /// Create an offer on a listing
@@ -359,13 +532,11 @@ impl MarketplaceContract {
 
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(listing_id))
             .expect("Listing not found");
 
-        if listing.status != ListingStatus::Active {
-            panic!("Listing is not active");
-        }
+        Self::require_live(&env, &listing);
 
         if listing.seller == buyer {
             panic!("Cannot offer on your own listing");
@@ -403,16 +574,12 @@ impl MarketplaceContract {
         };
 
         // Save offer
-        env.storage()
-            .instance()
-            .set(&DataKey::Offer(offer_id), &offer);
+        Self::set_persistent(&env, &DataKey::Offer(offer_id), &offer);
 
         // Update indexes
-        let mut listing_offers = Self::get_offers_by_listing(&env, listing_id);
+        let mut listing_offers = Self::get_offers_by_listing(env.clone(), listing_id);
         listing_offers.push_back(offer_id);
-        env.storage()
-            .instance()
-            .set(&DataKey::OffersByListing(listing_id), &listing_offers);
+        Self::set_persistent(&env, &DataKey::OffersByListing(listing_id), &listing_offers);
 
         // Transfer payment to contract (escrow)
         let token_client = token::Client::new(&env, &listing.payment_token);
@@ -427,7 +594,7 @@ impl MarketplaceContract {
 
         let mut offer: Offer = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Offer(offer_id))
             .expect("Offer not found");
 
@@ -444,7 +611,7 @@ impl MarketplaceContract {
 
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(offer.listing_id))
             .expect("Listing not found");
 
@@ -452,9 +619,7 @@ impl MarketplaceContract {
             panic!("Not the listing seller");
         }
 
-        if listing.status != ListingStatus::Active {
-            panic!("Listing is not active");
-        }
+        Self::require_live(&env, &listing);
 
         let config: MarketplaceConfig = env
             .storage()
@@ -462,47 +627,20 @@ impl MarketplaceContract {
             .get(&DataKey::Config)
             .expect("Not initialized");
 
-        // Calculate fees and royalties
-        let (seller_amount, fee_amount, royalty_amount) = Self::calculate_payouts(
-            &env,
-            offer.price,
-            config.fee_bps,
-            listing.royalty_bps,
-        );
-
-        let token_client = token::Client::new(&env, &listing.payment_token);
-
-        // Distribute payments
-        // 1. Pay seller (after fees and royalties)
-        token_client.transfer(&env.current_contract_address(), &seller, &seller_amount);
-
-        // 2. Pay marketplace fee
-        if fee_amount > 0 {
-            token_client.transfer(&env.current_contract_address(), &config.fee_recipient, &fee_amount);
-        }
-
-        // 3. Pay royalty to creator
-        if royalty_amount > 0 {
-            if let Some(creator) = listing.creator.clone() {
-                token_client.transfer(&env.current_contract_address(), &creator, &royalty_amount);
-            }
-        }
+        // Pay seller, marketplace fee and royalties
+        Self::distribute_payment(&env, &config, &listing, offer.price);
 
-        // Transfer asset from contract to buyer
-        Self::transfer_asset_from_contract(&env, &offer.buyer, &listing.asset);
+        // Transfer assets from contract to buyer
+        Self::transfer_listing_from_contract(&env, &offer.buyer, &listing);
 
         // Update offer status
         offer.status = OfferStatus::Accepted;
-        env.storage()
-            .instance()
-            .set(&DataKey::Offer(offer_id), &offer);
+        Self::set_persistent(&env, &DataKey::Offer(offer_id), &offer);
 
         // Update listing status
         let mut listing = listing;
         listing.status = ListingStatus::Sold;
-        env.storage()
-            .instance()
-            .set(&DataKey::Listing(offer.listing_id), &listing);
+        Self::set_persistent(&env, &DataKey::Listing(offer.listing_id), &listing);
 
         // Remove from active listings
         Self::remove_from_active_listings(&env, offer.listing_id);
@@ -511,7 +649,7 @@ impl MarketplaceContract {
         Self::refund_other_offers(&env, offer.listing_id, offer_id);
 
         // Record price in history
-        Self::record_price_history(&env, &listing.asset.contract, &listing.asset.token_id, offer.price);
+        Self::record_sale(&env, &Self::listing_items(&env, &listing), offer.price);
     }
 
     /// Reject an offer (refund buyer)
@@ -520,7 +658,7 @@ impl MarketplaceContract {
 
         let mut offer: Offer = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Offer(offer_id))
             .expect("Offer not found");
 
@@ -530,7 +668,7 @@ impl MarketplaceContract {
 
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(offer.listing_id))
             .expect("Listing not found");
 
@@ -544,9 +682,7 @@ impl MarketplaceContract {
 
         // Update offer status
         offer.status = OfferStatus::Rejected;
-        env.storage()
-            .instance()
-            .set(&DataKey::Offer(offer_id), &offer);
+        Self::set_persistent(&env, &DataKey::Offer(offer_id), &offer);
     }
 
     /// Create a counter-offer
@@ -561,7 +697,7 @@ impl MarketplaceContract {
 
         let offer: Offer = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Offer(offer_id))
             .expect("Offer not found");
 
@@ -571,7 +707,7 @@ impl MarketplaceContract {
 
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(offer.listing_id))
             .expect("Listing not found");
 
@@ -610,23 +746,17 @@ impl MarketplaceContract {
         };
 
         // Save counter offer
-        env.storage()
-            .instance()
-            .set(&DataKey::CounterOffer(counter_offer_id), &counter_offer);
+        Self::set_persistent(&env, &DataKey::CounterOffer(counter_offer_id), &counter_offer);
 
         // Update indexes
-        let mut offer_counters = Self::get_counter_offers_by_offer(&env, offer_id);
+        let mut offer_counters = Self::get_counter_offers_by_offer(env.clone(), offer_id);
         offer_counters.push_back(counter_offer_id);
-        env.storage()
-            .instance()
-            .set(&DataKey::CounterOffersByOffer(offer_id), &offer_counters);
+        Self::set_persistent(&env, &DataKey::CounterOffersByOffer(offer_id), &offer_counters);
 
         // Mark original offer as countered
         let mut offer = offer;
         offer.status = OfferStatus::Countered;
-        env.storage()
-            .instance()
-            .set(&DataKey::Offer(offer_id), &offer);
+        Self::set_persistent(&env, &DataKey::Offer(offer_id), &offer);
 
         counter_offer_id
     }
@@ -637,13 +767,13 @@ impl MarketplaceContract {
 
         let counter_offer: CounterOffer = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::CounterOffer(counter_offer_id))
             .expect("Counter offer not found");
 
         let offer: Offer = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Offer(counter_offer.offer_id))
             .expect("Offer not found");
 
@@ -660,10 +790,12 @@ impl MarketplaceContract {
 
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(offer.listing_id))
             .expect("Listing not found");
 
+        Self::require_live(&env, &listing);
+
         let config: MarketplaceConfig = env
             .storage()
             .instance()
@@ -672,52 +804,29 @@ impl MarketplaceContract {
 
         let token_client = token::Client::new(&env, &listing.payment_token);
 
-        // Refund original offer amount
-        token_client.transfer(&env.current_contract_address(), &buyer, &offer.price);
-
-        // Take new payment amount
+        // The original offer stays escrowed; settle the difference
         let price_difference = counter_offer.price - offer.price;
         if price_difference > 0 {
             token_client.transfer(&buyer, &env.current_contract_address(), &price_difference);
+        } else if price_difference < 0 {
+            token_client.transfer(&env.current_contract_address(), &buyer, &-price_difference);
         }
 
-        // Calculate fees and royalties
-        let (seller_amount, fee_amount, royalty_amount) = Self::calculate_payouts(
-            &env,
-            counter_offer.price,
-            config.fee_bps,
-            listing.royalty_bps,
-        );
-
-        // Distribute payments
-        token_client.transfer(&env.current_contract_address(), &counter_offer.seller, &seller_amount);
-
-        if fee_amount > 0 {
-            token_client.transfer(&env.current_contract_address(), &config.fee_recipient, &fee_amount);
-        }
-
-        if royalty_amount > 0 {
-            if let Some(creator) = listing.creator.clone() {
-                token_client.transfer(&env.current_contract_address(), &creator, &royalty_amount);
-            }
-        }
+        // Pay seller, marketplace fee and royalties
+        Self::distribute_payment(&env, &config, &listing, counter_offer.price);
 
-        // Transfer asset from contract to buyer
-        Self::transfer_asset_from_contract(&env, &buyer, &listing.asset);
+        // Transfer assets from contract to buyer
+        Self::transfer_listing_from_contract(&env, &buyer, &listing);
 
         // Update offer status
         let mut offer = offer;
         offer.status = OfferStatus::Accepted;
-        env.storage()
-            .instance()
-            .set(&DataKey::Offer(counter_offer.offer_id), &offer);
+        Self::set_persistent(&env, &DataKey::Offer(counter_offer.offer_id), &offer);
 
         // Update listing status
         let mut listing = listing;
         listing.status = ListingStatus::Sold;
-        env.storage()
-            .instance()
-            .set(&DataKey::Listing(offer.listing_id), &listing);
+        Self::set_persistent(&env, &DataKey::Listing(offer.listing_id), &listing);
 
         // Remove from active listings
         Self::remove_from_active_listings(&env, offer.listing_id);
@@ -726,7 +835,7 @@ impl MarketplaceContract {
         Self::refund_other_offers(&env, offer.listing_id, counter_offer.offer_id);
 
         // Record price in history
-        Self::record_price_history(&env, &listing.asset.contract, &listing.asset.token_id, counter_offer.price);
+        Self::record_sale(&env, &Self::listing_items(&env, &listing), counter_offer.price);
     }
 
     /// Cancel a listing
@@ -735,7 +844,7 @@ impl MarketplaceContract {
 
         let mut listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(listing_id))
             .expect("Listing not found");
 
@@ -747,61 +856,412 @@ impl MarketplaceContract {
             panic!("Listing is not active");
         }
 
-        // Return asset to seller
-        Self::transfer_asset_from_contract(&env, &seller, &listing.asset);
+        // Return assets to seller
+        Self::transfer_listing_from_contract(&env, &seller, &listing);
 
         // Refund all offers on this listing
         Self::refund_all_offers(&env, listing_id);
 
         // Update listing status
         listing.status = ListingStatus::Cancelled;
-        env.storage()
-            .instance()
-            .set(&DataKey::Listing(listing_id), &listing);
+        Self::set_persistent(&env, &DataKey::Listing(listing_id), &listing);
 
         // Remove from active listings
         Self::remove_from_active_listings(&env, listing_id);
     }
 
-    /// Cancel an offer
-    pub fn cancel_offer(env: Env, buyer: Address, offer_id: u64) {
-        buyer.require_auth();
+    /// Set an optional end time and a price schedule on an active listing.
+    /// The duration is bounded by the configured min/max listing duration.
+    pub fn schedule_listing(
+        env: Env,
+        seller: Address,
+        listing_id: u64,
+        end_time: Option<u64>,
+        decay: PriceDecay,
+    ) {
+        seller.require_auth();
 
-        let mut offer: Offer = env
+        let mut listing: Listing = env
             .storage()
-            .instance()
-            .get(&DataKey::Offer(offer_id))
-            .expect("Offer not found");
+            .persistent()
+            .get(&DataKey::Listing(listing_id))
+            .expect("Listing not found");
 
-        if offer.buyer != buyer {
-            panic!("Not the offer buyer");
+        if listing.seller != seller {
+            panic!("Not the listing seller");
         }
 
-        if offer.status != OfferStatus::Open {
-            panic!("Offer is not open");
+        Self::require_live(&env, &listing);
+
+        if let Some(end) = end_time {
+            let config: MarketplaceConfig = env
+                .storage()
+                .instance()
+                .get(&DataKey::Config)
+                .expect("Not initialized");
+
+            if end <= env.ledger().timestamp() {
+                panic!("End time must be in the future");
+            }
+
+            let duration = end - listing.created_time;
+            if duration < config.min_listing_duration || duration > config.max_listing_duration {
+                panic!("Listing duration out of range");
+            }
         }
 
-        // Refund buyer
-        let listing: Listing = env
+        match &decay {
+            PriceDecay::Linear(floor, floor_time) => {
+                if *floor <= 0 || *floor > listing.price {
+                    panic!("Invalid floor price");
+                }
+                if *floor_time <= listing.created_time {
+                    panic!("Floor time must be after listing creation");
+                }
+            }
+            PriceDecay::Step(floor, drop, step) => {
+                if *floor <= 0 || *floor > listing.price {
+                    panic!("Invalid floor price");
+                }
+                if *drop <= 0 || *step == 0 {
+                    panic!("Invalid price step");
+                }
+            }
+            PriceDecay::None => {}
+        }
+
+        listing.end_time = end_time;
+        listing.decay = decay;
+        Self::set_persistent(&env, &DataKey::Listing(listing_id), &listing);
+    }
+
+    /// Quote an active listing in the payment token or in a reference unit
+    /// converted through an oracle at purchase time
+    pub fn set_price_unit(env: Env, seller: Address, listing_id: u64, price_unit: PriceUnit) {
+        seller.require_auth();
+
+        let mut listing: Listing = env
             .storage()
-            .instance()
-            .get(&DataKey::Listing(offer.listing_id))
+            .persistent()
+            .get(&DataKey::Listing(listing_id))
             .expect("Listing not found");
 
-        let token_client = token::Client::new(&env, &listing.payment_token);
-        token_client.transfer(&env.current_contract_address(), &buyer, &offer.price);
+        if listing.seller != seller {
+            panic!("Not the listing seller");
+        }
 
-        // Update offer status
-        offer.status = OfferStatus::Cancelled;
-        env.storage()
-            .instance()
-            .set(&DataKey::Offer(offer_id), &offer);
+        Self::require_live(&env, &listing);
+
+        listing.price_unit = price_unit;
+        Self::set_persistent(&env, &DataKey::Listing(listing_id), &listing);
     }
 
-    // ──────────────────────────────────────────────────────────
+    /// Return escrowed assets of expired listings to their sellers and refund
+    /// their open offers. Anyone can call this. Examines at most `limit`
+    /// entries of the active index, resuming where the last sweep stopped,
+    /// and returns how many listings expired.
+    pub fn sweep_expired(env: Env, limit: u32) -> u32 {
+        let now = env.ledger().timestamp();
+        let mut cursor: u32 = env.storage().persistent().get(&DataKey::SweepCursor).unwrap_or(0);
+        let mut swept = 0;
+
+        for _ in 0..limit {
+            let count = Self::get_active_listing_count(env.clone());
+            if count == 0 {
+                break;
+            }
+            if cursor >= count {
+                cursor = 0;
+            }
+
+            let listing_id = Self::load_active_page(&env, cursor / ACTIVE_PAGE_SIZE)
+                .get(cursor % ACTIVE_PAGE_SIZE)
+                .unwrap();
+            let mut listing: Listing = env
+                .storage()
+                .persistent()
+                .get(&DataKey::Listing(listing_id))
+                .expect("Listing not found");
+
+            let expired = matches!(listing.end_time, Some(end_time) if now > end_time);
+            if !expired {
+                cursor += 1;
+                continue;
+            }
+
+            // Return assets to seller and refund offers
+            Self::transfer_listing_from_contract(&env, &listing.seller, &listing);
+            Self::refund_all_offers(&env, listing_id);
+
+            listing.status = ListingStatus::Expired;
+            Self::set_persistent(&env, &DataKey::Listing(listing_id), &listing);
+
+            // The last listing moves into this position, so stay on it
+            Self::remove_from_active_listings(&env, listing_id);
+            swept += 1;
+        }
+
+        env.storage().persistent().set(&DataKey::SweepCursor, &cursor);
+        swept
+    }
+
+    /// Cancel an offer
+    pub fn cancel_offer(env: Env, buyer: Address, offer_id: u64) {
+        buyer.require_auth();
+
+        let mut offer: Offer = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Offer(offer_id))
+            .expect("Offer not found");
+
+        if offer.buyer != buyer {
+            panic!("Not the offer buyer");
+        }
+
+        if offer.status != OfferStatus::Open {
+            panic!("Offer is not open");
+        }
+
+        // Refund buyer
+        let listing: Listing = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Listing(offer.listing_id))
+            .expect("Listing not found");
+
+        let token_client = token::Client::new(&env, &listing.payment_token);
+        token_client.transfer(&env.current_contract_address(), &buyer, &offer.price);
+
+        // Update offer status
+        offer.status = OfferStatus::Cancelled;
+        Self::set_persistent(&env, &DataKey::Offer(offer_id), &offer);
+    }
+
+    /// Create a standing offer for any token from a collection, or any
+    /// achievement token matching a trait filter. Escrows `price * quantity`.
+    /// The royalty terms are fixed here, so holders see what each sale pays
+    /// the creator before accepting.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_collection_offer(
+        env: Env,
+        buyer: Address,
+        target: OfferTarget,
+        payment_token: Address,
+        price: i128,
+        quantity: u32,
+        expiration_time: Option<u64>,
+        creator: Option<Address>,
+        royalty_bps: u32,
+    ) -> u64 {
+        buyer.require_auth();
+
+        if price <= 0 {
+            panic!("Price must be positive");
+        }
+
+        if quantity == 0 {
+            panic!("Quantity must be positive");
+        }
+
+        Self::check_fee_and_royalty(&env, royalty_bps);
+
+        // Check expiration
+        if let Some(exp_time) = expiration_time {
+            if exp_time <= env.ledger().timestamp() {
+                panic!("Expiration time must be in the future");
+            }
+        }
+
+        // Generate offer ID
+        let mut offer_id: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::CollectionOfferCount)
+            .unwrap_or(0);
+        offer_id += 1;
+        env.storage().instance().set(&DataKey::CollectionOfferCount, &offer_id);
+
+        let nft_contract = Self::target_contract(&target);
+        let offer = CollectionOffer {
+            offer_id,
+            buyer: buyer.clone(),
+            target,
+            payment_token: payment_token.clone(),
+            price,
+            quantity,
+            creator,
+            royalty_bps,
+            status: OfferStatus::Open,
+            created_time: env.ledger().timestamp(),
+            expiration_time,
+        };
+
+        Self::set_persistent(&env, &DataKey::CollectionOffer(offer_id), &offer);
+
+        // Update indexes
+        Self::index_push(&env, &PagedIndex::ContractOffers(nft_contract), offer_id);
+
+        // Transfer payment to contract (escrow)
+        let token_client = token::Client::new(&env, &payment_token);
+        token_client.transfer(&buyer, &env.current_contract_address(), &(price * quantity as i128));
+
+        offer_id
+    }
+
+    /// Sell a token into a collection offer. The token goes straight from the
+    /// holder to the buyer; royalties follow the offer's terms.
+    pub fn accept_collection_offer(env: Env, seller: Address, offer_id: u64, token_id: u32) {
+        seller.require_auth();
+
+        let mut offer: CollectionOffer = env
+            .storage()
+            .persistent()
+            .get(&DataKey::CollectionOffer(offer_id))
+            .expect("Offer not found");
+
+        if offer.status != OfferStatus::Open {
+            panic!("Offer is not open");
+        }
+
+        // Check expiration
+        if let Some(exp_time) = offer.expiration_time {
+            if env.ledger().timestamp() > exp_time {
+                panic!("Offer has expired");
+            }
+        }
+
+        if offer.buyer == seller {
+            panic!("Cannot accept your own offer");
+        }
+
+        let asset = Asset {
+            asset_type: AssetType::NFT,
+            contract: Self::target_contract(&offer.target),
+            token_id,
+        };
+        Self::verify_asset_ownership(&env, &seller, &asset);
+
+        if let OfferTarget::Traits(_, filter) = &offer.target {
+            if !Self::matches_traits(&env, &asset, filter) {
+                panic!("Token does not match offer traits");
+            }
+        }
+
+        let config: MarketplaceConfig = env
+            .storage()
+            .instance()
+            .get(&DataKey::Config)
+            .expect("Not initialized");
+
+        // Pay seller, marketplace fee and royalty from the escrow
+        let mut items = Vec::new(&env);
+        items.push_back(BundleItem {
+            asset: asset.clone(),
+            creator: offer.creator.clone(),
+            royalty_bps: offer.royalty_bps,
+        });
+        Self::pay_out(&env, &config, &offer.payment_token, &seller, &items, offer.price);
+
+        // Direct transfer from holder to buyer
+        let transfer_args = (seller.clone(), offer.buyer.clone(), token_id).into_val(&env);
+        env.invoke_contract::<()>(
+            &asset.contract,
+            &Symbol::new(&env, "transfer"),
+            transfer_args,
+        );
+
+        offer.quantity -= 1;
+        if offer.quantity == 0 {
+            offer.status = OfferStatus::Accepted;
+            Self::remove_collection_offer_index(&env, &asset.contract, offer_id);
+        }
+        Self::set_persistent(&env, &DataKey::CollectionOffer(offer_id), &offer);
+
+        // Record price in history
+        Self::record_price_history(&env, &asset.contract, &token_id, offer.price, 1);
+    }
+
+    /// Cancel a collection offer and refund what is still escrowed. Also the
+    /// way to reclaim funds from an expired offer.
+    pub fn cancel_collection_offer(env: Env, buyer: Address, offer_id: u64) {
+        buyer.require_auth();
+
+        let mut offer: CollectionOffer = env
+            .storage()
+            .persistent()
+            .get(&DataKey::CollectionOffer(offer_id))
+            .expect("Offer not found");
+
+        if offer.buyer != buyer {
+            panic!("Not the offer buyer");
+        }
+
+        if offer.status != OfferStatus::Open {
+            panic!("Offer is not open");
+        }
+
+        // Refund buyer
+        let token_client = token::Client::new(&env, &offer.payment_token);
+        token_client.transfer(&env.current_contract_address(), &buyer, &(offer.price * offer.quantity as i128));
+
+        // Update offer status
+        offer.status = OfferStatus::Cancelled;
+        Self::remove_collection_offer_index(&env, &Self::target_contract(&offer.target), offer_id);
+        Self::set_persistent(&env, &DataKey::CollectionOffer(offer_id), &offer);
+    }
+
+    // ──────────────────────────────────────────────────────────
     // HELPER FUNCTIONS
     // ──────────────────────────────────────────────────────────
 
+    /// Store a listing or offer record in its own persistent entry, extending
+    /// its TTL
+    fn set_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+        env.storage().persistent().set(key, value);
+        env.storage()
+            .persistent()
+            .extend_ttl(key, RECORD_TTL_THRESHOLD, RECORD_TTL_EXTEND);
+    }
+
+    /// Drop a closed collection offer from its contract's index
+    fn remove_collection_offer_index(env: &Env, nft_contract: &Address, offer_id: u64) {
+        Self::index_remove(env, &PagedIndex::ContractOffers(nft_contract.clone()), offer_id);
+    }
+
+    /// NFT contract a collection offer targets
+    fn target_contract(target: &OfferTarget) -> Address {
+        match target {
+            OfferTarget::Collection(contract) => contract.clone(),
+            OfferTarget::Traits(contract, _) => contract.clone(),
+        }
+    }
+
+    /// Check a token's achievement metadata against a trait filter
+    fn matches_traits(env: &Env, asset: &Asset, filter: &TraitFilter) -> bool {
+        let achievement: Option<Achievement> = env.invoke_contract(
+            &asset.contract,
+            &Symbol::new(env, "get_achievement"),
+            (asset.token_id,).into_val(env),
+        );
+        let achievement = match achievement {
+            Some(achievement) => achievement,
+            None => return false,
+        };
+
+        if let Some(puzzle_id) = filter.puzzle_id {
+            if achievement.puzzle_id != puzzle_id {
+                return false;
+            }
+        }
+        if let Some(metadata) = &filter.metadata {
+            if achievement.metadata != *metadata {
+                return false;
+            }
+        }
+        true
+    }
+
     /// Verify asset ownership
     fn verify_asset_ownership(env: &Env, owner: &Address, asset: &Asset) {
         // Invoke the NFT contract's owner_of function
@@ -838,6 +1298,195 @@ impl MarketplaceContract {
         );
     }
 
+    /// Complete a purchase at the current asking price
+    fn purchase(env: &Env, buyer: &Address, listing_id: u64, max_price: Option<i128>) {
+        let mut listing: Listing = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Listing(listing_id))
+            .expect("Listing not found");
+
+        Self::require_live(env, &listing);
+
+        let price = match &listing.price_unit {
+            PriceUnit::PaymentToken => Self::current_price(env, &listing),
+            PriceUnit::Reference(oracle, feed) => {
+                if max_price.is_none() {
+                    panic!("Oracle-priced listing needs a price limit");
+                }
+                Self::convert_reference_price(env, oracle, feed, Self::current_price(env, &listing))
+            }
+        };
+
+        if let Some(max_price) = max_price {
+            if price > max_price {
+                panic!("Price exceeds limit");
+            }
+        }
+
+        if listing.seller == *buyer {
+            panic!("Cannot buy your own listing");
+        }
+
+        let config: MarketplaceConfig = env
+            .storage()
+            .instance()
+            .get(&DataKey::Config)
+            .expect("Not initialized");
+
+        // Transfer payment from buyer to contract
+        let token_client = token::Client::new(env, &listing.payment_token);
+        token_client.transfer(buyer, &env.current_contract_address(), &price);
+
+        // Pay seller, marketplace fee and royalties
+        Self::distribute_payment(env, &config, &listing, price);
+
+        // Transfer assets from contract to buyer
+        Self::transfer_listing_from_contract(env, buyer, &listing);
+
+        // Update listing status
+        listing.status = ListingStatus::Sold;
+        Self::set_persistent(env, &DataKey::Listing(listing_id), &listing);
+
+        // Remove from active listings
+        Self::remove_from_active_listings(env, listing_id);
+
+        // Record price in history
+        Self::record_sale(env, &Self::listing_items(env, &listing), price);
+    }
+
+    /// Convert a reference-unit amount to payment tokens at the oracle price
+    fn convert_reference_price(env: &Env, oracle: &Address, feed: &Symbol, amount: i128) -> i128 {
+        let data: PriceData = env.invoke_contract(
+            oracle,
+            &Symbol::new(env, "get_price"),
+            (feed.clone(),).into_val(env),
+        );
+
+        if data.price <= 0 {
+            panic!("Invalid oracle price");
+        }
+
+        // Round up so the seller never receives less than the quoted amount
+        (amount * ORACLE_PRICE_SCALE + data.price - 1) / data.price
+    }
+
+    /// Panics unless the listing can still be bought
+    fn require_live(env: &Env, listing: &Listing) {
+        if listing.status != ListingStatus::Active {
+            panic!("Listing is not active");
+        }
+
+        if let Some(end_time) = listing.end_time {
+            if env.ledger().timestamp() > end_time {
+                panic!("Listing has expired");
+            }
+        }
+    }
+
+    /// Asking price after any scheduled decay
+    fn current_price(env: &Env, listing: &Listing) -> i128 {
+        let elapsed = env.ledger().timestamp().saturating_sub(listing.created_time);
+        match &listing.decay {
+            PriceDecay::None => listing.price,
+            PriceDecay::Linear(floor, floor_time) => {
+                let duration = floor_time - listing.created_time;
+                if elapsed >= duration {
+                    return *floor;
+                }
+                listing.price - (listing.price - floor) * elapsed as i128 / duration as i128
+            }
+            PriceDecay::Step(floor, drop, step) => {
+                let steps = (elapsed / step) as i128;
+                (listing.price - drop * steps).max(*floor)
+            }
+        }
+    }
+
+    /// Pay out an escrowed sale price: marketplace fee, creator royalties and
+    /// the remainder to the seller. A bundle's price is split evenly across
+    /// its assets and each share pays that asset's creator royalty.
+    fn distribute_payment(env: &Env, config: &MarketplaceConfig, listing: &Listing, price: i128) {
+        let items = Self::listing_items(env, listing);
+        Self::pay_out(env, config, &listing.payment_token, &listing.seller, &items, price);
+    }
+
+    /// Reject royalty terms that, together with the marketplace fee, would
+    /// take more than the sale price
+    fn check_fee_and_royalty(env: &Env, royalty_bps: u32) {
+        if royalty_bps > 10000 {
+            panic!("Royalty cannot exceed 100%");
+        }
+        if royalty_bps + Self::get_config(env.clone()).fee_bps > 10000 {
+            panic!("Fee and royalty cannot exceed 100%");
+        }
+    }
+
+    /// Pay out an escrowed price for `items` to the seller, creators and the
+    /// marketplace
+    fn pay_out(
+        env: &Env,
+        config: &MarketplaceConfig,
+        payment_token: &Address,
+        seller: &Address,
+        items: &Vec<BundleItem>,
+        price: i128,
+    ) {
+        let token_client = token::Client::new(env, payment_token);
+        let contract = env.current_contract_address();
+
+        let share = price / items.len() as i128;
+
+        let (_, fee_amount, _) = Self::calculate_payouts(env, price, config.fee_bps, 0);
+        let mut seller_amount = price - fee_amount;
+
+        // 1. Pay royalty to each creator
+        for item in items.iter() {
+            let (_, _, royalty_amount) = Self::calculate_payouts(env, share, 0, item.royalty_bps);
+            if royalty_amount > 0 {
+                if let Some(creator) = item.creator {
+                    token_client.transfer(&contract, &creator, &royalty_amount);
+                    seller_amount -= royalty_amount;
+                }
+            }
+        }
+
+        // A fee raised after listing can't be paid out of the seller's share
+        if seller_amount < 0 {
+            panic!("Fee and royalty cannot exceed 100%");
+        }
+
+        // 2. Pay seller (after fees and royalties)
+        token_client.transfer(&contract, seller, &seller_amount);
+
+        // 3. Pay marketplace fee
+        if fee_amount > 0 {
+            token_client.transfer(&contract, &config.fee_recipient, &fee_amount);
+        }
+    }
+
+    /// The assets a listing sells, with their royalty terms
+    fn listing_items(env: &Env, listing: &Listing) -> Vec<BundleItem> {
+        if !listing.bundle.is_empty() {
+            return listing.bundle.clone();
+        }
+
+        let mut items = Vec::new(env);
+        items.push_back(BundleItem {
+            asset: listing.asset.clone(),
+            creator: listing.creator.clone(),
+            royalty_bps: listing.royalty_bps,
+        });
+        items
+    }
+
+    /// Transfer every asset of a listing from the contract
+    fn transfer_listing_from_contract(env: &Env, to: &Address, listing: &Listing) {
+        for item in Self::listing_items(env, listing).iter() {
+            Self::transfer_asset_from_contract(env, to, &item.asset);
+        }
+    }
+
     /// Calculate payouts (seller amount, fee amount, royalty amount)
     fn calculate_payouts(
         _env: &Env,
@@ -852,62 +1501,170 @@ impl MarketplaceContract {
         (seller_amount, fee_amount, royalty_amount)
     }
 
-    /// Record price in history for price discovery
-    fn record_price_history(env: &Env, contract: &Address, token_id: &u32, price: i128) {
-        let mut history: Vec<i128> = env
+    /// Record a listing's sale under each asset it sold, at an equal share of
+    /// the price, as pay_out splits it
+    fn record_sale(env: &Env, items: &Vec<BundleItem>, price: i128) {
+        let share = price / items.len() as i128;
+        for item in items.iter() {
+            Self::record_price_history(env, &item.asset.contract, &item.asset.token_id, share, 1);
+        }
+    }
+
+    /// Record a sale for price discovery, both for the asset and for its
+    /// collection. Each history is a ring buffer of the last HISTORY_CAPACITY sales.
+    fn record_price_history(env: &Env, contract: &Address, token_id: &u32, price: i128, quantity: u32) {
+        let record = SaleRecord {
+            token_id: *token_id,
+            price,
+            quantity,
+            timestamp: env.ledger().timestamp(),
+        };
+
+        for scope in [Some(*token_id), None] {
+            let count_key = DataKey::SaleCount(contract.clone(), scope);
+            let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+            env.storage()
+                .persistent()
+                .set(&DataKey::Sale(contract.clone(), scope, count % HISTORY_CAPACITY), &record);
+            env.storage().persistent().set(&count_key, &(count + 1));
+        }
+    }
+
+    /// Retained sales for an asset, or a whole collection, oldest first
+    fn sale_history(env: &Env, contract: &Address, token_id: Option<u32>) -> Vec<SaleRecord> {
+        let count: u32 = env
             .storage()
-            .instance()
-            .get(&DataKey::PriceHistory(contract.clone(), *token_id))
-            .unwrap_or(Vec::new(env));
-
-        history.push_back(price);
-
-        // Keep only last 100 prices
-        if history.len() > 100 {
-            // Create a new Vec with only the last 100 elements
-            let mut new_history = Vec::new(env);
-            let start_index = history.len() - 100;
-            for i in start_index..history.len() {
-                new_history.push_back(*history.get(i).unwrap());
-            }
-            history = new_history;
+            .persistent()
+            .get(&DataKey::SaleCount(contract.clone(), token_id))
+            .unwrap_or(0);
+
+        let mut history = Vec::new(env);
+        for i in count.saturating_sub(HISTORY_CAPACITY)..count {
+            history.push_back(
+                env.storage()
+                    .persistent()
+                    .get(&DataKey::Sale(contract.clone(), token_id, i % HISTORY_CAPACITY))
+                    .unwrap(),
+            );
         }
+        history
+    }
 
-        env.storage()
-            .instance()
-            .set(&DataKey::PriceHistory(contract.clone(), *token_id), &history);
+    /// Per-unit prices of retained sales in the last `window` seconds
+    fn unit_prices_in_window(env: &Env, contract: &Address, token_id: Option<u32>, window: u64) -> Vec<i128> {
+        let since = env.ledger().timestamp().saturating_sub(window);
+        let mut prices = Vec::new(env);
+        for sale in Self::sale_history(env, contract, token_id).iter() {
+            if sale.timestamp >= since {
+                prices.push_back(sale.price / sale.quantity as i128);
+            }
+        }
+        prices
     }
 
     /// Remove listing from active listings
     fn remove_from_active_listings(env: &Env, listing_id: u64) {
-        let mut active_listings = Self::get_active_listings(env);
-        if let Some(index) = active_listings.first_index_of(listing_id) {
-            active_listings.remove(index);
-            env.storage()
-                .instance()
-                .set(&DataKey::ActiveListings, &active_listings);
+        Self::index_remove(env, &PagedIndex::Active, listing_id);
+    }
+
+    /// Append a listing to the active index
+    fn add_to_active_listings(env: &Env, listing_id: u64) {
+        Self::index_push(env, &PagedIndex::Active, listing_id);
+    }
+
+    fn load_active_page(env: &Env, page: u32) -> Vec<u64> {
+        Self::load_index_page(env, &PagedIndex::Active, page)
+    }
+
+    /// Remove an id from a paged index. The last id in the index is moved
+    /// into the freed position, so removal touches at most two pages.
+    fn index_remove(env: &Env, index: &PagedIndex, id: u64) {
+        let position: u32 = match env.storage().persistent().get(&index.position_key(id)) {
+            Some(position) => position,
+            None => return,
+        };
+        env.storage().persistent().remove(&index.position_key(id));
+
+        let count = Self::index_count(env, index);
+        let last = count - 1;
+        let mut last_page = Self::load_index_page(env, index, last / ACTIVE_PAGE_SIZE);
+        let moved = last_page.pop_back().unwrap();
+
+        if position != last {
+            if position / ACTIVE_PAGE_SIZE == last / ACTIVE_PAGE_SIZE {
+                last_page.set(position % ACTIVE_PAGE_SIZE, moved);
+            } else {
+                let mut page = Self::load_index_page(env, index, position / ACTIVE_PAGE_SIZE);
+                page.set(position % ACTIVE_PAGE_SIZE, moved);
+                Self::set_persistent(env, &index.page_key(position / ACTIVE_PAGE_SIZE), &page);
+            }
+            Self::set_persistent(env, &index.position_key(moved), &position);
+        }
+
+        if last_page.is_empty() {
+            env.storage().persistent().remove(&index.page_key(last / ACTIVE_PAGE_SIZE));
+        } else {
+            Self::set_persistent(env, &index.page_key(last / ACTIVE_PAGE_SIZE), &last_page);
         }
+        Self::set_persistent(env, &index.count_key(), &last);
+    }
+
+    /// Append an id to a paged index
+    fn index_push(env: &Env, index: &PagedIndex, id: u64) {
+        let count = Self::index_count(env, index);
+        let mut page = Self::load_index_page(env, index, count / ACTIVE_PAGE_SIZE);
+        page.push_back(id);
+
+        Self::set_persistent(env, &index.page_key(count / ACTIVE_PAGE_SIZE), &page);
+        Self::set_persistent(env, &index.position_key(id), &count);
+        Self::set_persistent(env, &index.count_key(), &(count + 1));
+    }
+
+    fn index_count(env: &Env, index: &PagedIndex) -> u32 {
+        env.storage().persistent().get(&index.count_key()).unwrap_or(0)
+    }
+
+    fn load_index_page(env: &Env, index: &PagedIndex, page: u32) -> Vec<u64> {
+        env.storage()
+            .persistent()
+            .get(&index.page_key(page))
+            .unwrap_or(Vec::new(env))
+    }
+
+    /// Ids at positions `offset..offset + limit` of a paged index
+    fn index_range(env: &Env, index: &PagedIndex, offset: u32, limit: u32) -> Vec<u64> {
+        let end = offset.saturating_add(limit).min(Self::index_count(env, index));
+
+        let mut ids = Vec::new(env);
+        let mut position = offset;
+        while position < end {
+            let page = Self::load_index_page(env, index, position / ACTIVE_PAGE_SIZE);
+            let page_end = (position / ACTIVE_PAGE_SIZE + 1) * ACTIVE_PAGE_SIZE;
+            for i in position..end.min(page_end) {
+                ids.push_back(page.get(i % ACTIVE_PAGE_SIZE).unwrap());
+            }
+            position = end.min(page_end);
+        }
+        ids
     }
 
     /// Refund all offers on a listing
     fn refund_all_offers(env: &Env, listing_id: u64) {
-        let offers = Self::get_offers_by_listing(env, listing_id);
+        let offers = Self::get_offers_by_listing(env.clone(), listing_id);
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(listing_id))
             .expect("Listing not found");
 
         let token_client = token::Client::new(env, &listing.payment_token);
 
         for offer_id in offers.iter() {
-            if let Some(mut offer) = env.storage().instance().get::<DataKey, Offer>(&DataKey::Offer(offer_id)) {
+            if let Some(mut offer) = env.storage().persistent().get::<DataKey, Offer>(&DataKey::Offer(offer_id)) {
                 if offer.status == OfferStatus::Open {
                     token_client.transfer(&env.current_contract_address(), &offer.buyer, &offer.price);
                     offer.status = OfferStatus::Cancelled;
-                    env.storage()
-                        .instance()
-                        .set(&DataKey::Offer(offer_id), &offer);
+                    Self::set_persistent(env, &DataKey::Offer(offer_id), &offer);
                 }
             }
         }
@@ -915,10 +1672,10 @@ impl MarketplaceContract {
 
     /// Refund other offers (except the accepted one)
     fn refund_other_offers(env: &Env, listing_id: u64, accepted_offer_id: u64) {
-        let offers = Self::get_offers_by_listing(env, listing_id);
+        let offers = Self::get_offers_by_listing(env.clone(), listing_id);
         let listing: Listing = env
             .storage()
-            .instance()
+            .persistent()
             .get(&DataKey::Listing(listing_id))
             .expect("Listing not found");
 
@@ -926,13 +1683,11 @@ impl MarketplaceContract {
 
         for offer_id in offers.iter() {
             if offer_id != accepted_offer_id {
-                if let Some(mut offer) = env.storage().instance().get::<DataKey, Offer>(&DataKey::Offer(offer_id)) {
+                if let Some(mut offer) = env.storage().persistent().get::<DataKey, Offer>(&DataKey::Offer(offer_id)) {
                     if offer.status == OfferStatus::Open {
                         token_client.transfer(&env.current_contract_address(), &offer.buyer, &offer.price);
                         offer.status = OfferStatus::Cancelled;
-                        env.storage()
-                            .instance()
-                            .set(&DataKey::Offer(offer_id), &offer);
+                        Self::set_persistent(env, &DataKey::Offer(offer_id), &offer);
                     }
                 }
             }
@@ -945,108 +1700,171 @@ impl MarketplaceContract {
 
     /// Get listing details
     pub fn get_listing(env: Env, listing_id: u64) -> Option<Listing> {
-        env.storage().instance().get(&DataKey::Listing(listing_id))
+        env.storage().persistent().get(&DataKey::Listing(listing_id))
     }
 
     /// Get offer details
     pub fn get_offer(env: Env, offer_id: u64) -> Option<Offer> {
-        env.storage().instance().get(&DataKey::Offer(offer_id))
+        env.storage().persistent().get(&DataKey::Offer(offer_id))
     }
 
     /// Get counter offer details
     pub fn get_counter_offer(env: Env, counter_offer_id: u64) -> Option<CounterOffer> {
-        env.storage().instance().get(&DataKey::CounterOffer(counter_offer_id))
+        env.storage().persistent().get(&DataKey::CounterOffer(counter_offer_id))
     }
 
     /// Get all listings by seller
-    pub fn get_listings_by_seller(env: &Env, seller: &Address) -> Vec<u64> {
+    pub fn get_listings_by_seller(env: Env, seller: Address) -> Vec<u64> {
         env.storage()
-            .instance()
-            .get(&DataKey::ListingsBySeller(seller.clone()))
-            .unwrap_or(Vec::new(env))
+            .persistent()
+            .get(&DataKey::ListingsBySeller(seller))
+            .unwrap_or(Vec::new(&env))
     }
 
     /// Get all listings for an asset
-    pub fn get_listings_by_asset(env: &Env, contract: &Address, token_id: &u32) -> Vec<u64> {
+    pub fn get_listings_by_asset(env: Env, contract: Address, token_id: u32) -> Vec<u64> {
         env.storage()
-            .instance()
-            .get(&DataKey::ListingsByAsset(contract.clone(), *token_id))
-            .unwrap_or(Vec::new(env))
+            .persistent()
+            .get(&DataKey::ListingsByAsset(contract, token_id))
+            .unwrap_or(Vec::new(&env))
     }
 
-    /// Get all active listings
-    pub fn get_active_listings(env: &Env) -> Vec<u64> {
-        env.storage()
-            .instance()
-            .get(&DataKey::ActiveListings)
-            .unwrap_or(Vec::new(env))
+    /// Get a page of active listings. Order is not stable: removing a
+    /// listing moves the last one into its place.
+    pub fn get_active_listings(env: Env, offset: u32, limit: u32) -> Vec<u64> {
+        Self::index_range(&env, &PagedIndex::Active, offset, limit)
+    }
+
+    /// Get the number of active listings
+    pub fn get_active_listing_count(env: Env) -> u32 {
+        Self::index_count(&env, &PagedIndex::Active)
+    }
+
+    /// Get the asking price of a listing after any scheduled decay, in its price unit
+    pub fn get_current_price(env: Env, listing_id: u64) -> i128 {
+        let listing: Listing = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Listing(listing_id))
+            .expect("Listing not found");
+        Self::current_price(&env, &listing)
     }
 
     /// Get all offers for a listing
-    pub fn get_offers_by_listing(env: &Env, listing_id: u64) -> Vec<u64> {
+    pub fn get_offers_by_listing(env: Env, listing_id: u64) -> Vec<u64> {
         env.storage()
-            .instance()
+            .persistent()
             .get(&DataKey::OffersByListing(listing_id))
-            .unwrap_or(Vec::new(env))
+            .unwrap_or(Vec::new(&env))
     }
 
     /// Get all counter offers for an offer
-    pub fn get_counter_offers_by_offer(env: &Env, offer_id: u64) -> Vec<u64> {
+    pub fn get_counter_offers_by_offer(env: Env, offer_id: u64) -> Vec<u64> {
         env.storage()
-            .instance()
+            .persistent()
             .get(&DataKey::CounterOffersByOffer(offer_id))
-            .unwrap_or(Vec::new(env))
+            .unwrap_or(Vec::new(&env))
     }
 
-    /// Get price history for an asset
-    pub fn get_price_history(env: Env, contract: Address, token_id: u32) -> Vec<i128> {
-        env.storage()
-            .instance()
-            .get(&DataKey::PriceHistory(contract, token_id))
-            .unwrap_or(Vec::new(&env))
+    /// Get collection offer details
+    pub fn get_collection_offer(env: Env, offer_id: u64) -> Option<CollectionOffer> {
+        env.storage().persistent().get(&DataKey::CollectionOffer(offer_id))
+    }
+
+    /// Get a page of the open collection offers for an NFT contract. Order
+    /// is not stable: closing an offer moves the last one into its place.
+    pub fn get_collection_offers(env: Env, nft_contract: Address, offset: u32, limit: u32) -> Vec<u64> {
+        Self::index_range(&env, &PagedIndex::ContractOffers(nft_contract), offset, limit)
+    }
+
+    /// Get the number of open collection offers for an NFT contract
+    pub fn get_collection_offer_count(env: Env, nft_contract: Address) -> u32 {
+        Self::index_count(&env, &PagedIndex::ContractOffers(nft_contract))
+    }
+
+    /// Get retained sales for an asset, oldest first
+    pub fn get_price_history(env: Env, contract: Address, token_id: u32) -> Vec<SaleRecord> {
+        Self::sale_history(&env, &contract, Some(token_id))
+    }
+
+    /// Get retained sales across a collection, oldest first
+    pub fn get_collection_history(env: Env, contract: Address) -> Vec<SaleRecord> {
+        Self::sale_history(&env, &contract, None)
     }
 
-    /// Get average price from history
+    /// Get average price per unit from history
     pub fn get_average_price(env: Env, contract: Address, token_id: u32) -> Option<i128> {
         let history = Self::get_price_history(env.clone(), contract, token_id);
         if history.is_empty() {
             return None;
         }
 
-        let sum: i128 = history.iter().fold(0i128, |acc, &price| acc + price);
+        let sum: i128 = history.iter().map(|sale| sale.price / sale.quantity as i128).sum();
         Some(sum / history.len() as i128)
     }
 
-    /// Get minimum price from history
+    /// Get minimum price per unit from history
     pub fn get_min_price(env: Env, contract: Address, token_id: u32) -> Option<i128> {
         let history = Self::get_price_history(env.clone(), contract, token_id);
-        if history.is_empty() {
-            return None;
-        }
+        history.iter().map(|sale| sale.price / sale.quantity as i128).min()
+    }
+
+    /// Get maximum price per unit from history
+    pub fn get_max_price(env: Env, contract: Address, token_id: u32) -> Option<i128> {
+        let history = Self::get_price_history(env.clone(), contract, token_id);
+        history.iter().map(|sale| sale.price / sale.quantity as i128).max()
+    }
 
-        let mut min = history.get(0).unwrap();
-        for price in history.iter() {
-            if price < min {
-                min = price;
+    /// Volume-weighted average price per asset over the last `window` seconds,
+    /// for one token or, with no `token_id`, the whole collection
+    pub fn get_vwap(env: Env, contract: Address, token_id: Option<u32>, window: u64) -> Option<i128> {
+        let since = env.ledger().timestamp().saturating_sub(window);
+        let mut value = 0i128;
+        let mut volume = 0i128;
+        for sale in Self::sale_history(&env, &contract, token_id).iter() {
+            if sale.timestamp >= since {
+                value += sale.price;
+                volume += sale.quantity as i128;
             }
         }
-        Some(*min)
+
+        if volume == 0 {
+            return None;
+        }
+        Some(value / volume)
     }
 
-    /// Get maximum price from history
-    pub fn get_max_price(env: Env, contract: Address, token_id: u32) -> Option<i128> {
-        let history = Self::get_price_history(env.clone(), contract, token_id);
-        if history.is_empty() {
+    /// Median price per asset over the last `window` seconds
+    pub fn get_median_price(env: Env, contract: Address, token_id: Option<u32>, window: u64) -> Option<i128> {
+        let prices = Self::unit_prices_in_window(&env, &contract, token_id, window);
+        if prices.is_empty() {
             return None;
         }
 
-        let mut max = history.get(0).unwrap();
-        for price in history.iter() {
-            if price > max {
-                max = price;
+        // Insertion sort; the history is bounded by HISTORY_CAPACITY
+        let mut sorted: Vec<i128> = Vec::new(&env);
+        for price in prices.iter() {
+            let mut pos = sorted.len();
+            for (i, other) in sorted.iter().enumerate() {
+                if price < other {
+                    pos = i as u32;
+                    break;
+                }
             }
+            sorted.insert(pos, price);
+        }
+
+        let mid = sorted.len() / 2;
+        if sorted.len() % 2 == 1 {
+            sorted.get(mid)
+        } else {
+            Some((sorted.get(mid - 1).unwrap() + sorted.get(mid).unwrap()) / 2)
         }
-        Some(*max)
+    }
+
+    /// Lowest price per asset over the last `window` seconds
+    pub fn get_floor_price(env: Env, contract: Address, token_id: Option<u32>, window: u64) -> Option<i128> {
+        Self::unit_prices_in_window(&env, &contract, token_id, window).iter().min()
     }
 
     /// Get marketplace configuration
@@ -2,19 +2,79 @@
 
 use super::*;
 use soroban_sdk::{
+    contract, contractimpl,
     testutils::{Address as _, Ledger},
-    token, Address, Env, symbol_short,
+    token, vec, Address, Env, String, Symbol,
 };
 
+// Minimal NFT contract that tracks ownership
+#[contract]
+pub struct MockNft;
+
+#[contractimpl]
+impl MockNft {
+    pub fn mint(env: Env, to: Address, token_id: u32) {
+        env.storage().persistent().set(&token_id, &to);
+    }
+
+    pub fn owner_of(env: Env, token_id: u32) -> Address {
+        env.storage().persistent().get(&token_id).unwrap()
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, token_id: u32) {
+        if Self::owner_of(env.clone(), token_id) != from {
+            panic!("Not the owner");
+        }
+        env.storage().persistent().set(&token_id, &to);
+    }
+
+    pub fn set_achievement(env: Env, token_id: u32, puzzle_id: u32, metadata: String) {
+        let achievement = Achievement {
+            owner: Self::owner_of(env.clone(), token_id),
+            puzzle_id,
+            metadata,
+            timestamp: 0,
+        };
+        env.storage().persistent().set(&(0u32, token_id), &achievement);
+    }
+
+    pub fn get_achievement(env: Env, token_id: u32) -> Option<Achievement> {
+        env.storage().persistent().get(&(0u32, token_id))
+    }
+}
+
+// Oracle returning whatever price was last set for a feed
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, feed: Symbol, price: i128) {
+        let data = PriceData {
+            price,
+            timestamp: env.ledger().timestamp(),
+            round_id: 1,
+        };
+        env.storage().instance().set(&feed, &data);
+    }
+
+    pub fn get_price(env: Env, feed: Symbol) -> PriceData {
+        env.storage().instance().get(&feed).unwrap()
+    }
+}
+
 // Helper function to create a mock NFT contract
 fn create_mock_nft(env: &Env) -> Address {
-    // For testing, we'll use a simple mock that tracks ownership
-    // In a real scenario, this would be the actual NFT contract
-    Address::generate(env)
+    env.register_contract(None, MockNft)
+}
+
+// Helper function to mint a mock NFT
+fn mint_nft(env: &Env, nft_contract: &Address, owner: &Address, token_id: u32) {
+    MockNftClient::new(env, nft_contract).mint(owner, &token_id);
 }
 
 // Helper function to create a test asset
-fn create_test_asset(env: &Env, nft_contract: Address, token_id: u32) -> Asset {
+fn create_test_asset(_env: &Env, nft_contract: Address, token_id: u32) -> Asset {
     Asset {
         asset_type: AssetType::NFT,
         contract: nft_contract,
@@ -36,7 +96,7 @@ fn test_initialize() {
         &fee_recipient,
         &250, // 2.5% fee
         &3600, // 1 hour min
-        &86400 * 30, // 30 days max
+        &(86400 * 30), // 30 days max
     );
 
     let config = client.get_config();
@@ -53,8 +113,6 @@ fn test_create_listing() {
     // Setup token
     let token_admin = Address::generate(&env);
     let token_contract_id = env.register_stellar_asset_contract_v2(token_admin.clone()).address();
-    let token_client = token::Client::new(&env, &token_contract_id);
-    let token_admin_client = token::StellarAssetClient::new(&env, &token_contract_id);
 
     // Setup marketplace
     let contract_id = env.register_contract(None, MarketplaceContract);
@@ -62,7 +120,7 @@ fn test_create_listing() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup seller and NFT
     let seller = Address::generate(&env);
@@ -71,6 +129,7 @@ fn test_create_listing() {
 
     // Create listing
     let asset = create_test_asset(&env, nft_contract.clone(), token_id);
+    mint_nft(&env, &nft_contract, &seller, token_id);
     let creator = Some(Address::generate(&env));
     let listing_id = client.create_listing(
         &seller,
@@ -91,11 +150,11 @@ fn test_create_listing() {
 
     // Verify listing appears in seller's listings
     let seller_listings = client.get_listings_by_seller(&seller);
-    assert!(seller_listings.contains(&listing_id));
+    assert!(seller_listings.contains(listing_id));
 
     // Verify listing appears in active listings
-    let active_listings = client.get_active_listings();
-    assert!(active_listings.contains(&listing_id));
+    let active_listings = client.get_active_listings(&0, &100);
+    assert!(active_listings.contains(listing_id));
 }
 
 #[test]
@@ -115,7 +174,7 @@ fn test_buy_listing() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -128,6 +187,7 @@ fn test_buy_listing() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -161,7 +221,7 @@ fn test_buy_listing() {
     // Verify price history
     let history = client.get_price_history(&nft_contract, &1u32);
     assert_eq!(history.len(), 1);
-    assert_eq!(history.get(0).unwrap(), &1000);
+    assert_eq!(history.get(0).unwrap().price, 1000);
 }
 
 #[test]
@@ -181,7 +241,7 @@ fn test_create_and_accept_offer() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -194,6 +254,7 @@ fn test_create_and_accept_offer() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -257,7 +318,7 @@ fn test_counter_offer() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -269,6 +330,7 @@ fn test_counter_offer() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -320,7 +382,7 @@ fn test_cancel_listing() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -332,6 +394,7 @@ fn test_cancel_listing() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -382,7 +445,7 @@ fn test_cancel_offer() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -394,6 +457,7 @@ fn test_cancel_offer() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -439,7 +503,7 @@ fn test_reject_offer() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -451,6 +515,7 @@ fn test_reject_offer() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -491,7 +556,7 @@ fn test_price_discovery() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller1 = Address::generate(&env);
@@ -507,6 +572,7 @@ fn test_price_discovery() {
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
 
     // Create and sell first listing
+    mint_nft(&env, &nft_contract, &seller1, 1);
     let listing_id1 = client.create_listing(
         &seller1,
         &asset,
@@ -518,6 +584,7 @@ fn test_price_discovery() {
     client.buy(&buyer1, &listing_id1);
 
     // Create and sell second listing (different price)
+    mint_nft(&env, &nft_contract, &seller2, 1);
     let listing_id2 = client.create_listing(
         &seller2,
         &asset,
@@ -531,8 +598,8 @@ fn test_price_discovery() {
     // Check price history
     let history = client.get_price_history(&nft_contract, &1u32);
     assert_eq!(history.len(), 2);
-    assert_eq!(history.get(0).unwrap(), &1000);
-    assert_eq!(history.get(1).unwrap(), &1500);
+    assert_eq!(history.get(0).unwrap().price, 1000);
+    assert_eq!(history.get(1).unwrap().price, 1500);
 
     // Check average price
     let avg_price = client.get_average_price(&nft_contract, &1u32).unwrap();
@@ -564,7 +631,7 @@ fn test_multiple_offers_refund() {
 
     let admin = Address::generate(&env);
     let fee_recipient = Address::generate(&env);
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Setup users
     let seller = Address::generate(&env);
@@ -580,6 +647,7 @@ fn test_multiple_offers_refund() {
     // Create listing
     let nft_contract = create_mock_nft(&env);
     let asset = create_test_asset(&env, nft_contract.clone(), 1u32);
+    mint_nft(&env, &nft_contract, &seller, 1);
     let listing_id = client.create_listing(
         &seller,
         &asset,
@@ -628,7 +696,7 @@ fn test_update_config() {
     let fee_recipient = Address::generate(&env);
     let new_fee_recipient = Address::generate(&env);
 
-    client.initialize(&admin, &fee_recipient, &250, &3600, &86400 * 30);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
 
     // Update config
     client.update_config(
@@ -643,3 +711,641 @@ fn test_update_config() {
     assert_eq!(config.fee_recipient, new_fee_recipient);
     assert_eq!(config.fee_bps, 300);
 }
+
+fn setup_marketplace(env: &Env) -> (MarketplaceContractClient<'_>, Address, Address) {
+    let token_admin = Address::generate(env);
+    let token_contract_id = env.register_stellar_asset_contract_v2(token_admin).address();
+
+    let contract_id = env.register_contract(None, MarketplaceContract);
+    let client = MarketplaceContractClient::new(env, &contract_id);
+
+    let admin = Address::generate(env);
+    let fee_recipient = Address::generate(env);
+    client.initialize(&admin, &fee_recipient, &250, &3600, &(86400 * 30));
+
+    (client, token_contract_id, fee_recipient)
+}
+
+fn bundle_item(env: &Env, nft_contract: &Address, token_id: u32, creator: Option<Address>, royalty_bps: u32) -> BundleItem {
+    BundleItem {
+        asset: create_test_asset(env, nft_contract.clone(), token_id),
+        creator,
+        royalty_bps,
+    }
+}
+
+#[test]
+fn test_bundle_listing_splits_royalties() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, fee_recipient) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let creator1 = Address::generate(&env);
+    let creator2 = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    let hints = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &seller, 1);
+    mint_nft(&env, &nfts, &seller, 2);
+    mint_nft(&env, &hints, &seller, 7);
+
+    let mut items = Vec::new(&env);
+    items.push_back(bundle_item(&env, &nfts, 1, Some(creator1.clone()), 500));
+    items.push_back(bundle_item(&env, &nfts, 2, Some(creator2.clone()), 1000));
+    items.push_back(bundle_item(&env, &hints, 7, None, 0));
+    let listing_id = client.create_bundle_listing(&seller, &items, &token_contract_id, &3000);
+
+    // Every asset is escrowed and indexed
+    let nft_client = MockNftClient::new(&env, &nfts);
+    assert_eq!(nft_client.owner_of(&2), client.address);
+    assert!(client.get_listings_by_asset(&hints, &7).contains(listing_id));
+
+    client.buy(&buyer, &listing_id);
+
+    // Fee: 3000 * 250 / 10000 = 75
+    // Each asset's share is 1000: royalties 50 and 100
+    // Seller gets: 3000 - 75 - 150 = 2775
+    assert_eq!(token_client.balance(&fee_recipient), 75);
+    assert_eq!(token_client.balance(&creator1), 50);
+    assert_eq!(token_client.balance(&creator2), 100);
+    assert_eq!(token_client.balance(&seller), 2775);
+    assert_eq!(token_client.balance(&buyer), 7000);
+
+    assert_eq!(nft_client.owner_of(&1), buyer);
+    assert_eq!(nft_client.owner_of(&2), buyer);
+    assert_eq!(MockNftClient::new(&env, &hints).owner_of(&7), buyer);
+    assert_eq!(client.get_listing(&listing_id).unwrap().status, ListingStatus::Sold);
+
+    // Each asset's share is recorded under its own contract
+    assert_eq!(client.get_price_history(&hints, &7).get(0).unwrap().price, 1000);
+    assert_eq!(client.get_collection_history(&hints).len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Fee and royalty cannot exceed 100%")]
+fn test_listing_rejects_fee_plus_royalty_over_price() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let creator = Address::generate(&env);
+    let nfts = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &seller, 1);
+
+    // 2.5% fee plus a 98% royalty would pay out more than the price
+    client.create_listing(
+        &seller,
+        &create_test_asset(&env, nfts, 1),
+        &token_contract_id,
+        &1000,
+        &Some(creator),
+        &9800,
+    );
+}
+
+#[test]
+fn test_bundle_royalty_is_checked_at_its_average_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let creator = Address::generate(&env);
+    let nfts = create_mock_nft(&env);
+    let mut items = Vec::new(&env);
+    for token_id in 1..=3 {
+        mint_nft(&env, &nfts, &seller, token_id);
+        items.push_back(bundle_item(&env, &nfts, token_id, Some(creator.clone()), 4000));
+    }
+
+    // Three 40% royalties on a third of the price each, plus the 2.5% fee
+    let listing_id = client.create_bundle_listing(&seller, &items, &token_contract_id, &3000);
+    assert_eq!(client.get_listing(&listing_id).unwrap().status, ListingStatus::Active);
+}
+
+#[test]
+fn test_cancel_bundle_listing_returns_assets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let nfts = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &seller, 1);
+    mint_nft(&env, &nfts, &seller, 2);
+
+    let mut items = Vec::new(&env);
+    items.push_back(bundle_item(&env, &nfts, 1, None, 0));
+    items.push_back(bundle_item(&env, &nfts, 2, None, 0));
+    let listing_id = client.create_bundle_listing(&seller, &items, &token_contract_id, &500);
+
+    client.cancel_listing(&seller, &listing_id);
+
+    let nft_client = MockNftClient::new(&env, &nfts);
+    assert_eq!(nft_client.owner_of(&1), seller);
+    assert_eq!(nft_client.owner_of(&2), seller);
+}
+
+#[test]
+fn test_buy_many() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    let mut listing_ids = Vec::new(&env);
+    for token_id in 1..=3u32 {
+        mint_nft(&env, &nfts, &seller, token_id);
+        let asset = create_test_asset(&env, nfts.clone(), token_id);
+        listing_ids.push_back(client.create_listing(&seller, &asset, &token_contract_id, &1000, &None, &0));
+    }
+
+    client.buy_many(&buyer, &listing_ids);
+
+    assert_eq!(token_client.balance(&buyer), 7000);
+    for listing_id in listing_ids.iter() {
+        assert_eq!(client.get_listing(&listing_id).unwrap().status, ListingStatus::Sold);
+    }
+    assert_eq!(MockNftClient::new(&env, &nfts).owner_of(&3), buyer);
+    assert_eq!(client.get_active_listings(&0, &100).len(), 0);
+}
+
+#[test]
+fn test_buy_many_reverts_if_any_listing_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&other_buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &seller, 1);
+    mint_nft(&env, &nfts, &seller, 2);
+    let first = client.create_listing(&seller, &create_test_asset(&env, nfts.clone(), 1), &token_contract_id, &1000, &None, &0);
+    let second = client.create_listing(&seller, &create_test_asset(&env, nfts.clone(), 2), &token_contract_id, &1000, &None, &0);
+
+    // The second listing sells before the cart is checked out
+    client.buy(&other_buyer, &second);
+
+    let mut listing_ids = Vec::new(&env);
+    listing_ids.push_back(first);
+    listing_ids.push_back(second);
+    assert!(client.try_buy_many(&buyer, &listing_ids).is_err());
+
+    // Nothing from the failed cart went through
+    assert_eq!(token_client.balance(&buyer), 10000);
+    assert_eq!(client.get_listing(&first).unwrap().status, ListingStatus::Active);
+    assert_eq!(MockNftClient::new(&env, &nfts).owner_of(&1), client.address);
+}
+
+#[test]
+fn test_collection_offer_accepted_by_holders() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, fee_recipient) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let buyer = Address::generate(&env);
+    let holder1 = Address::generate(&env);
+    let holder2 = Address::generate(&env);
+    let creator = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &holder1, 1);
+    mint_nft(&env, &nfts, &holder2, 2);
+
+    // Floor bid: any two tokens at 1000 each
+    let offer_id = client.create_collection_offer(
+        &buyer,
+        &OfferTarget::Collection(nfts.clone()),
+        &token_contract_id,
+        &1000,
+        &2,
+        &None,
+        &Some(creator.clone()),
+        &500,
+    );
+    assert_eq!(token_client.balance(&buyer), 8000);
+    assert!(client.get_collection_offers(&nfts, &0, &10).contains(offer_id));
+
+    client.accept_collection_offer(&holder1, &offer_id, &1);
+
+    // Fee: 25, royalty: 50, holder gets 925
+    assert_eq!(token_client.balance(&holder1), 925);
+    assert_eq!(token_client.balance(&creator), 50);
+    assert_eq!(token_client.balance(&fee_recipient), 25);
+    assert_eq!(MockNftClient::new(&env, &nfts).owner_of(&1), buyer);
+    assert_eq!(client.get_collection_offer(&offer_id).unwrap().quantity, 1);
+
+    client.accept_collection_offer(&holder2, &offer_id, &2);
+    assert_eq!(MockNftClient::new(&env, &nfts).owner_of(&2), buyer);
+    assert_eq!(token_client.balance(&holder2), 925);
+    assert_eq!(token_client.balance(&creator), 100);
+
+    // Filled offers leave the contract's index
+    let offer = client.get_collection_offer(&offer_id).unwrap();
+    assert_eq!(offer.status, OfferStatus::Accepted);
+    assert_eq!(client.get_collection_offer_count(&nfts), 0);
+    assert_eq!(token_client.balance(&client.address), 0);
+    assert_eq!(client.get_price_history(&nfts, &2).get(0).unwrap().price, 1000);
+}
+
+#[test]
+fn test_trait_offer_checks_achievement_metadata() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let buyer = Address::generate(&env);
+    let holder = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    let nft_client = MockNftClient::new(&env, &nfts);
+    mint_nft(&env, &nfts, &holder, 1);
+    mint_nft(&env, &nfts, &holder, 2);
+    nft_client.set_achievement(&1, &3, &String::from_str(&env, "gold"));
+    nft_client.set_achievement(&2, &7, &String::from_str(&env, "gold"));
+
+    let filter = TraitFilter {
+        puzzle_id: Some(7),
+        metadata: Some(String::from_str(&env, "gold")),
+    };
+    let offer_id = client.create_collection_offer(
+        &buyer,
+        &OfferTarget::Traits(nfts.clone(), filter),
+        &token_contract_id,
+        &500,
+        &1,
+        &None,
+        &None,
+        &0,
+    );
+
+    // Wrong puzzle
+    assert!(client.try_accept_collection_offer(&holder, &offer_id, &1).is_err());
+
+    client.accept_collection_offer(&holder, &offer_id, &2);
+    assert_eq!(nft_client.owner_of(&2), buyer);
+    assert_eq!(nft_client.owner_of(&1), holder);
+}
+
+#[test]
+fn test_collection_offer_expires_and_refunds() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let buyer = Address::generate(&env);
+    let holder = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &holder, 1);
+
+    let offer_id = client.create_collection_offer(
+        &buyer,
+        &OfferTarget::Collection(nfts.clone()),
+        &token_contract_id,
+        &1000,
+        &3,
+        &Some(5000),
+        &None,
+        &0,
+    );
+    assert_eq!(token_client.balance(&buyer), 7000);
+
+    env.ledger().set_timestamp(5001);
+    assert!(client.try_accept_collection_offer(&holder, &offer_id, &1).is_err());
+
+    client.cancel_collection_offer(&buyer, &offer_id);
+    assert_eq!(token_client.balance(&buyer), 10000);
+    assert_eq!(
+        client.get_collection_offer(&offer_id).unwrap().status,
+        OfferStatus::Cancelled
+    );
+    assert_eq!(client.get_collection_offer_count(&nfts), 0);
+}
+
+#[test]
+fn test_collection_offer_index_is_paged() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+    let nfts = create_mock_nft(&env);
+
+    let mut ids = Vec::new(&env);
+    for _ in 0..3 {
+        ids.push_back(client.create_collection_offer(
+            &buyer,
+            &OfferTarget::Collection(nfts.clone()),
+            &token_contract_id,
+            &1,
+            &1,
+            &None,
+            &None,
+            &0,
+        ));
+    }
+    assert_eq!(client.get_collection_offer_count(&nfts), 3);
+    assert_eq!(client.get_collection_offers(&nfts, &1, &5), vec![&env, ids.get(1).unwrap(), ids.get(2).unwrap()]);
+
+    // Cancelling moves the last offer into the freed position
+    client.cancel_collection_offer(&buyer, &ids.get(0).unwrap());
+    assert_eq!(client.get_collection_offers(&nfts, &0, &5), vec![&env, ids.get(2).unwrap(), ids.get(1).unwrap()]);
+}
+
+#[test]
+#[should_panic(expected = "Asset not owned by seller")]
+fn test_collection_offer_requires_current_holder() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let buyer = Address::generate(&env);
+    let holder = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    mint_nft(&env, &nfts, &holder, 1);
+
+    let offer_id = client.create_collection_offer(
+        &buyer,
+        &OfferTarget::Collection(nfts.clone()),
+        &token_contract_id,
+        &1000,
+        &1,
+        &None,
+        &None,
+        &0,
+    );
+    client.accept_collection_offer(&Address::generate(&env), &offer_id, &1);
+}
+
+fn list_nft(env: &Env, client: &MarketplaceContractClient, nfts: &Address, seller: &Address, token_id: u32, payment_token: &Address, price: i128) -> u64 {
+    mint_nft(env, nfts, seller, token_id);
+    let asset = create_test_asset(env, nfts.clone(), token_id);
+    client.create_listing(seller, &asset, payment_token, &price, &None, &0)
+}
+
+#[test]
+fn test_linear_price_decay() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(10000);
+    let (client, token_contract_id, fee_recipient) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    let listing_id = list_nft(&env, &client, &nfts, &seller, 1, &token_contract_id, 1000);
+
+    // Falls from 1000 to 400 over 1000 seconds
+    client.schedule_listing(&seller, &listing_id, &None, &PriceDecay::Linear(400, 11000));
+    assert_eq!(client.get_current_price(&listing_id), 1000);
+
+    env.ledger().set_timestamp(10500);
+    assert_eq!(client.get_current_price(&listing_id), 700);
+
+    client.buy(&buyer, &listing_id);
+    assert_eq!(token_client.balance(&buyer), 9300);
+    assert_eq!(token_client.balance(&fee_recipient), 17);
+    assert_eq!(token_client.balance(&seller), 683);
+    assert_eq!(client.get_price_history(&nfts, &1).get(0).unwrap().price, 700);
+}
+
+#[test]
+fn test_step_price_decay() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let nfts = create_mock_nft(&env);
+    let listing_id = list_nft(&env, &client, &nfts, &seller, 1, &token_contract_id, 1000);
+
+    // Drops 100 every hour down to 500
+    client.schedule_listing(&seller, &listing_id, &None, &PriceDecay::Step(500, 100, 3600));
+
+    env.ledger().set_timestamp(3599);
+    assert_eq!(client.get_current_price(&listing_id), 1000);
+    env.ledger().set_timestamp(7200);
+    assert_eq!(client.get_current_price(&listing_id), 800);
+    env.ledger().set_timestamp(86400);
+    assert_eq!(client.get_current_price(&listing_id), 500);
+}
+
+#[test]
+fn test_expired_listing_is_swept() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    let nfts = create_mock_nft(&env);
+    let expiring = list_nft(&env, &client, &nfts, &seller, 1, &token_contract_id, 1000);
+    let open_ended = list_nft(&env, &client, &nfts, &seller, 2, &token_contract_id, 1000);
+    client.schedule_listing(&seller, &expiring, &Some(7200), &PriceDecay::None);
+    client.create_offer(&buyer, &expiring, &800, &None);
+
+    // Nothing to sweep yet
+    assert_eq!(client.sweep_expired(&10), 0);
+
+    env.ledger().set_timestamp(7201);
+    assert!(client.try_buy(&buyer, &expiring).is_err());
+
+    assert_eq!(client.sweep_expired(&10), 1);
+    assert_eq!(client.get_listing(&expiring).unwrap().status, ListingStatus::Expired);
+    assert_eq!(MockNftClient::new(&env, &nfts).owner_of(&1), seller);
+    assert_eq!(token_client.balance(&buyer), 10000);
+
+    let active = client.get_active_listings(&0, &10);
+    assert_eq!(active.len(), 1);
+    assert_eq!(active.get(0).unwrap(), open_ended);
+}
+
+#[test]
+#[should_panic(expected = "Listing duration out of range")]
+fn test_schedule_listing_respects_max_duration() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let nfts = create_mock_nft(&env);
+    let listing_id = list_nft(&env, &client, &nfts, &seller, 1, &token_contract_id, 1000);
+    client.schedule_listing(&seller, &listing_id, &Some(86400 * 31), &PriceDecay::None);
+}
+
+#[test]
+fn test_active_listings_index_spans_pages() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.budget().reset_unlimited();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let nfts = create_mock_nft(&env);
+    for token_id in 1..=150u32 {
+        list_nft(&env, &client, &nfts, &seller, token_id, &token_contract_id, 1000);
+    }
+    assert_eq!(client.get_active_listing_count(), 150);
+
+    // Removing from the first page pulls the last listing across the page boundary
+    client.cancel_listing(&seller, &3);
+    assert_eq!(client.get_active_listing_count(), 149);
+    assert_eq!(client.get_active_listings(&2, &1).get(0).unwrap(), 150);
+
+    let mut seen = 0;
+    let mut offset = 0;
+    loop {
+        let page = client.get_active_listings(&offset, &40);
+        if page.is_empty() {
+            break;
+        }
+        for listing_id in page.iter() {
+            assert_ne!(listing_id, 3);
+            seen += 1;
+        }
+        offset += 40;
+    }
+    assert_eq!(seen, 149);
+}
+
+#[test]
+fn test_oracle_priced_listing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+    let token_client = token::Client::new(&env, &token_contract_id);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &10000);
+
+    // One payment token is worth 2 reference units
+    let oracle_id = env.register_contract(None, MockOracle);
+    let oracle = MockOracleClient::new(&env, &oracle_id);
+    let feed = Symbol::new(&env, "TOKEN");
+    oracle.set_price(&feed, &20_000_000);
+
+    // Priced at 1000 reference units
+    let nfts = create_mock_nft(&env);
+    let listing_id = list_nft(&env, &client, &nfts, &seller, 1, &token_contract_id, 1000);
+    client.set_price_unit(&seller, &listing_id, &PriceUnit::Reference(oracle_id.clone(), feed.clone()));
+
+    // A buyer must bound what they pay
+    assert!(client.try_buy(&buyer, &listing_id).is_err());
+
+    // The token halves in value before the buyer's limit is reached
+    oracle.set_price(&feed, &10_000_000);
+    assert!(client.try_buy_with_limit(&buyer, &listing_id, &600).is_err());
+
+    oracle.set_price(&feed, &20_000_000);
+    client.buy_with_limit(&buyer, &listing_id, &600);
+    assert_eq!(token_client.balance(&buyer), 9500);
+    assert_eq!(client.get_price_history(&nfts, &1).get(0).unwrap().price, 500);
+}
+
+#[test]
+fn test_windowed_price_analytics() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.budget().reset_unlimited();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &100000);
+    let nfts = create_mock_nft(&env);
+
+    env.ledger().set_timestamp(1000);
+    let id = list_nft(&env, &client, &nfts, &seller, 1, &token_contract_id, 1000);
+    client.buy(&buyer, &id);
+
+    env.ledger().set_timestamp(2000);
+    let id = list_nft(&env, &client, &nfts, &seller, 2, &token_contract_id, 400);
+    client.buy(&buyer, &id);
+
+    // A two-asset bundle at 1000 is 500 per asset
+    env.ledger().set_timestamp(3000);
+    mint_nft(&env, &nfts, &seller, 3);
+    mint_nft(&env, &nfts, &seller, 4);
+    let mut items = Vec::new(&env);
+    items.push_back(bundle_item(&env, &nfts, 3, None, 0));
+    items.push_back(bundle_item(&env, &nfts, 4, None, 0));
+    let id = client.create_bundle_listing(&seller, &items, &token_contract_id, &1000);
+    client.buy(&buyer, &id);
+
+    let id = list_nft(&env, &client, &nfts, &seller, 5, &token_contract_id, 700);
+    client.buy(&buyer, &id);
+
+    // The bundle is recorded once per asset
+    assert_eq!(client.get_collection_history(&nfts).len(), 5);
+    assert_eq!(client.get_price_history(&nfts, &4).get(0).unwrap().price, 500);
+    assert_eq!(client.get_average_price(&nfts, &3), Some(500));
+
+    // Last 1500 seconds: 400, 500 x2, 700
+    assert_eq!(client.get_vwap(&nfts, &None, &1500), Some(525));
+    assert_eq!(client.get_median_price(&nfts, &None, &1500), Some(500));
+    assert_eq!(client.get_floor_price(&nfts, &None, &1500), Some(400));
+
+    // Everything: 1000, 400, 500 x2, 700
+    assert_eq!(client.get_vwap(&nfts, &None, &5000), Some(620));
+    assert_eq!(client.get_median_price(&nfts, &None, &5000), Some(500));
+
+    // Single token, and an empty window
+    assert_eq!(client.get_vwap(&nfts, &Some(1), &5000), Some(1000));
+    assert_eq!(client.get_floor_price(&nfts, &Some(1), &1500), None);
+}
+
+#[test]
+fn test_price_history_ring_buffer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.budget().reset_unlimited();
+    let (client, token_contract_id, _) = setup_marketplace(&env);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_contract_id).mint(&buyer, &1_000_000);
+    let nfts = create_mock_nft(&env);
+
+    for token_id in 1..=105u32 {
+        let id = list_nft(&env, &client, &nfts, &seller, token_id, &token_contract_id, token_id as i128);
+        client.buy(&buyer, &id);
+    }
+
+    // Only the last 100 sales are kept, oldest first
+    let history = client.get_collection_history(&nfts);
+    assert_eq!(history.len(), 100);
+    assert_eq!(history.get(0).unwrap().token_id, 6);
+    assert_eq!(history.get(99).unwrap().price, 105);
+}
@@ -0,0 +1,87 @@
+use soroban_sdk::{Address, Env, Vec};
+
+use crate::types::{DataKey, Match};
+
+/// Builds a single-elimination bracket sized to the next power of two.
+/// Seed `i` (registration order) meets seed `size - 1 - i`, so the top
+/// seeds receive the byes, which are resolved straight away.
+pub fn seed(e: &Env, id: u32, participants: &Vec<Address>) {
+    let n = participants.len();
+    let size = n.next_power_of_two();
+    e.storage().persistent().set(&DataKey::BracketSize(id), &size);
+
+    // Create every match up front so the full tree is queryable
+    let mut offset = 0;
+    let mut round = 0;
+    let mut matches_in_round = size / 2;
+    while matches_in_round > 0 {
+        for i in 0..matches_in_round {
+            let m = Match {
+                id: offset + i,
+                round,
+                p1: None,
+                p2: None,
+                winner: None,
+            };
+            e.storage().persistent().set(&DataKey::Match(id, m.id), &m);
+        }
+        offset += matches_in_round;
+        round += 1;
+        matches_in_round /= 2;
+    }
+
+    for i in 0..size / 2 {
+        let p1 = participants.get(i);
+        let p2 = participants.get(size - 1 - i);
+
+        let mut m: Match = e.storage().persistent().get(&DataKey::Match(id, i)).unwrap();
+        m.p1 = p1.clone();
+        m.p2 = p2.clone();
+
+        if p2.is_none() {
+            // Bye: p1 advances without playing
+            m.winner = p1.clone();
+            e.storage().persistent().set(&DataKey::Match(id, i), &m);
+            advance(e, id, size, i, p1.unwrap());
+        } else {
+            e.storage().persistent().set(&DataKey::Match(id, i), &m);
+        }
+    }
+}
+
+/// Advances the winner of a reported match. Returns the final placings
+/// (champion, runner-up) once the final is decided.
+pub fn on_result(e: &Env, id: u32, m: &Match, winner: &Address, loser: &Address) -> Option<Vec<Address>> {
+    let size: u32 = e.storage().persistent().get(&DataKey::BracketSize(id)).unwrap();
+    if m.id == size - 2 {
+        let mut places = Vec::new(e);
+        places.push_back(winner.clone());
+        places.push_back(loser.clone());
+        return Some(places);
+    }
+
+    advance(e, id, size, m.id, winner.clone());
+    None
+}
+
+/// Full bracket in match id order, first round through the final
+pub fn get(e: &Env, id: u32) -> Vec<Match> {
+    let mut bracket = Vec::new(e);
+    let size: u32 = e.storage().persistent().get(&DataKey::BracketSize(id)).unwrap_or(0);
+    for match_id in 0..size.saturating_sub(1) {
+        bracket.push_back(e.storage().persistent().get(&DataKey::Match(id, match_id)).unwrap());
+    }
+    bracket
+}
+
+/// Places the winner of `match_id` into its slot in the next round
+fn advance(e: &Env, id: u32, size: u32, match_id: u32, winner: Address) {
+    let next_id = size / 2 + match_id / 2;
+    let mut next: Match = e.storage().persistent().get(&DataKey::Match(id, next_id)).unwrap();
+    if match_id & 1 == 0 {
+        next.p1 = Some(winner);
+    } else {
+        next.p2 = Some(winner);
+    }
+    e.storage().persistent().set(&DataKey::Match(id, next_id), &next);
+}
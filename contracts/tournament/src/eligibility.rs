@@ -0,0 +1,63 @@
+use soroban_sdk::{Address, Env, IntoVal, Symbol, Val, Vec};
+
+use crate::types::{EligibilityRule, PlayerRating, SkillTier};
+
+/// Checks every rule on the tournament against its source contract and
+/// panics on the first one the player fails.
+pub fn check(e: &Env, rules: &Vec<EligibilityRule>, player: &Address) {
+    for rule in rules.iter() {
+        match rule {
+            EligibilityRule::Rating(contract, min, max) => {
+                let rating: PlayerRating = call(e, &contract, "get_rating", player, None);
+                if rating.rating < min || rating.rating > max {
+                    panic!("Rating out of range");
+                }
+            }
+            EligibilityRule::Tier(contract, min, max) => {
+                let (tier, _): (SkillTier, u32) = call(e, &contract, "get_tier", player, None);
+                if tier < min || tier > max {
+                    panic!("Tier out of range");
+                }
+            }
+            EligibilityRule::Whitelist(contract, required_tier) => {
+                let whitelisted: bool = call(
+                    e,
+                    &contract,
+                    "is_whitelisted",
+                    player,
+                    Some(required_tier.into_val(e)),
+                );
+                if !whitelisted {
+                    panic!("Not whitelisted");
+                }
+            }
+            EligibilityRule::Reputation(contract, min_score) => {
+                let score: u32 = call(e, &contract, "calculate_score", player, None);
+                if score < min_score {
+                    panic!("Reputation too low");
+                }
+            }
+            EligibilityRule::Subscription(contract) => {
+                let active: bool = call(e, &contract, "has_active_subscription", player, None);
+                if !active {
+                    panic!("No active subscription");
+                }
+            }
+        }
+    }
+}
+
+fn call<T: soroban_sdk::TryFromVal<Env, Val>>(
+    e: &Env,
+    contract: &Address,
+    func: &str,
+    player: &Address,
+    extra: Option<Val>,
+) -> T {
+    let mut args: Vec<Val> = Vec::new(e);
+    args.push_back(player.into_val(e));
+    if let Some(extra) = extra {
+        args.push_back(extra);
+    }
+    e.invoke_contract(contract, &Symbol::new(e, func), args)
+}
@@ -0,0 +1,280 @@
+use core::cmp::Ordering;
+use soroban_sdk::{Address, Env, Map, Vec};
+
+use crate::types::{DataKey, Match, Standing, TieBreaker, TournamentConfig, TournamentFormat};
+
+/// Candidate pairings the Swiss backtracking may try before giving up on a
+/// rematch-free round
+const MAX_PAIRING_STEPS: u32 = 1_000;
+
+/// Sets up standings and pairs the first round of a Swiss or round-robin
+/// tournament.
+pub fn start(e: &Env, id: u32, participants: &Vec<Address>, config: &TournamentConfig) {
+    let n = participants.len();
+    let total_rounds = match config.format {
+        TournamentFormat::Swiss(rounds) => {
+            if rounds == 0 || rounds >= n {
+                panic!("Invalid number of rounds");
+            }
+            rounds
+        }
+        // Everyone meets everyone once; odd fields add a bye seat
+        _ => n + n % 2 - 1,
+    };
+
+    for player in participants.iter() {
+        let standing = Standing {
+            player: player.clone(),
+            points: 0,
+            losses: 0,
+            buchholz: 0,
+            had_bye: false,
+            opponents: Vec::new(e),
+            beaten: Vec::new(e),
+        };
+        e.storage().persistent().set(&DataKey::Standing(id, player), &standing);
+    }
+
+    e.storage().persistent().set(&DataKey::TotalRounds(id), &total_rounds);
+    e.storage().persistent().set(&DataKey::MatchCount(id), &0u32);
+
+    pair_round(e, id, 0, participants, config);
+}
+
+/// Updates standings for a reported match. When that completes the round,
+/// either pairs the next one or, after the last round, returns the final
+/// standings order.
+pub fn on_result(
+    e: &Env,
+    id: u32,
+    m: &Match,
+    winner: &Address,
+    loser: &Address,
+    participants: &Vec<Address>,
+    config: &TournamentConfig,
+) -> Option<Vec<Address>> {
+    let mut w = load_standing(e, id, winner);
+    w.points += 1;
+    w.beaten.push_back(loser.clone());
+    e.storage().persistent().set(&DataKey::Standing(id, winner.clone()), &w);
+
+    let mut l = load_standing(e, id, loser);
+    l.losses += 1;
+    e.storage().persistent().set(&DataKey::Standing(id, loser.clone()), &l);
+
+    let round_done = round_matches(e, id, m.round).iter().all(|other| other.winner.is_some());
+    if !round_done {
+        return None;
+    }
+
+    let total_rounds: u32 = e.storage().persistent().get(&DataKey::TotalRounds(id)).unwrap();
+    if m.round + 1 == total_rounds {
+        let mut places = Vec::new(e);
+        for s in standings(e, id, participants, config.tie_breaker).iter() {
+            places.push_back(s.player);
+        }
+        return Some(places);
+    }
+
+    pair_round(e, id, m.round + 1, participants, config);
+    None
+}
+
+/// Standings ordered by points, then the tie-breaker, then seed
+pub fn standings(e: &Env, id: u32, participants: &Vec<Address>, tie_breaker: TieBreaker) -> Vec<Standing> {
+    let mut by_player: Map<Address, Standing> = Map::new(e);
+    for player in participants.iter() {
+        by_player.set(player.clone(), load_standing(e, id, &player));
+    }
+
+    let mut sorted: Vec<Standing> = Vec::new(e);
+    for player in participants.iter() {
+        let mut s = by_player.get(player).unwrap();
+        s.buchholz = s
+            .opponents
+            .iter()
+            .map(|o| by_player.get(o).map(|os| os.points).unwrap_or(0))
+            .sum();
+
+        // Insert after every entry ranked at or above this one, keeping ties in seed order
+        let mut pos = sorted.len();
+        for (i, other) in sorted.iter().enumerate() {
+            if compare(&s, &other, tie_breaker) == Ordering::Greater {
+                pos = i as u32;
+                break;
+            }
+        }
+        sorted.insert(pos, s);
+    }
+    sorted
+}
+
+pub fn round_matches(e: &Env, id: u32, round: u32) -> Vec<Match> {
+    let mut matches = Vec::new(e);
+    let ids: Vec<u32> = e
+        .storage()
+        .persistent()
+        .get(&DataKey::RoundMatches(id, round))
+        .unwrap_or(Vec::new(e));
+    for match_id in ids.iter() {
+        matches.push_back(e.storage().persistent().get(&DataKey::Match(id, match_id)).unwrap());
+    }
+    matches
+}
+
+fn compare(a: &Standing, b: &Standing, tie_breaker: TieBreaker) -> Ordering {
+    a.points.cmp(&b.points).then_with(|| match tie_breaker {
+        TieBreaker::Buchholz => a.buchholz.cmp(&b.buchholz),
+        TieBreaker::HeadToHead => {
+            if a.beaten.contains(&b.player) {
+                Ordering::Greater
+            } else if b.beaten.contains(&a.player) {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        }
+    })
+}
+
+fn pair_round(
+    e: &Env,
+    id: u32,
+    round: u32,
+    participants: &Vec<Address>,
+    config: &TournamentConfig,
+) {
+    let mut ids = Vec::new(e);
+    match config.format {
+        TournamentFormat::Swiss(_) => pair_swiss(e, id, round, participants, config.tie_breaker, &mut ids),
+        _ => pair_round_robin(e, id, round, participants, &mut ids),
+    }
+
+    e.storage().persistent().set(&DataKey::RoundMatches(id, round), &ids);
+    e.storage().persistent().set(&DataKey::CurrentRound(id), &round);
+}
+
+/// Circle method: seed 0 stays fixed while the others rotate one seat per
+/// round. Seats past the field size are byes.
+fn pair_round_robin(e: &Env, id: u32, round: u32, participants: &Vec<Address>, ids: &mut Vec<u32>) {
+    let n = participants.len();
+    let seats = n + n % 2;
+    let seat = |k: u32| if k == 0 { 0 } else { 1 + (k - 1 + round) % (seats - 1) };
+
+    for i in 0..seats / 2 {
+        let a = participants.get(seat(i));
+        let b = participants.get(seat(seats - 1 - i));
+        match (a, b) {
+            (Some(a), Some(b)) => ids.push_back(create_match(e, id, round, a, Some(b))),
+            (Some(p), None) | (None, Some(p)) => ids.push_back(create_match(e, id, round, p, None)),
+            (None, None) => {}
+        }
+    }
+}
+
+/// Pairs players top-down in standings order, each with the highest-ranked
+/// remaining player they haven't met, backtracking when that leaves the rest
+/// of the field unpairable. If no rematch-free pairing is found within
+/// `MAX_PAIRING_STEPS` candidates, falls back to plain top-down pairing.
+/// With an odd field the lowest-ranked player without a bye sits out for a
+/// point.
+fn pair_swiss(e: &Env, id: u32, round: u32, participants: &Vec<Address>, tie_breaker: TieBreaker, ids: &mut Vec<u32>) {
+    let mut pool = standings(e, id, participants, tie_breaker);
+
+    if pool.len() % 2 == 1 {
+        let mut bye_index = pool.len() - 1;
+        for i in (0..pool.len()).rev() {
+            if !pool.get(i).unwrap().had_bye {
+                bye_index = i;
+                break;
+            }
+        }
+        let bye = pool.get(bye_index).unwrap();
+        pool.remove(bye_index);
+        ids.push_back(create_match(e, id, round, bye.player, None));
+    }
+
+    let mut steps = MAX_PAIRING_STEPS;
+    let pairs = pair_without_rematches(e, &pool, &mut steps).unwrap_or_else(|| {
+        let mut pairs = Vec::new(e);
+        for i in (0..pool.len()).step_by(2) {
+            pairs.push_back((pool.get(i).unwrap().player, pool.get(i + 1).unwrap().player));
+        }
+        pairs
+    });
+
+    for (a, b) in pairs.iter() {
+        ids.push_back(create_match(e, id, round, a, Some(b)));
+    }
+}
+
+/// Backtracking search over pairings, spending one of `steps` per candidate
+/// pair tried and giving up once they run out
+fn pair_without_rematches(e: &Env, pool: &Vec<Standing>, steps: &mut u32) -> Option<Vec<(Address, Address)>> {
+    let a = match pool.first() {
+        Some(a) => a,
+        None => return Some(Vec::new(e)),
+    };
+
+    for i in 1..pool.len() {
+        let b = pool.get(i).unwrap();
+        if a.opponents.contains(&b.player) {
+            continue;
+        }
+
+        if *steps == 0 {
+            return None;
+        }
+        *steps -= 1;
+
+        let mut rest = pool.clone();
+        rest.remove(i);
+        rest.remove(0);
+        if let Some(mut pairs) = pair_without_rematches(e, &rest, steps) {
+            pairs.push_front((a.player.clone(), b.player));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// Stores a new match and records the pairing in both players' standings.
+/// A bye is resolved immediately and scores a point.
+fn create_match(e: &Env, tournament_id: u32, round: u32, p1: Address, p2: Option<Address>) -> u32 {
+    let id: u32 = e.storage().persistent().get(&DataKey::MatchCount(tournament_id)).unwrap();
+    e.storage().persistent().set(&DataKey::MatchCount(tournament_id), &(id + 1));
+
+    let mut s1 = load_standing(e, tournament_id, &p1);
+    let winner = match &p2 {
+        Some(p2) => {
+            let mut s2 = load_standing(e, tournament_id, p2);
+            s1.opponents.push_back(p2.clone());
+            s2.opponents.push_back(p1.clone());
+            e.storage().persistent().set(&DataKey::Standing(tournament_id, p2.clone()), &s2);
+            None
+        }
+        None => {
+            s1.points += 1;
+            s1.had_bye = true;
+            Some(p1.clone())
+        }
+    };
+    e.storage().persistent().set(&DataKey::Standing(tournament_id, p1.clone()), &s1);
+
+    let m = Match {
+        id,
+        round,
+        p1: Some(p1),
+        p2,
+        winner,
+    };
+    e.storage().persistent().set(&DataKey::Match(tournament_id, id), &m);
+    id
+}
+
+fn load_standing(e: &Env, id: u32, player: &Address) -> Standing {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Standing(id, player.clone()))
+        .unwrap()
+}
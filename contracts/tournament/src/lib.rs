@@ -1,146 +1,207 @@
 #![no_std]
 use soroban_sdk::{contract, contractimpl, token, Address, Env, Vec};
 
+mod bracket;
+mod eligibility;
+mod league;
 mod types;
 mod test;
-use types::{DataKey, TournamentConfig, TournamentState};
+use types::{
+    DataKey, Match, Standing, TournamentConfig, TournamentFormat, TournamentInfo, TournamentState,
+};
+
+const BPS_DENOMINATOR: u32 = 10_000;
+const STATE_PAGE_SIZE: u32 = 100; // Tournament ids per page of a state index
 
 #[contract]
 pub struct TournamentContract;
 
 #[contractimpl]
 impl TournamentContract {
-    pub fn initialize(e: Env, admin: Address, token: Address, entry_fee: i128) {
-        if e.storage().instance().has(&DataKey::Config) {
-            panic!("Already initialized");
-        }
-        let config = TournamentConfig {
-            admin,
-            token,
-            entry_fee,
-        };
-        e.storage().instance().set(&DataKey::Config, &config);
-        e.storage().instance().set(&DataKey::State, &TournamentState::Open);
-        e.storage().instance().set(&DataKey::TotalPrize, &0i128);
-        
+    /// Creates a tournament and returns its id. `config.admin` runs it.
+    /// `prize_split` gives each final place its share of `TotalPrize` in bps
+    /// and must sum to 10_000. Elimination brackets place a champion and a
+    /// runner-up only, so they accept at most two shares. `eligibility`
+    /// rules are checked against their contracts when a player registers.
+    pub fn create_tournament(e: Env, config: TournamentConfig) -> u32 {
+        config.admin.require_auth();
+
+        if config.prize_split.is_empty()
+            || config.prize_split.iter().sum::<u32>() != BPS_DENOMINATOR
+        {
+            panic!("Invalid prize split");
+        }
+        if config.format == TournamentFormat::SingleElimination && config.prize_split.len() > 2 {
+            panic!("Elimination pays at most two places");
+        }
+        if config.max_participants < 2 || config.max_participants < config.prize_split.len() {
+            panic!("Invalid max participants");
+        }
+        if config.registration_end <= config.registration_start {
+            panic!("Invalid registration window");
+        }
+
+        let id: u32 = e.storage().instance().get(&DataKey::TournamentCount).unwrap_or(0) + 1;
+        e.storage().instance().set(&DataKey::TournamentCount, &id);
+
+        e.storage().persistent().set(&DataKey::Config(id), &config);
+        e.storage().persistent().set(&DataKey::TotalPrize(id), &0i128);
+
         // Initialize empty participants list
         let participants: Vec<Address> = Vec::new(&e);
-        e.storage().instance().set(&DataKey::Participants, &participants);
+        e.storage().persistent().set(&DataKey::Participants(id), &participants);
+
+        Self::set_state(&e, id, TournamentState::Open);
+        id
     }
 
-    pub fn register(e: Env, player: Address) {
+    pub fn register(e: Env, tournament_id: u32, player: Address) {
         player.require_auth();
 
-        let state: TournamentState = e.storage().instance().get(&DataKey::State).unwrap();
+        let state = Self::get_state(e.clone(), tournament_id);
         if state != TournamentState::Open {
             panic!("Tournament not open for registration");
         }
 
-        let config: TournamentConfig = e.storage().instance().get(&DataKey::Config).unwrap();
-        
-        let mut participants: Vec<Address> = e.storage().instance().get(&DataKey::Participants).unwrap();
+        let config = Self::get_config(e.clone(), tournament_id);
+
+        let now = e.ledger().timestamp();
+        if now < config.registration_start || now > config.registration_end {
+            panic!("Registration closed");
+        }
+
+        let mut participants = Self::get_participants(e.clone(), tournament_id);
         if participants.contains(&player) {
             panic!("Already registered");
         }
+        if participants.len() >= config.max_participants {
+            panic!("Tournament full");
+        }
+
+        eligibility::check(&e, &config.eligibility, &player);
 
         // Transfer entry fee
         let client = token::Client::new(&e, &config.token);
         client.transfer(&player, &e.current_contract_address(), &config.entry_fee);
 
         // Update prize pool
-        let mut total_prize: i128 = e.storage().instance().get(&DataKey::TotalPrize).unwrap();
-        total_prize += config.entry_fee;
-        e.storage().instance().set(&DataKey::TotalPrize, &total_prize);
+        let total_prize = Self::get_prize_pool(e.clone(), tournament_id) + config.entry_fee;
+        e.storage().persistent().set(&DataKey::TotalPrize(tournament_id), &total_prize);
 
         // Add to participants
         participants.push_back(player);
-        e.storage().instance().set(&DataKey::Participants, &participants);
+        e.storage().persistent().set(&DataKey::Participants(tournament_id), &participants);
     }
 
-    pub fn start_tournament(e: Env) {
-        let config: TournamentConfig = e.storage().instance().get(&DataKey::Config).unwrap();
+    pub fn start_tournament(e: Env, tournament_id: u32) {
+        let config = Self::get_config(e.clone(), tournament_id);
         config.admin.require_auth();
 
-        let state: TournamentState = e.storage().instance().get(&DataKey::State).unwrap();
+        let state = Self::get_state(e.clone(), tournament_id);
         if state != TournamentState::Open {
             panic!("Tournament already started or ended");
         }
 
-        let participants: Vec<Address> = e.storage().instance().get(&DataKey::Participants).unwrap();
-        if participants.len() < 2 {
+        let participants = Self::get_participants(e.clone(), tournament_id);
+        if participants.len() < 2 || participants.len() < config.prize_split.len() {
             panic!("Not enough participants");
         }
 
-        e.storage().instance().set(&DataKey::State, &TournamentState::Started);
-        // Bracket generation logic would go here. 
-        // For simplicity in this iteration, we assume off-chain bracket management 
-        // or a simple linear matching handled by the admin via `record_result`.
+        Self::set_state(&e, tournament_id, TournamentState::Started);
+        match config.format {
+            TournamentFormat::SingleElimination => bracket::seed(&e, tournament_id, &participants),
+            _ => league::start(&e, tournament_id, &participants, &config),
+        }
     }
 
-    pub fn record_result(e: Env, winner: Address) { 
-        // Note: This is a simplified version where admin declares winners of matches/tournament directly
-        // In a full version, we'd pass match_id and validate against the bracket.
-        let config: TournamentConfig = e.storage().instance().get(&DataKey::Config).unwrap();
+    /// Records the winner of a match. Brackets advance the winner to the next
+    /// round; Swiss and round-robin pair the next round once every match in
+    /// the current one is in. The last result ends the tournament and pays
+    /// out `TotalPrize` according to the prize split.
+    pub fn report_match(e: Env, tournament_id: u32, match_id: u32, winner: Address) {
+        let config = Self::get_config(e.clone(), tournament_id);
         config.admin.require_auth();
 
-        let state: TournamentState = e.storage().instance().get(&DataKey::State).unwrap();
+        let state = Self::get_state(e.clone(), tournament_id);
         if state != TournamentState::Started {
             panic!("Tournament not in progress");
         }
-        
-        // Check if winner is a valid participant - simplified check
-        let participants: Vec<Address> = e.storage().instance().get(&DataKey::Participants).unwrap();
-        if !participants.contains(&winner) {
-             panic!("Winner is not a participant");
+
+        let mut m: Match = e
+            .storage()
+            .persistent()
+            .get(&DataKey::Match(tournament_id, match_id))
+            .unwrap_or_else(|| panic!("Match not found"));
+
+        if m.winner.is_some() {
+            panic!("Match already reported");
         }
-        
-        // For this MVP, let's assume `record_result` declares the FINAL tournament winner for simplicity
-        // or effectively distributes the prize.
-        
-        // We will move to Ended state and distribute prize
-        e.storage().instance().set(&DataKey::State, &TournamentState::Ended);
-        
-        let total_prize: i128 = e.storage().instance().get(&DataKey::TotalPrize).unwrap();
-        if total_prize > 0 {
-             let client = token::Client::new(&e, &config.token);
-             client.transfer(&e.current_contract_address(), &winner, &total_prize);
+
+        let (p1, p2) = match (&m.p1, &m.p2) {
+            (Some(p1), Some(p2)) => (p1.clone(), p2.clone()),
+            _ => panic!("Match not ready"),
+        };
+
+        if winner != p1 && winner != p2 {
+            panic!("Winner is not in this match");
+        }
+
+        let loser = if winner == p1 { p2 } else { p1 };
+
+        m.winner = Some(winner.clone());
+        e.storage().persistent().set(&DataKey::Match(tournament_id, match_id), &m);
+
+        let places = match config.format {
+            TournamentFormat::SingleElimination => {
+                bracket::on_result(&e, tournament_id, &m, &winner, &loser)
+            }
+            _ => {
+                let participants = Self::get_participants(e.clone(), tournament_id);
+                league::on_result(&e, tournament_id, &m, &winner, &loser, &participants, &config)
+            }
+        };
+
+        if let Some(places) = places {
+            // Last result in: end the tournament and distribute the prize
+            Self::set_state(&e, tournament_id, TournamentState::Ended);
+            Self::distribute_prizes(&e, tournament_id, &config, &places);
         }
     }
 
-    pub fn cancel_tournament(e: Env) {
-        let config: TournamentConfig = e.storage().instance().get(&DataKey::Config).unwrap();
+    pub fn cancel_tournament(e: Env, tournament_id: u32) {
+        let config = Self::get_config(e.clone(), tournament_id);
         config.admin.require_auth();
 
-        let state: TournamentState = e.storage().instance().get(&DataKey::State).unwrap();
-        if state == TournamentState::Ended {
+        let state = Self::get_state(e.clone(), tournament_id);
+        if state == TournamentState::Ended || state == TournamentState::Cancelled {
             panic!("Cannot cancel ended tournament");
         }
 
-        e.storage().instance().set(&DataKey::State, &TournamentState::Cancelled);
-        
+        Self::set_state(&e, tournament_id, TournamentState::Cancelled);
+
         // Allow refunds - in this model, we can iterate and refund or let users pull.
         // For gas efficiency, usually pull pattern is better, but loop is okay for small numbers.
         // Let's implement a 'withdraw_refund' function for users to call instead of auto-refunding loop to be safe.
     }
 
-    pub fn withdraw_refund(e: Env, player: Address) {
+    pub fn withdraw_refund(e: Env, tournament_id: u32, player: Address) {
         player.require_auth();
-        let state: TournamentState = e.storage().instance().get(&DataKey::State).unwrap();
+        let state = Self::get_state(e.clone(), tournament_id);
         if state != TournamentState::Cancelled {
             panic!("Tournament not cancelled");
         }
 
-        let participants: Vec<Address> = e.storage().instance().get(&DataKey::Participants).unwrap();
+        let participants = Self::get_participants(e.clone(), tournament_id);
         if !participants.contains(&player) {
             panic!("Not a participant");
         }
 
-        // Ideally we track if they already withdrew. 
+        // Ideally we track if they already withdrew.
         // Quick fix: Remove them from participants list after refund to prevent double refund.
         // Note: Vector removal by value is O(N), might be expensive for large lists.
         // Valid for MVP.
-        
-        let config: TournamentConfig = e.storage().instance().get(&DataKey::Config).unwrap();
+
+        let config = Self::get_config(e.clone(), tournament_id);
         let client = token::Client::new(&e, &config.token);
         client.transfer(&e.current_contract_address(), &player, &config.entry_fee);
 
@@ -151,19 +212,194 @@ impl TournamentContract {
                 new_participants.push_back(p);
             }
         }
-        e.storage().instance().set(&DataKey::Participants, &new_participants);
+        e.storage().persistent().set(&DataKey::Participants(tournament_id), &new_participants);
     }
-    
+
     // View functions
-    pub fn get_state(e: Env) -> TournamentState {
-        e.storage().instance().get(&DataKey::State).unwrap()
+    pub fn get_config(e: Env, tournament_id: u32) -> TournamentConfig {
+        e.storage()
+            .persistent()
+            .get(&DataKey::Config(tournament_id))
+            .unwrap_or_else(|| panic!("Tournament not found"))
     }
-    
-    pub fn get_participants(e: Env) -> Vec<Address> {
-        e.storage().instance().get(&DataKey::Participants).unwrap()
+
+    pub fn get_state(e: Env, tournament_id: u32) -> TournamentState {
+        e.storage()
+            .persistent()
+            .get(&DataKey::State(tournament_id))
+            .unwrap_or_else(|| panic!("Tournament not found"))
+    }
+
+    pub fn get_participants(e: Env, tournament_id: u32) -> Vec<Address> {
+        e.storage()
+            .persistent()
+            .get(&DataKey::Participants(tournament_id))
+            .unwrap_or(Vec::new(&e))
     }
-    
-    pub fn get_prize_pool(e: Env) -> i128 {
-        e.storage().instance().get(&DataKey::TotalPrize).unwrap_or(0)
+
+    pub fn get_prize_pool(e: Env, tournament_id: u32) -> i128 {
+        e.storage().persistent().get(&DataKey::TotalPrize(tournament_id)).unwrap_or(0)
+    }
+
+    pub fn get_tournament(e: Env, tournament_id: u32) -> TournamentInfo {
+        TournamentInfo {
+            id: tournament_id,
+            state: Self::get_state(e.clone(), tournament_id),
+            config: Self::get_config(e.clone(), tournament_id),
+            participant_count: Self::get_participants(e.clone(), tournament_id).len(),
+            prize_pool: Self::get_prize_pool(e, tournament_id),
+        }
+    }
+
+    /// Tournaments currently in `state`, for lobby pagination. Order is not
+    /// stable: a tournament leaving a state moves the last one into its place.
+    pub fn list_tournaments(e: Env, state: TournamentState, offset: u32, limit: u32) -> Vec<TournamentInfo> {
+        let count = Self::get_state_count(e.clone(), state.clone());
+        let end = offset.saturating_add(limit).min(count);
+
+        let mut result = Vec::new(&e);
+        let mut position = offset;
+        while position < end {
+            let page = Self::load_state_page(&e, &state, position / STATE_PAGE_SIZE);
+            let page_end = (position / STATE_PAGE_SIZE + 1) * STATE_PAGE_SIZE;
+            for i in position..end.min(page_end) {
+                let id = page.get(i % STATE_PAGE_SIZE).unwrap();
+                result.push_back(Self::get_tournament(e.clone(), id));
+            }
+            position = end.min(page_end);
+        }
+        result
+    }
+
+    /// Number of tournaments currently in `state`
+    pub fn get_state_count(e: Env, state: TournamentState) -> u32 {
+        e.storage().persistent().get(&DataKey::StateCount(state)).unwrap_or(0)
+    }
+
+    pub fn get_tournament_count(e: Env) -> u32 {
+        e.storage().instance().get(&DataKey::TournamentCount).unwrap_or(0)
+    }
+
+    pub fn get_match(e: Env, tournament_id: u32, match_id: u32) -> Option<Match> {
+        e.storage().persistent().get(&DataKey::Match(tournament_id, match_id))
+    }
+
+    /// Full bracket in match id order, first round through the final
+    pub fn get_bracket(e: Env, tournament_id: u32) -> Vec<Match> {
+        bracket::get(&e, tournament_id)
+    }
+
+    pub fn get_current_round(e: Env, tournament_id: u32) -> u32 {
+        e.storage().persistent().get(&DataKey::CurrentRound(tournament_id)).unwrap_or(0)
+    }
+
+    /// Swiss / round-robin pairings for a round
+    pub fn get_round_matches(e: Env, tournament_id: u32, round: u32) -> Vec<Match> {
+        league::round_matches(&e, tournament_id, round)
+    }
+
+    /// Swiss / round-robin standings table, best first
+    pub fn get_standings(e: Env, tournament_id: u32) -> Vec<Standing> {
+        let config = Self::get_config(e.clone(), tournament_id);
+        if config.format == TournamentFormat::SingleElimination {
+            return Vec::new(&e);
+        }
+        let participants = Self::get_participants(e.clone(), tournament_id);
+        league::standings(&e, tournament_id, &participants, config.tie_breaker)
+    }
+}
+
+impl TournamentContract {
+    /// Moves a tournament to `state`, keeping the per-state lobby index in sync
+    fn set_state(e: &Env, id: u32, state: TournamentState) {
+        if let Some(previous) = e.storage().persistent().get::<_, TournamentState>(&DataKey::State(id)) {
+            Self::remove_from_state_index(e, &previous, id);
+        }
+        Self::add_to_state_index(e, &state, id);
+
+        e.storage().persistent().set(&DataKey::State(id), &state);
+    }
+
+    /// Append a tournament to a state's paged index
+    fn add_to_state_index(e: &Env, state: &TournamentState, id: u32) {
+        let count: u32 = e.storage().persistent().get(&DataKey::StateCount(state.clone())).unwrap_or(0);
+        let mut page = Self::load_state_page(e, state, count / STATE_PAGE_SIZE);
+        page.push_back(id);
+
+        e.storage()
+            .persistent()
+            .set(&DataKey::StatePage(state.clone(), count / STATE_PAGE_SIZE), &page);
+        e.storage().persistent().set(&DataKey::StatePosition(id), &count);
+        e.storage().persistent().set(&DataKey::StateCount(state.clone()), &(count + 1));
+    }
+
+    /// Remove a tournament from a state's paged index. The last id in the
+    /// index is moved into the freed position, so removal touches at most two
+    /// pages.
+    fn remove_from_state_index(e: &Env, state: &TournamentState, id: u32) {
+        let position: u32 = match e.storage().persistent().get(&DataKey::StatePosition(id)) {
+            Some(position) => position,
+            None => return,
+        };
+        e.storage().persistent().remove(&DataKey::StatePosition(id));
+
+        let count: u32 = e.storage().persistent().get(&DataKey::StateCount(state.clone())).unwrap_or(0);
+        let last = count - 1;
+        let mut last_page = Self::load_state_page(e, state, last / STATE_PAGE_SIZE);
+        let moved = last_page.pop_back().unwrap();
+
+        if position != last {
+            if position / STATE_PAGE_SIZE == last / STATE_PAGE_SIZE {
+                last_page.set(position % STATE_PAGE_SIZE, moved);
+            } else {
+                let mut page = Self::load_state_page(e, state, position / STATE_PAGE_SIZE);
+                page.set(position % STATE_PAGE_SIZE, moved);
+                e.storage()
+                    .persistent()
+                    .set(&DataKey::StatePage(state.clone(), position / STATE_PAGE_SIZE), &page);
+            }
+            e.storage().persistent().set(&DataKey::StatePosition(moved), &position);
+        }
+
+        let last_key = DataKey::StatePage(state.clone(), last / STATE_PAGE_SIZE);
+        if last_page.is_empty() {
+            e.storage().persistent().remove(&last_key);
+        } else {
+            e.storage().persistent().set(&last_key, &last_page);
+        }
+        e.storage().persistent().set(&DataKey::StateCount(state.clone()), &last);
+    }
+
+    fn load_state_page(e: &Env, state: &TournamentState, page: u32) -> Vec<u32> {
+        e.storage()
+            .persistent()
+            .get(&DataKey::StatePage(state.clone(), page))
+            .unwrap_or(Vec::new(e))
+    }
+
+    /// Pays each place its share of the prize pool; rounding dust goes to
+    /// first place.
+    fn distribute_prizes(e: &Env, id: u32, config: &TournamentConfig, places: &Vec<Address>) {
+        let total_prize: i128 = e.storage().persistent().get(&DataKey::TotalPrize(id)).unwrap();
+        if total_prize <= 0 {
+            return;
+        }
+
+        let mut shares = Vec::new(e);
+        let mut paid = 0;
+        for bps in config.prize_split.iter() {
+            let share = total_prize * bps as i128 / BPS_DENOMINATOR as i128;
+            shares.push_back(share);
+            paid += share;
+        }
+        shares.set(0, shares.get(0).unwrap() + total_prize - paid);
+
+        let client = token::Client::new(e, &config.token);
+        for (i, share) in shares.iter().enumerate() {
+            if share > 0 {
+                let player = places.get(i as u32).unwrap();
+                client.transfer(&e.current_contract_address(), &player, &share);
+            }
+        }
     }
 }
@@ -1,7 +1,85 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, token, Address, Env};
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{Address as _, Ledger},
+    token, vec, Address, Env, Vec,
+};
+use types::{EligibilityRule, PlayerRating, SkillTier, TieBreaker, TournamentFormat};
+
+// Stand-ins for the SkillRating, WhitelistContract, ReputationContract and
+// SubscriptionContract entry points used by eligibility rules. Each stores a
+// per-player answer set by the test.
+
+#[contract]
+pub struct MockSkillRating;
+
+#[contractimpl]
+impl MockSkillRating {
+    pub fn set(env: Env, player: Address, rating: i32, tier: SkillTier) {
+        env.storage().persistent().set(&player, &(rating, tier));
+    }
+
+    pub fn get_rating(env: Env, player: Address) -> PlayerRating {
+        let (rating, _): (i32, SkillTier) = env.storage().persistent().get(&player).unwrap();
+        PlayerRating {
+            rating,
+            last_update_ts: 0,
+            season_id: 0,
+        }
+    }
+
+    pub fn get_tier(env: Env, player: Address) -> (SkillTier, u32) {
+        let (_, tier): (i32, SkillTier) = env.storage().persistent().get(&player).unwrap();
+        (tier, 0)
+    }
+}
+
+#[contract]
+pub struct MockWhitelist;
+
+#[contractimpl]
+impl MockWhitelist {
+    pub fn add(env: Env, player: Address, tier: u32) {
+        env.storage().persistent().set(&player, &tier);
+    }
+
+    pub fn is_whitelisted(env: Env, address: Address, required_tier: Option<u32>) -> bool {
+        match env.storage().persistent().get::<_, u32>(&address) {
+            Some(tier) => required_tier.is_none_or(|required| tier >= required),
+            None => false,
+        }
+    }
+}
+
+#[contract]
+pub struct MockReputation;
+
+#[contractimpl]
+impl MockReputation {
+    pub fn set_score(env: Env, player: Address, score: u32) {
+        env.storage().persistent().set(&player, &score);
+    }
+
+    pub fn calculate_score(env: Env, player: Address) -> u32 {
+        env.storage().persistent().get(&player).unwrap_or(0)
+    }
+}
+
+#[contract]
+pub struct MockSubscription;
+
+#[contractimpl]
+impl MockSubscription {
+    pub fn subscribe(env: Env, user: Address) {
+        env.storage().persistent().set(&user, &true);
+    }
+
+    pub fn has_active_subscription(env: Env, user: Address) -> bool {
+        env.storage().persistent().has(&user)
+    }
+}
 
 fn create_token_contract<'a>(e: &Env, admin: &Address) -> (token::Client<'a>, token::StellarAssetClient<'a>) {
     let contract_address = e.register_stellar_asset_contract_v2(admin.clone())
@@ -17,6 +95,36 @@ fn create_tournament_contract<'a>(e: &Env) -> TournamentContractClient<'a> {
     TournamentContractClient::new(e, &contract_id)
 }
 
+fn config(
+    admin: &Address,
+    token: &Address,
+    format: TournamentFormat,
+    tie_breaker: TieBreaker,
+    prize_split: Vec<u32>,
+) -> TournamentConfig {
+    let eligibility = Vec::new(prize_split.env());
+    TournamentConfig {
+        admin: admin.clone(),
+        token: token.clone(),
+        entry_fee: 100,
+        max_participants: 64,
+        registration_start: 0,
+        registration_end: 1_000,
+        format,
+        tie_breaker,
+        prize_split,
+        eligibility,
+    }
+}
+
+fn init_elimination(e: &Env, client: &TournamentContractClient, admin: &Address, token: &Address, first_place_bps: u32) -> u32 {
+    let mut split = vec![e, first_place_bps];
+    if first_place_bps < 10_000 {
+        split.push_back(10_000 - first_place_bps);
+    }
+    client.create_tournament(&config(admin, token, TournamentFormat::SingleElimination, TieBreaker::Buchholz, split))
+}
+
 #[test]
 fn test_tournament_flow() {
     let e = Env::default();
@@ -34,28 +142,27 @@ fn test_tournament_flow() {
     token_admin_client.mint(&user1, &1000);
     token_admin_client.mint(&user2, &1000);
 
-    // Initialize tournament
-    let entry_fee = 100;
-    tournament_client.initialize(&admin, &token_client.address, &entry_fee);
+    // Create tournament
+    let id = init_elimination(&e, &tournament_client, &admin, &token_client.address, 10_000);
 
     // Register users
-    tournament_client.register(&user1);
-    tournament_client.register(&user2);
+    tournament_client.register(&id, &user1);
+    tournament_client.register(&id, &user2);
 
     // Verify registrations
-    let participants = tournament_client.get_participants();
+    let participants = tournament_client.get_participants(&id);
     assert_eq!(participants.len(), 2);
-    assert_eq!(tournament_client.get_prize_pool(), 200);
+    assert_eq!(tournament_client.get_prize_pool(&id), 200);
 
     // Start tournament
-    tournament_client.start_tournament();
-    assert_eq!(tournament_client.get_state(), TournamentState::Started);
+    tournament_client.start_tournament(&id);
+    assert_eq!(tournament_client.get_state(&id), TournamentState::Started);
 
-    // Record result (User1 wins)
-    tournament_client.record_result(&user1);
+    // Two players: match 0 is the final (User1 wins)
+    tournament_client.report_match(&id, &0, &user1);
     
     // Verify changes
-    assert_eq!(tournament_client.get_state(), TournamentState::Ended);
+    assert_eq!(tournament_client.get_state(&id), TournamentState::Ended);
     // User1 should have 900 (remaining) + 200 (prize) = 1100
     assert_eq!(token_client.balance(&user1), 1100);
     // User2 should have 900
@@ -76,18 +183,536 @@ fn test_cancel_and_refund() {
 
     token_admin_client.mint(&user1, &1000);
 
-    tournament_client.initialize(&admin, &token_client.address, &100);
-    tournament_client.register(&user1);
+    let id = init_elimination(&e, &tournament_client, &admin, &token_client.address, 10_000);
+    tournament_client.register(&id, &user1);
 
-    tournament_client.cancel_tournament();
-    assert_eq!(tournament_client.get_state(), TournamentState::Cancelled);
+    tournament_client.cancel_tournament(&id);
+    assert_eq!(tournament_client.get_state(&id), TournamentState::Cancelled);
 
-    tournament_client.withdraw_refund(&user1);
+    tournament_client.withdraw_refund(&id, &user1);
     
     // User1 should be back to 1000
     assert_eq!(token_client.balance(&user1), 1000);
     
     // Participants list should be empty (or at least user1 removed)
-    let participants = tournament_client.get_participants();
+    let participants = tournament_client.get_participants(&id);
     assert!(!participants.contains(&user1));
 }
+
+#[test]
+fn test_bracket_with_byes() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+
+    let (token_client, token_admin_client) = create_token_contract(&e, &token_admin);
+    let tournament_client = create_tournament_contract(&e);
+    let id = init_elimination(&e, &tournament_client, &admin, &token_client.address, 7_000);
+
+    let mut players = Vec::new(&e);
+    for _ in 0..5 {
+        let player = Address::generate(&e);
+        token_admin_client.mint(&player, &100);
+        tournament_client.register(&id, &player);
+        players.push_back(player);
+    }
+    let p = |i: u32| players.get(i).unwrap();
+
+    tournament_client.start_tournament(&id);
+
+    // 5 players -> 8 slots: seeds 0-2 get byes, seeds 3 and 4 play
+    let bracket = tournament_client.get_bracket(&id);
+    assert_eq!(bracket.len(), 7);
+    assert_eq!(bracket.get(0).unwrap().winner, Some(p(0)));
+    assert_eq!(bracket.get(2).unwrap().p2, None);
+    assert_eq!(bracket.get(3).unwrap().p1, Some(p(3)));
+    assert_eq!(bracket.get(3).unwrap().p2, Some(p(4)));
+    assert_eq!(bracket.get(4).unwrap().p1, Some(p(0)));
+    assert_eq!(bracket.get(4).unwrap().p2, Some(p(1)));
+    assert_eq!(bracket.get(5).unwrap().p1, Some(p(2)));
+    assert_eq!(bracket.get(5).unwrap().p2, None);
+    assert_eq!(bracket.get(6).unwrap().round, 2);
+
+    tournament_client.report_match(&id, &3, &p(4));
+    assert_eq!(tournament_client.get_match(&id, &5).unwrap().p2, Some(p(4)));
+
+    tournament_client.report_match(&id, &4, &p(1));
+    tournament_client.report_match(&id, &5, &p(4));
+    assert_eq!(tournament_client.get_state(&id), TournamentState::Started);
+
+    tournament_client.report_match(&id, &6, &p(4));
+
+    let final_match = tournament_client.get_match(&id, &6).unwrap();
+    assert_eq!(final_match.p1, Some(p(1)));
+    assert_eq!(final_match.winner, Some(p(4)));
+    assert_eq!(tournament_client.get_state(&id), TournamentState::Ended);
+    // 70/30 split between champion and runner-up
+    assert_eq!(token_client.balance(&p(4)), 350);
+    assert_eq!(token_client.balance(&p(1)), 150);
+    assert!(tournament_client.get_bracket(&id).iter().all(|m| m.winner.is_some()));
+}
+
+#[test]
+#[should_panic(expected = "Match not ready")]
+fn test_report_match_not_ready() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+
+    let (token_client, token_admin_client) = create_token_contract(&e, &token_admin);
+    let tournament_client = create_tournament_contract(&e);
+    let id = init_elimination(&e, &tournament_client, &admin, &token_client.address, 10_000);
+
+    let mut players = Vec::new(&e);
+    for _ in 0..4 {
+        let player = Address::generate(&e);
+        token_admin_client.mint(&player, &100);
+        tournament_client.register(&id, &player);
+        players.push_back(player);
+    }
+    tournament_client.start_tournament(&id);
+
+    // The final can't be reported before the semi-finals
+    tournament_client.report_match(&id, &2, &players.get(0).unwrap());
+}
+
+#[test]
+#[should_panic(expected = "Winner is not in this match")]
+fn test_report_match_wrong_winner() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+
+    let (token_client, token_admin_client) = create_token_contract(&e, &token_admin);
+    let tournament_client = create_tournament_contract(&e);
+    let id = init_elimination(&e, &tournament_client, &admin, &token_client.address, 10_000);
+
+    let mut players = Vec::new(&e);
+    for _ in 0..4 {
+        let player = Address::generate(&e);
+        token_admin_client.mint(&player, &100);
+        tournament_client.register(&id, &player);
+        players.push_back(player);
+    }
+    tournament_client.start_tournament(&id);
+
+    // Match 0 is seed 0 vs seed 3
+    tournament_client.report_match(&id, &0, &players.get(1).unwrap());
+}
+
+/// Registers `n` funded players in a league tournament
+fn setup_league<'a>(
+    e: &Env,
+    n: u32,
+    format: TournamentFormat,
+    tie_breaker: TieBreaker,
+    prize_split: Vec<u32>,
+) -> (TournamentContractClient<'a>, token::Client<'a>, u32, Vec<Address>) {
+    let admin = Address::generate(e);
+    let token_admin = Address::generate(e);
+
+    let (token_client, token_admin_client) = create_token_contract(e, &token_admin);
+    let tournament_client = create_tournament_contract(e);
+    let id = tournament_client.create_tournament(&config(&admin, &token_client.address, format, tie_breaker, prize_split));
+
+    let mut players = Vec::new(e);
+    for _ in 0..n {
+        let player = Address::generate(e);
+        token_admin_client.mint(&player, &100);
+        tournament_client.register(&id, &player);
+        players.push_back(player);
+    }
+    (tournament_client, token_client, id, players)
+}
+
+/// Reports every open match in the current round, using `beats` to decide
+fn play_round(client: &TournamentContractClient, id: u32, beats: impl Fn(&Address, &Address) -> bool) {
+    for m in client.get_round_matches(&id, &client.get_current_round(&id)).iter() {
+        if m.winner.is_none() {
+            let (p1, p2) = (m.p1.unwrap(), m.p2.unwrap());
+            let winner = if beats(&p1, &p2) { p1 } else { p2 };
+            client.report_match(&id, &m.id, &winner);
+        }
+    }
+}
+
+#[test]
+fn test_round_robin() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (client, token_client, id, players) = setup_league(
+        &e,
+        4,
+        TournamentFormat::RoundRobin,
+        TieBreaker::HeadToHead,
+        vec![&e, 7_000, 3_000],
+    );
+    client.start_tournament(&id);
+
+    // Earlier seeds always win
+    let seed = |p: &Address| players.first_index_of(p).unwrap();
+    for round in 0..3 {
+        assert_eq!(client.get_current_round(&id), round);
+        assert_eq!(client.get_round_matches(&id, &round).len(), 2);
+        play_round(&client, id, |a, b| seed(a) < seed(b));
+    }
+
+    assert_eq!(client.get_state(&id), TournamentState::Ended);
+
+    let standings = client.get_standings(&id);
+    for (i, s) in standings.iter().enumerate() {
+        assert_eq!(s.player, players.get(i as u32).unwrap());
+        assert_eq!(s.points, 3 - i as u32);
+        // Everyone met everyone else exactly once
+        assert_eq!(s.opponents.len(), 3);
+        for other in players.iter() {
+            assert_eq!(s.opponents.contains(&other), other != s.player);
+        }
+    }
+
+    assert_eq!(token_client.balance(&players.get(0).unwrap()), 280);
+    assert_eq!(token_client.balance(&players.get(1).unwrap()), 120);
+    assert_eq!(token_client.balance(&players.get(2).unwrap()), 0);
+}
+
+#[test]
+fn test_round_robin_odd_field_gives_byes() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (client, _, id, players) = setup_league(
+        &e,
+        3,
+        TournamentFormat::RoundRobin,
+        TieBreaker::HeadToHead,
+        vec![&e, 10_000],
+    );
+    client.start_tournament(&id);
+
+    // A cycle: 0 beats 1, 1 beats 2, 2 beats 0
+    let seed = |p: &Address| players.first_index_of(p).unwrap();
+    for _ in 0..3 {
+        play_round(&client, id, |a, b| (seed(a) + 1) % 3 == seed(b));
+    }
+
+    assert_eq!(client.get_state(&id), TournamentState::Ended);
+    for s in client.get_standings(&id).iter() {
+        assert!(s.had_bye);
+        assert_eq!(s.points, 2);
+        assert_eq!(s.opponents.len(), 2);
+    }
+}
+
+#[test]
+fn test_swiss_pairs_by_score_without_rematches() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (client, token_client, id, players) = setup_league(
+        &e,
+        6,
+        TournamentFormat::Swiss(3),
+        TieBreaker::Buchholz,
+        vec![&e, 5_000, 3_000, 2_000],
+    );
+    client.start_tournament(&id);
+
+    let seed = |p: &Address| players.first_index_of(p).unwrap();
+    for round in 0..3 {
+        if round > 0 {
+            // Pairings follow the standings: leaders meet each other
+            let standings = client.get_standings(&id);
+            let first = client.get_round_matches(&id, &round).get(0).unwrap();
+            assert_eq!(first.p1, Some(standings.get(0).unwrap().player));
+        }
+        play_round(&client, id, |a, b| seed(a) < seed(b));
+    }
+
+    assert_eq!(client.get_state(&id), TournamentState::Ended);
+
+    let standings = client.get_standings(&id);
+    for s in standings.iter() {
+        let mut met = s.opponents.clone();
+        while let Some(o) = met.pop_front() {
+            assert!(!met.contains(&o), "rematch");
+        }
+    }
+
+    let top = standings.get(0).unwrap();
+    assert_eq!(top.player, players.get(0).unwrap());
+    assert_eq!(top.points, 3);
+    assert_eq!(token_client.balance(&top.player), 300);
+    assert_eq!(token_client.balance(&standings.get(1).unwrap().player), 180);
+    assert_eq!(token_client.balance(&standings.get(2).unwrap().player), 120);
+}
+
+#[test]
+#[should_panic(expected = "Invalid prize split")]
+fn test_invalid_prize_split() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    setup_league(&e, 0, TournamentFormat::RoundRobin, TieBreaker::Buchholz, vec![&e, 6_000, 3_000]);
+}
+
+#[test]
+fn test_concurrent_tournaments() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let other_admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+
+    let (token_client, token_admin_client) = create_token_contract(&e, &token_admin);
+    let client = create_tournament_contract(&e);
+
+    let cup = init_elimination(&e, &client, &admin, &token_client.address, 10_000);
+    let league = client.create_tournament(&config(
+        &other_admin,
+        &token_client.address,
+        TournamentFormat::RoundRobin,
+        TieBreaker::HeadToHead,
+        vec![&e, 10_000],
+    ));
+    let third = init_elimination(&e, &client, &admin, &token_client.address, 10_000);
+    assert_eq!((cup, league, third), (1, 2, 3));
+    assert_eq!(client.get_tournament_count(), 3);
+
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    token_admin_client.mint(&user1, &300);
+    token_admin_client.mint(&user2, &300);
+    for id in [cup, league] {
+        client.register(&id, &user1);
+        client.register(&id, &user2);
+    }
+
+    // Each tournament keeps its own participants, pool and lifecycle
+    client.start_tournament(&cup);
+    client.report_match(&cup, &0, &user2);
+    client.cancel_tournament(&third);
+
+    assert_eq!(client.get_state(&cup), TournamentState::Ended);
+    assert_eq!(client.get_state(&league), TournamentState::Open);
+    assert_eq!(client.get_participants(&league).len(), 2);
+    assert_eq!(client.get_prize_pool(&league), 200);
+    assert_eq!(token_client.balance(&user2), 300);
+
+    let open = client.list_tournaments(&TournamentState::Open, &0, &10);
+    assert_eq!(open.len(), 1);
+    assert_eq!(open.get(0).unwrap().id, league);
+    assert_eq!(open.get(0).unwrap().config.admin, other_admin);
+    assert_eq!(open.get(0).unwrap().participant_count, 2);
+
+    assert_eq!(client.list_tournaments(&TournamentState::Ended, &0, &10).get(0).unwrap().id, cup);
+    assert_eq!(client.list_tournaments(&TournamentState::Cancelled, &0, &10).get(0).unwrap().id, third);
+}
+
+#[test]
+fn test_list_tournaments_paginates() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+    let (token_client, _) = create_token_contract(&e, &token_admin);
+    let client = create_tournament_contract(&e);
+
+    for _ in 0..5 {
+        init_elimination(&e, &client, &admin, &token_client.address, 10_000);
+    }
+
+    let page = client.list_tournaments(&TournamentState::Open, &1, &2);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get(0).unwrap().id, 2);
+    assert_eq!(page.get(1).unwrap().id, 3);
+
+    assert_eq!(client.list_tournaments(&TournamentState::Open, &4, &10).len(), 1);
+    assert_eq!(client.list_tournaments(&TournamentState::Open, &5, &10).len(), 0);
+    assert_eq!(client.list_tournaments(&TournamentState::Started, &0, &10).len(), 0);
+
+    // Leaving a state moves the last tournament into the freed slot
+    client.cancel_tournament(&2);
+    let open = client.list_tournaments(&TournamentState::Open, &0, &10);
+    let mut ids = Vec::new(&e);
+    for t in open.iter() {
+        ids.push_back(t.id);
+    }
+    assert_eq!(ids, vec![&e, 1, 5, 3, 4]);
+    assert_eq!(client.get_state_count(&TournamentState::Open), 4);
+    assert_eq!(client.get_state_count(&TournamentState::Cancelled), 1);
+}
+
+#[test]
+#[should_panic(expected = "Registration closed")]
+fn test_register_after_window() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+    let (token_client, token_admin_client) = create_token_contract(&e, &token_admin);
+    let client = create_tournament_contract(&e);
+    let id = init_elimination(&e, &client, &admin, &token_client.address, 10_000);
+
+    let player = Address::generate(&e);
+    token_admin_client.mint(&player, &100);
+
+    e.ledger().with_mut(|l| l.timestamp = 1_001);
+    client.register(&id, &player);
+}
+
+#[test]
+#[should_panic(expected = "Tournament full")]
+fn test_register_when_full() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let token_admin = Address::generate(&e);
+    let (token_client, token_admin_client) = create_token_contract(&e, &token_admin);
+    let client = create_tournament_contract(&e);
+
+    let mut cfg = config(
+        &admin,
+        &token_client.address,
+        TournamentFormat::SingleElimination,
+        TieBreaker::Buchholz,
+        vec![&e, 10_000],
+    );
+    cfg.max_participants = 2;
+    let id = client.create_tournament(&cfg);
+
+    for _ in 0..3 {
+        let player = Address::generate(&e);
+        token_admin_client.mint(&player, &100);
+        client.register(&id, &player);
+    }
+}
+
+/// Creates an elimination tournament gated by `rule` and a funded player
+fn setup_gated<'a>(e: &Env, rule: EligibilityRule) -> (TournamentContractClient<'a>, u32, Address) {
+    let admin = Address::generate(e);
+    let token_admin = Address::generate(e);
+    let (token_client, token_admin_client) = create_token_contract(e, &token_admin);
+    let client = create_tournament_contract(e);
+
+    let mut cfg = config(
+        &admin,
+        &token_client.address,
+        TournamentFormat::SingleElimination,
+        TieBreaker::Buchholz,
+        vec![e, 10_000],
+    );
+    cfg.eligibility = vec![e, rule];
+    let id = client.create_tournament(&cfg);
+
+    let player = Address::generate(e);
+    token_admin_client.mint(&player, &100);
+    (client, id, player)
+}
+
+#[test]
+fn test_tier_gated_registration() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let skill_id = e.register_contract(None, MockSkillRating);
+    let skill = MockSkillRatingClient::new(&e, &skill_id);
+
+    // Bronze-only cup
+    let (client, id, bronze) = setup_gated(
+        &e,
+        EligibilityRule::Tier(skill_id.clone(), SkillTier::Bronze, SkillTier::Bronze),
+    );
+    skill.set(&bronze, &900, &SkillTier::Bronze);
+    client.register(&id, &bronze);
+    assert_eq!(client.get_participants(&id).len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Tier out of range")]
+fn test_tier_gated_rejects_higher_tier() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let skill_id = e.register_contract(None, MockSkillRating);
+    let skill = MockSkillRatingClient::new(&e, &skill_id);
+
+    let (client, id, player) = setup_gated(
+        &e,
+        EligibilityRule::Tier(skill_id.clone(), SkillTier::Bronze, SkillTier::Silver),
+    );
+    skill.set(&player, &1_600, &SkillTier::Gold);
+    client.register(&id, &player);
+}
+
+#[test]
+#[should_panic(expected = "Rating out of range")]
+fn test_rating_gated_rejects_low_rating() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let skill_id = e.register_contract(None, MockSkillRating);
+    let skill = MockSkillRatingClient::new(&e, &skill_id);
+
+    let (client, id, player) = setup_gated(&e, EligibilityRule::Rating(skill_id.clone(), 1_200, 1_800));
+    skill.set(&player, &1_100, &SkillTier::Silver);
+    client.register(&id, &player);
+}
+
+#[test]
+fn test_whitelist_gated_registration() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let whitelist_id = e.register_contract(None, MockWhitelist);
+    let whitelist = MockWhitelistClient::new(&e, &whitelist_id);
+
+    let (client, id, player) = setup_gated(&e, EligibilityRule::Whitelist(whitelist_id.clone(), Some(2)));
+    whitelist.add(&player, &1);
+    let res = client.try_register(&id, &player);
+    assert!(res.is_err());
+
+    whitelist.add(&player, &2);
+    client.register(&id, &player);
+    assert!(client.get_participants(&id).contains(&player));
+}
+
+#[test]
+#[should_panic(expected = "Reputation too low")]
+fn test_reputation_gated_rejects_low_score() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let reputation_id = e.register_contract(None, MockReputation);
+    let reputation = MockReputationClient::new(&e, &reputation_id);
+
+    let (client, id, player) = setup_gated(&e, EligibilityRule::Reputation(reputation_id.clone(), 50));
+    reputation.set_score(&player, &49);
+    client.register(&id, &player);
+}
+
+#[test]
+fn test_subscription_gated_registration() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let subscription_id = e.register_contract(None, MockSubscription);
+    let subscription = MockSubscriptionClient::new(&e, &subscription_id);
+
+    let (client, id, player) = setup_gated(&e, EligibilityRule::Subscription(subscription_id.clone()));
+    assert!(client.try_register(&id, &player).is_err());
+
+    subscription.subscribe(&player);
+    client.register(&id, &player);
+    assert_eq!(client.get_prize_pool(&id), 100);
+}
@@ -1,4 +1,4 @@
-use soroban_sdk::{contracttype, Address};
+use soroban_sdk::{contracttype, Address, Vec};
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -9,30 +9,127 @@ pub enum TournamentState {
     Cancelled,
 }
 
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TournamentFormat {
+    SingleElimination,
+    Swiss(u32), // number of rounds
+    RoundRobin,
+}
+
+/// Orders players level on points in Swiss and round-robin standings
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TieBreaker {
+    Buchholz,   // sum of opponents' points
+    HeadToHead, // result of the game between the tied players
+}
+
+/// Mirrors `skill_rating::Tier` so `get_tier` results decode here. Variants
+/// are declared lowest first, so the derived ordering follows the ladder.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum SkillTier {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+    Master,
+    Grandmaster,
+}
+
+/// Mirrors `skill_rating::PlayerRating`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlayerRating {
+    pub rating: i32,
+    pub last_update_ts: u64,
+    pub season_id: u64,
+}
+
+/// A registration requirement checked against another contract. The first
+/// field is always that contract's address.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EligibilityRule {
+    Rating(Address, i32, i32),             // SkillRating: min, max rating
+    Tier(Address, SkillTier, SkillTier),   // SkillRating: min, max tier
+    Whitelist(Address, Option<u32>),       // WhitelistContract: required tier
+    Reputation(Address, u32),              // ReputationContract: min score
+    Subscription(Address),                 // SubscriptionContract: active
+}
+
+/// Per-tournament settings, fixed at creation
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TournamentConfig {
     pub admin: Address,
     pub token: Address,
     pub entry_fee: i128,
+    pub max_participants: u32,
+    pub registration_start: u64,
+    pub registration_end: u64,
+    pub format: TournamentFormat,
+    pub tie_breaker: TieBreaker,
+    pub prize_split: Vec<u32>, // bps of TotalPrize per final place, 1st first
+    pub eligibility: Vec<EligibilityRule>, // all must pass to register
+}
+
+/// Lobby view of a tournament
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TournamentInfo {
+    pub id: u32,
+    pub state: TournamentState,
+    pub config: TournamentConfig,
+    pub participant_count: u32,
+    pub prize_pool: i128,
 }
 
 #[contracttype]
-#[allow(dead_code)]
 pub enum DataKey {
-    Config,
-    State,
-    Participants, // Vector<Address>
-    Match(u32),   // Map match_id to Match
-    Results,      // Map match_id to Winner Address
-    TotalPrize,
+    TournamentCount,
+    StatePage(TournamentState, u32), // Vec<u32> - one page of the ids currently in a state
+    StateCount(TournamentState),     // u32 - number of tournaments in a state
+    StatePosition(u32),              // u32 - a tournament's position in its state's index
+    Config(u32),
+    State(u32),
+    Participants(u32), // Vector<Address>
+    Match(u32, u32),   // Map (tournament_id, match_id) to Match
+    BracketSize(u32),  // Number of first-round slots (power of two)
+    TotalPrize(u32),
+    CurrentRound(u32), // Swiss / round-robin round in progress
+    TotalRounds(u32),
+    MatchCount(u32),
+    RoundMatches(u32, u32), // Match ids paired in a round
+    Standing(u32, Address),
 }
 
+/// A tournament match. In a single-elimination bracket of `size` slots,
+/// first-round matches are `0..size / 2` and the final is `size - 2`; a player
+/// is `None` until the feeding match resolves. Swiss and round-robin matches
+/// are numbered in the order they are paired. A match with no `p2` is a bye.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-#[allow(dead_code)]
 pub struct Match {
-    pub p1: Address,
-    pub p2: Address,
+    pub id: u32,
+    pub round: u32,
+    pub p1: Option<Address>,
+    pub p2: Option<Address>,
     pub winner: Option<Address>,
 }
+
+/// A player's record in a Swiss or round-robin tournament. A win or a bye is
+/// worth one point; `buchholz` is filled in when standings are queried.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Standing {
+    pub player: Address,
+    pub points: u32,
+    pub losses: u32,
+    pub buchholz: u32,
+    pub had_bye: bool,
+    pub opponents: Vec<Address>,
+    pub beaten: Vec<Address>,
+}